@@ -0,0 +1,637 @@
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("journal {0} already exists, undo the previous run or remove it first")]
+    JournalExists(PathBuf),
+    #[error("{0} already exists")]
+    TargetExists(PathBuf),
+    #[error("{path}:{line}: invalid journal entry")]
+    InvalidJournal { path: PathBuf, line: usize },
+    #[error("folder `{0}` is not a relative path inside the Maildir")]
+    InvalidFolder(String),
+    #[error("the configuration has {0} accounts, pick one with --account")]
+    AccountRequired(usize),
+    #[error("no account named `{0}`")]
+    UnknownAccount(String),
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+/// How folder names map to directories below the Maildir root.
+#[derive(clap::ValueEnum, Copy, Clone, Default, Debug, PartialEq, Eq)]
+pub enum Layout {
+    /// `Archive/News` is `<root>/Archive/News`, like mbsync's `SubFolders Verbatim`.
+    #[default]
+    Verbatim,
+    /// `Archive/News` is `<root>/.Archive.News` and the root is the inbox, like Dovecot and
+    /// mbsync's `SubFolders Maildir++`.
+    #[value(name = "maildir++")]
+    MaildirPlusPlus,
+}
+
+/// A tree of Maildir folders.
+#[derive(Clone, Debug)]
+pub struct Maildir {
+    pub root: PathBuf,
+    pub layout: Layout,
+}
+
+impl Maildir {
+    /// Returns the directory of the folder called `name`, e.g. `Archive/News`, failing for
+    /// names that would leave the Maildir, such as `../Other` or `/tmp`.
+    pub fn folder(&self, name: &str) -> Result<PathBuf, Error> {
+        if !Path::new(name)
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+        {
+            return Err(Error::InvalidFolder(name.to_owned()));
+        }
+
+        let folder = match self.layout {
+            Layout::Verbatim => self.root.join(name),
+            Layout::MaildirPlusPlus if name.eq_ignore_ascii_case("INBOX") => self.root.clone(),
+            Layout::MaildirPlusPlus => self.root.join(format!(".{}", name.replace('/', "."))),
+        };
+        Ok(folder)
+    }
+}
+
+/// Returns the configuration with only the account labelled or addressed `name`, which may
+/// only be left out if there is a single account. Each account has a mailbox of its own, so
+/// the filters of one must not run on another's mail.
+pub fn select_account(config: Configuration, name: Option<&str>) -> Result<Configuration, Error> {
+    let account = match name {
+        Some(name) => config
+            .accounts
+            .into_iter()
+            .find(|account| {
+                account.account.as_deref() == Some(name) || account.email.as_deref() == Some(name)
+            })
+            .ok_or_else(|| Error::UnknownAccount(name.to_owned()))?,
+        None => match <[Account; 1]>::try_from(config.accounts) {
+            Ok([account]) => account,
+            Err(accounts) => return Err(Error::AccountRequired(accounts.len())),
+        },
+    };
+
+    Ok(Configuration {
+        accounts: vec![account],
+    })
+}
+
+/// A change to a message file, as recorded in the journal.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Operation {
+    /// The message was copied to `to`, undone by removing `to`.
+    Copy { from: PathBuf, to: PathBuf },
+    /// The message was moved or its flags changed, undone by moving it back.
+    Rename { from: PathBuf, to: PathBuf },
+}
+
+impl std::fmt::Display for Operation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Operation::Copy { from, to } => {
+                write!(f, "copy {} -> {}", from.display(), to.display())
+            }
+            Operation::Rename { from, to } => {
+                write!(f, "move {} -> {}", from.display(), to.display())
+            }
+        }
+    }
+}
+
+/// What applying the matching filters does to one message file.
+#[derive(Debug, Default, PartialEq)]
+pub struct Plan {
+    pub operations: Vec<Operation>,
+    /// Keys of the actions that have no meaning for a local Maildir, e.g. `forward`.
+    pub skipped: Vec<&'static str>,
+}
+
+/// Works out the file operations for the actions of `hits` on the message at `path`.
+///
+/// `delete` sets the trashed flag rather than removing the file, so it can be undone and
+/// synchronized like a delete in a mail client.
+pub fn plan(maildir: &Maildir, path: &Path, hits: &[Hit]) -> Result<Plan, Error> {
+    let mut plan = Plan::default();
+    let (Some(name), Some(subdir), Some(folder)) = (
+        path.file_name().and_then(|name| name.to_str()),
+        path.parent(),
+        path.parent().and_then(Path::parent),
+    ) else {
+        return Ok(plan);
+    };
+
+    let (base, old_flags) = helpers::split_name(name);
+    let mut flags = old_flags.clone();
+    let mut target = None;
+    let mut copies = Vec::new();
+
+    for action in hits.iter().flat_map(|hit| &hit.filter.then) {
+        match action {
+            Then::MoveTo(MoveTo { folder }) => target = Some(maildir.folder(folder)?),
+            Then::CopyTo(CopyTo { folder }) => copies.push(maildir.folder(folder)?),
+            Then::Simple(SimpleAction::Delete) => {
+                flags.insert('T');
+            }
+            Then::Simple(SimpleAction::MarkRead) => {
+                flags.insert('S');
+            }
+            Then::Simple(SimpleAction::MarkUnread) => {
+                flags.remove(&'S');
+            }
+            Then::Simple(SimpleAction::Flag) => {
+                flags.insert('F');
+            }
+            Then::Simple(SimpleAction::StopProcessing) => {}
+            action => {
+                if !plan.skipped.contains(&action.key()) {
+                    plan.skipped.push(action.key());
+                }
+            }
+        }
+    }
+
+    // Messages stay in `new` until their flags change, as in a mail client.
+    let flags_changed = flags != old_flags;
+    let in_cur = subdir.file_name().is_some_and(|subdir| subdir == "cur");
+    let (subdir, name) = match in_cur || flags_changed {
+        true => ("cur", helpers::format_name(base, &flags)),
+        false => ("new", name.to_owned()),
+    };
+
+    for copy in copies.into_iter().filter(|copy| copy != folder) {
+        plan.operations.push(Operation::Copy {
+            from: path.to_owned(),
+            to: copy.join(subdir).join(helpers::strip_uid(&name)),
+        });
+    }
+
+    let to = match target.filter(|target| target != folder) {
+        // mbsync keeps the UID in the name, which is only valid in the original folder.
+        Some(target) => target.join(subdir).join(helpers::strip_uid(&name)),
+        None => folder.join(subdir).join(&name),
+    };
+    if to != path {
+        plan.operations.push(Operation::Rename {
+            from: path.to_owned(),
+            to,
+        });
+    }
+
+    Ok(plan)
+}
+
+/// Performs `operations`, creating missing folders and recording each step in `journal`.
+pub fn execute(operations: &[Operation], journal: &mut Journal) -> Result<(), Error> {
+    for operation in operations {
+        let (Operation::Copy { to, .. } | Operation::Rename { to, .. }) = operation;
+        if to.exists() {
+            return Err(Error::TargetExists(to.clone()));
+        }
+        if let Some(folder) = to.parent().and_then(Path::parent) {
+            for subdir in ["cur", "new", "tmp"] {
+                fs::create_dir_all(folder.join(subdir))?;
+            }
+        }
+
+        match operation {
+            Operation::Copy { from, to } => {
+                fs::copy(from, to)?;
+            }
+            Operation::Rename { from, to } => fs::rename(from, to)?,
+        }
+        journal.record(operation)?;
+    }
+    Ok(())
+}
+
+/// A log of the operations of one `apply` run, one per line, so the run can be undone.
+pub struct Journal {
+    file: fs::File,
+    path: PathBuf,
+    entries: usize,
+}
+
+impl Journal {
+    /// Creates the journal at `path`, refusing to overwrite the journal of an earlier run.
+    pub fn create(path: &Path) -> Result<Self, Error> {
+        let mut file = fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(path)
+            .map_err(|err| match err.kind() {
+                io::ErrorKind::AlreadyExists => Error::JournalExists(path.to_owned()),
+                _ => Error::Io(err),
+            })?;
+        writeln!(file, "{JOURNAL_HEADER}")?;
+        Ok(Journal {
+            file,
+            path: path.to_owned(),
+            entries: 0,
+        })
+    }
+
+    fn record(&mut self, operation: &Operation) -> io::Result<()> {
+        writeln!(self.file, "{}", helpers::format_entry(operation))?;
+        self.entries += 1;
+        self.file.sync_data()
+    }
+
+    /// Ends the run, removing the journal if nothing was recorded, as there is nothing to undo
+    /// and it would block the next run.
+    pub fn finish(self) -> io::Result<()> {
+        match self.entries {
+            0 => fs::remove_file(&self.path),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Reverts the operations in the journal at `path`, last one first, then removes the journal.
+///
+/// The journal is rewritten after each reverted operation, so if one fails, undoing again
+/// continues with it rather than with operations that were already reverted.
+pub fn undo(path: &Path) -> Result<Vec<Operation>, Error> {
+    let journal = fs::read_to_string(path)?;
+    let operations = journal
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .map(|(index, line)| {
+            helpers::parse_entry(line).ok_or_else(|| Error::InvalidJournal {
+                path: path.to_owned(),
+                line: index + 1,
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    for (index, operation) in operations.iter().enumerate().rev() {
+        match operation {
+            Operation::Copy { to, .. } => fs::remove_file(to)?,
+            Operation::Rename { from, to } => {
+                if from.exists() {
+                    return Err(Error::TargetExists(from.clone()));
+                }
+                fs::rename(to, from)?;
+            }
+        }
+        helpers::rewrite_journal(path, &operations[..index])?;
+    }
+    fs::remove_file(path)?;
+
+    Ok(operations)
+}
+
+const JOURNAL_HEADER: &str = "# mail-message-filters apply journal";
+
+mod helpers {
+    /// Splits a Maildir file name into its unique part and its flags.
+    pub fn split_name(name: &str) -> (&str, BTreeSet<char>) {
+        match name.rsplit_once(":2,") {
+            Some((base, flags)) => (base, flags.chars().collect()),
+            None => (name, BTreeSet::new()),
+        }
+    }
+
+    /// Joins a unique part and flags, which Maildir wants in ASCII order.
+    pub fn format_name(base: &str, flags: &BTreeSet<char>) -> String {
+        format!("{base}:2,{}", flags.iter().collect::<String>())
+    }
+
+    /// Removes mbsync's `,U=<uid>` from a file name.
+    pub fn strip_uid(name: &str) -> String {
+        let Some(start) = name.find(",U=") else {
+            return name.to_owned();
+        };
+        let end = name[start + 3..]
+            .find(|c: char| !c.is_ascii_digit())
+            .map_or(name.len(), |end| start + 3 + end);
+        format!("{}{}", &name[..start], &name[end..])
+    }
+
+    pub fn format_entry(operation: &Operation) -> String {
+        let (kind, from, to) = match operation {
+            Operation::Copy { from, to } => ("copy", from, to),
+            Operation::Rename { from, to } => ("rename", from, to),
+        };
+        format!("{kind}\t{}\t{}", from.display(), to.display())
+    }
+
+    /// Replaces the journal at `path` with one of `operations`, through a temporary file.
+    pub fn rewrite_journal(path: &Path, operations: &[Operation]) -> io::Result<()> {
+        let mut journal = format!("{JOURNAL_HEADER}\n");
+        for operation in operations {
+            journal.push_str(&format_entry(operation));
+            journal.push('\n');
+        }
+
+        let mut temporary = path.as_os_str().to_owned();
+        temporary.push(".tmp");
+        let mut file = fs::File::create(&temporary)?;
+        file.write_all(journal.as_bytes())?;
+        file.sync_data()?;
+        fs::rename(&temporary, path)
+    }
+
+    pub fn parse_entry(line: &str) -> Option<Operation> {
+        let mut fields = line.split('\t');
+        let (kind, from, to) = (fields.next()?, fields.next()?, fields.next()?);
+        if fields.next().is_some() {
+            return None;
+        }
+        let (from, to) = (PathBuf::from(from), PathBuf::from(to));
+        match kind {
+            "copy" => Some(Operation::Copy { from, to }),
+            "rename" => Some(Operation::Rename { from, to }),
+            _ => None,
+        }
+    }
+
+    use std::{
+        collections::BTreeSet,
+        fs,
+        io::{self, Write},
+        path::{Path, PathBuf},
+    };
+
+    use super::{Operation, JOURNAL_HEADER};
+}
+
+#[cfg(test)]
+mod tests {
+    fn config() -> Configuration {
+        crate::configuration::parse(
+            "\
+- thunderbird_id: work
+  message_filters:
+    - title: Github
+      when:
+        - field: from
+          ends_with: ['@github.com']
+      then:
+        - copy_to: Archive/Github
+        - move_to: Github
+        - mark_read
+        - forward: me@example.com
+    - title: Spam
+      when:
+        - field: subject
+          contains: [viagra]
+      then:
+        - delete
+",
+        )
+        .unwrap()
+    }
+
+    fn plan_for(layout: Layout, path: &str, headers: &str) -> Plan {
+        let config = config();
+        let engine = Engine::new(&config).unwrap();
+        let message = Message::parse(format!("{headers}\n\nbody\n").as_bytes());
+        let maildir = Maildir {
+            root: PathBuf::from("/mail"),
+            layout,
+        };
+        plan(&maildir, Path::new(path), &engine.evaluate(&message, 0)).unwrap()
+    }
+
+    #[test]
+    fn test_plan_move_and_flags() {
+        let plan = plan_for(
+            Layout::Verbatim,
+            "/mail/INBOX/new/1.host,U=42",
+            "From: noreply@github.com",
+        );
+
+        assert_eq!(
+            plan.operations,
+            [
+                Operation::Copy {
+                    from: PathBuf::from("/mail/INBOX/new/1.host,U=42"),
+                    to: PathBuf::from("/mail/Archive/Github/cur/1.host:2,S"),
+                },
+                Operation::Rename {
+                    from: PathBuf::from("/mail/INBOX/new/1.host,U=42"),
+                    to: PathBuf::from("/mail/Github/cur/1.host:2,S"),
+                },
+            ]
+        );
+        assert_eq!(plan.skipped, ["forward"]);
+    }
+
+    #[test]
+    fn test_plan_maildir_plus_plus() {
+        let plan = plan_for(
+            Layout::MaildirPlusPlus,
+            "/mail/cur/1.host:2,F",
+            "Subject: cheap viagra",
+        );
+        assert_eq!(
+            plan.operations,
+            [Operation::Rename {
+                from: PathBuf::from("/mail/cur/1.host:2,F"),
+                to: PathBuf::from("/mail/cur/1.host:2,FT"),
+            }]
+        );
+
+        let plan = plan_for(Layout::MaildirPlusPlus, "/mail/new/1.host", "Subject: hi");
+        assert_eq!(plan, Plan::default());
+
+        let maildir = Maildir {
+            root: PathBuf::from("/mail"),
+            layout: Layout::MaildirPlusPlus,
+        };
+        assert_eq!(
+            maildir.folder("Archive/News").unwrap(),
+            Path::new("/mail/.Archive.News")
+        );
+        assert_eq!(maildir.folder("INBOX").unwrap(), Path::new("/mail"));
+    }
+
+    #[test]
+    fn test_folder_outside_the_maildir() {
+        for layout in [Layout::Verbatim, Layout::MaildirPlusPlus] {
+            let maildir = Maildir {
+                root: PathBuf::from("/mail"),
+                layout,
+            };
+            for name in ["../../out", "Archive/../../out", "/tmp/out", "./Archive"] {
+                assert!(
+                    matches!(maildir.folder(name), Err(Error::InvalidFolder(_))),
+                    "{name}"
+                );
+            }
+        }
+
+        let config = crate::configuration::parse(
+            "\
+- thunderbird_id: work
+  message_filters:
+    - title: Escape
+      when:
+        - field: subject
+          contains: [x]
+      then:
+        - move_to: ../../out
+",
+        )
+        .unwrap();
+        let engine = Engine::new(&config).unwrap();
+        let message = Message::parse(b"Subject: x\n\nbody\n");
+        let maildir = Maildir {
+            root: PathBuf::from("/mail"),
+            layout: Layout::Verbatim,
+        };
+        assert!(matches!(
+            plan(&maildir, Path::new("/mail/INBOX/new/1"), &engine.evaluate(&message, 0)),
+            Err(Error::InvalidFolder(folder)) if folder == "../../out"
+        ));
+    }
+
+    #[test]
+    fn test_select_account() {
+        let config = crate::configuration::parse(
+            "\
+- account: work
+  message_filters: []
+- email: jdoe@example.com
+  message_filters: []
+",
+        )
+        .unwrap();
+
+        assert!(matches!(
+            select_account(config.clone(), None),
+            Err(Error::AccountRequired(2))
+        ));
+        assert!(matches!(
+            select_account(config.clone(), Some("home")),
+            Err(Error::UnknownAccount(_))
+        ));
+        let selected = select_account(config.clone(), Some("jdoe@example.com")).unwrap();
+        assert_eq!(selected.accounts, config.accounts[1..]);
+
+        let single = Configuration {
+            accounts: config.accounts[..1].to_vec(),
+        };
+        assert_eq!(select_account(single.clone(), None).unwrap(), single);
+    }
+
+    #[test]
+    fn test_journal_entries() {
+        let operation = Operation::Rename {
+            from: PathBuf::from("/mail/new/1 x"),
+            to: PathBuf::from("/mail/Github/cur/1 x:2,S"),
+        };
+        let entry = helpers::format_entry(&operation);
+
+        assert_eq!(entry, "rename\t/mail/new/1 x\t/mail/Github/cur/1 x:2,S");
+        assert_eq!(helpers::parse_entry(&entry), Some(operation));
+        assert_eq!(helpers::parse_entry("delete\t/a\t/b"), None);
+        assert_eq!(helpers::strip_uid("1.host,U=42:2,S"), "1.host:2,S");
+    }
+
+    #[test]
+    fn test_execute_and_undo() {
+        let root = std::env::temp_dir().join(format!("mmf-apply-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("INBOX/new")).unwrap();
+        let path = root.join("INBOX/new/1.host");
+        fs::write(&path, "From: noreply@github.com\n\nbody\n").unwrap();
+
+        let maildir = Maildir {
+            root: root.clone(),
+            layout: Layout::Verbatim,
+        };
+        let config = config();
+        let engine = Engine::new(&config).unwrap();
+        let message = Message::parse(&fs::read(&path).unwrap());
+        let plan = plan(&maildir, &path, &engine.evaluate(&message, 0)).unwrap();
+
+        let journal_path = root.join("journal");
+        let mut journal = Journal::create(&journal_path).unwrap();
+        execute(&plan.operations, &mut journal).unwrap();
+        assert!(!path.exists());
+        assert!(root.join("Github/cur/1.host:2,S").exists());
+        assert!(root.join("Archive/Github/cur/1.host:2,S").exists());
+        assert!(matches!(
+            Journal::create(&journal_path),
+            Err(Error::JournalExists(_))
+        ));
+        journal.finish().unwrap();
+        assert!(journal_path.exists());
+
+        assert_eq!(undo(&journal_path).unwrap(), plan.operations);
+        assert!(path.exists());
+        assert!(!root.join("Github/cur/1.host:2,S").exists());
+        assert!(!root.join("Archive/Github/cur/1.host:2,S").exists());
+        assert!(!journal_path.exists());
+
+        Journal::create(&journal_path).unwrap().finish().unwrap();
+        assert!(!journal_path.exists());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_interrupted_undo() {
+        let root = std::env::temp_dir().join(format!("mmf-undo-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("INBOX/new")).unwrap();
+        let path = root.join("INBOX/new/1.host");
+        fs::write(&path, "From: noreply@github.com\n\nbody\n").unwrap();
+
+        let maildir = Maildir {
+            root: root.clone(),
+            layout: Layout::Verbatim,
+        };
+        let config = config();
+        let engine = Engine::new(&config).unwrap();
+        let message = Message::parse(&fs::read(&path).unwrap());
+        let plan = plan(&maildir, &path, &engine.evaluate(&message, 0)).unwrap();
+        let journal_path = root.join("journal");
+        execute(
+            &plan.operations,
+            &mut Journal::create(&journal_path).unwrap(),
+        )
+        .unwrap();
+
+        // Removing the copy fails once the move back has been undone.
+        let copy = root.join("Archive/Github/cur/1.host:2,S");
+        fs::remove_file(&copy).unwrap();
+        fs::create_dir(&copy).unwrap();
+        assert!(matches!(undo(&journal_path), Err(Error::Io(_))));
+        assert!(path.exists());
+        assert_eq!(
+            fs::read_to_string(&journal_path).unwrap(),
+            format!(
+                "{JOURNAL_HEADER}\n{}\n",
+                helpers::format_entry(&plan.operations[0])
+            )
+        );
+
+        fs::remove_dir(&copy).unwrap();
+        fs::write(&copy, "").unwrap();
+        assert_eq!(undo(&journal_path).unwrap(), plan.operations[..1]);
+        assert!(path.exists());
+        assert!(!copy.exists());
+        assert!(!journal_path.exists());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    use super::*;
+    use crate::{configuration::Configuration, engine::Engine, message::Message};
+}
+
+use std::{
+    fs,
+    io::{self, Write},
+    path::{Component, Path, PathBuf},
+};
+
+use crate::{
+    configuration::{Account, Configuration, CopyTo, MoveTo, SimpleAction, Then},
+    engine::Hit,
+};
@@ -0,0 +1,590 @@
+#[derive(clap::Parser, Debug)]
+struct Cli {
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(clap::Subcommand, Debug)]
+enum Command {
+    /// Lint the configuration file.
+    Lint,
+    /// Print the configuration file in a specific format.
+    Print {
+        format: PrintFormat,
+        /// Write one file per account into this directory instead of printing to stdout
+        /// (thunderbird only).
+        #[arg(long)]
+        out_dir: Option<PathBuf>,
+        /// Print this filter list with the generated filters replacing the ones written by a
+        /// previous merge, keeping all others (thunderbird only).
+        #[arg(long, conflicts_with = "out_dir")]
+        merge_into: Option<PathBuf>,
+        /// Indent the generated XML (evolution only).
+        #[arg(long)]
+        pretty: bool,
+    },
+    /// Show which filters and actions would apply to existing messages.
+    Test {
+        /// Messages stored as single `.eml` files.
+        messages: Vec<PathBuf>,
+        /// Read every message in an mbox file.
+        #[arg(long)]
+        mbox: Vec<PathBuf>,
+        /// Read every message in a Maildir directory.
+        #[arg(long)]
+        maildir: Vec<PathBuf>,
+    },
+    /// Apply the filters to the messages in a local Maildir.
+    Apply {
+        /// Root of the Maildir tree.
+        #[arg(long)]
+        maildir: PathBuf,
+        /// Folder whose messages are filtered, defaults to the root itself.
+        #[arg(long)]
+        folder: Option<String>,
+        /// The account whose filters are applied, by its `account` label or `email`, required
+        /// when the configuration has several accounts.
+        #[arg(long)]
+        account: Option<String>,
+        /// How folder names map to directories.
+        #[arg(long, value_enum, default_value_t)]
+        layout: apply::Layout,
+        /// Print what would be done without changing anything.
+        #[arg(long)]
+        dry_run: bool,
+        /// Where to record the changes, for `undo`.
+        #[arg(long, default_value = DEFAULT_JOURNAL)]
+        journal: PathBuf,
+    },
+    /// Revert the changes of an `apply` run.
+    Undo {
+        #[arg(long, default_value = DEFAULT_JOURNAL)]
+        journal: PathBuf,
+    },
+    /// Write the filters into a client's configuration, backing up the files it replaces.
+    Install {
+        client: Client,
+        /// The profile to install into, by name or directory, defaults to the default profile
+        /// (thunderbird only).
+        #[arg(long)]
+        profile: Option<String>,
+        /// The client's configuration directory, defaults to `~/.thunderbird` or
+        /// `~/.config/evolution`.
+        #[arg(long)]
+        dir: Option<PathBuf>,
+    },
+    /// Show how the installed filters differ from the generated ones, filter by filter.
+    Diff {
+        client: Client,
+        /// The installed filter file, found like `install` does if omitted.
+        #[arg(long)]
+        target: Option<PathBuf>,
+        /// The profile whose filters are compared (thunderbird only).
+        #[arg(long)]
+        profile: Option<String>,
+        /// The client's configuration directory, defaults to `~/.thunderbird` or
+        /// `~/.config/evolution`.
+        #[arg(long)]
+        dir: Option<PathBuf>,
+    },
+    /// Convert a client's filter file into a configuration file.
+    Import {
+        format: ImportFormat,
+        /// The filter file, read from stdin if omitted.
+        file: Option<PathBuf>,
+        /// The client's account id, inferred from folder URIs if omitted (evolution and
+        /// thunderbird only).
+        #[arg(long)]
+        id: Option<String>,
+        /// Mailbox hierarchy separator of the script, defaults to `/` (sieve only).
+        #[arg(long)]
+        separator: Option<String>,
+    },
+}
+
+const DEFAULT_JOURNAL: &str = "mail-message-filters.journal";
+
+#[derive(clap::ValueEnum, Copy, Clone, Debug)]
+enum PrintFormat {
+    Evolution,
+    Sieve,
+    Thunderbird,
+}
+
+#[derive(clap::ValueEnum, Copy, Clone, Debug)]
+enum Client {
+    Evolution,
+    Thunderbird,
+}
+
+#[derive(clap::ValueEnum, Copy, Clone, Debug)]
+enum ImportFormat {
+    Evolution,
+    Sieve,
+    Thunderbird,
+}
+
+/// Runs the `mail-message-filters` command line tool with the process's arguments, reading
+/// the configuration from stdin.
+pub fn run() -> ExitCode {
+    let cli = Cli::parse();
+
+    let result = match cli.command {
+        Command::Lint => lint_config(),
+        Command::Print {
+            format,
+            out_dir,
+            merge_into,
+            pretty,
+        } => match out_dir {
+            Some(out_dir) => write_config(format, &out_dir),
+            None => print_config(format, merge_into.as_deref(), pretty),
+        },
+        Command::Test {
+            messages,
+            mbox,
+            maildir,
+        } => test_messages(&messages, &mbox, &maildir),
+        Command::Apply {
+            maildir,
+            folder,
+            account,
+            layout,
+            dry_run,
+            journal,
+        } => {
+            let maildir = apply::Maildir {
+                root: maildir,
+                layout,
+            };
+            apply_maildir(
+                &maildir,
+                folder.as_deref(),
+                account.as_deref(),
+                dry_run,
+                &journal,
+            )
+        }
+        Command::Undo { journal } => undo_apply(&journal),
+        Command::Install {
+            client,
+            profile,
+            dir,
+        } => install_config(client, profile.as_deref(), dir),
+        Command::Diff {
+            client,
+            target,
+            profile,
+            dir,
+        } => diff_config(client, target, profile.as_deref(), dir),
+        Command::Import {
+            format,
+            file,
+            id,
+            separator,
+        } => import_config(format, file.as_deref(), id.as_deref(), separator.as_deref()),
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn read_source() -> Result<String> {
+    use std::io::Read;
+
+    let mut buff = String::new();
+    let _ = io::stdin().read_to_string(&mut buff)?;
+    Ok(buff)
+}
+
+fn read_config() -> Result<Configuration> {
+    let config = configuration::parse(&read_source()?)?;
+    Ok(config)
+}
+
+fn lint_config() -> Result<()> {
+    let source = read_source()?;
+    let config = configuration::parse(&source)?;
+
+    let diagnostics = lint::lint_config(&config, &SourceMap::parse(&source));
+    for diagnostic in &diagnostics {
+        println!("{diagnostic}");
+    }
+
+    let errors = diagnostics
+        .iter()
+        .filter(|diagnostic| diagnostic.severity == lint::Severity::Error)
+        .count();
+    if errors > 0 {
+        return Err(lint::Error::Failed(errors).into());
+    }
+
+    Ok(())
+}
+
+fn print_config(format: PrintFormat, merge_into: Option<&Path>, pretty: bool) -> Result<()> {
+    let config = read_config()?;
+
+    if pretty && !matches!(format, PrintFormat::Evolution) {
+        return Err(format!("--pretty is not supported for {format:?}").into());
+    }
+    if let Some(existing) = merge_into {
+        if !matches!(format, PrintFormat::Thunderbird) {
+            return Err(format!("--merge-into is not supported for {format:?}").into());
+        }
+        let existing = std::fs::read_to_string(existing)?;
+        println!("{}", printer::thunderbird::merge_config(config, &existing)?);
+        return Ok(());
+    }
+
+    let output = match format {
+        PrintFormat::Evolution => {
+            let mut config = config;
+            if resolver::needs_evolution_ids(&config) {
+                resolver::resolve_evolution_ids(&mut config, &evolution_dir()?.join("sources"))?;
+            }
+            warn_dropped_fields(&config);
+            printer::evolution::write_config(config, io::stdout().lock(), pretty)?;
+            return Ok(());
+        }
+        PrintFormat::Sieve => printer::sieve::print_config(config)?,
+        PrintFormat::Thunderbird => printer::thunderbird::print_config(config)?,
+    };
+
+    println!("{}", output);
+
+    Ok(())
+}
+
+fn write_config(format: PrintFormat, out_dir: &Path) -> Result<()> {
+    let config = read_config()?;
+
+    let files = match format {
+        PrintFormat::Thunderbird => printer::thunderbird::print_accounts(config)?,
+        _ => return Err(format!("--out-dir is not supported for {format:?}").into()),
+    };
+
+    for (path, output) in files {
+        let path = out_dir.join(path);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&path, output)?;
+        eprintln!("wrote {}", path.display());
+    }
+
+    Ok(())
+}
+
+fn test_messages(messages: &[PathBuf], mbox: &[PathBuf], maildir: &[PathBuf]) -> Result<()> {
+    if messages.is_empty() && mbox.is_empty() && maildir.is_empty() {
+        return Err("no messages given, pass .eml files, --mbox or --maildir".into());
+    }
+
+    let config = read_config()?;
+    let engine = engine::Engine::new(&config)?;
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs() as i64);
+
+    let mut entries = Vec::new();
+    for path in messages {
+        entries.push(mailbox::read_eml(path)?);
+    }
+    for path in mbox {
+        entries.extend(mailbox::read_mbox(path)?);
+    }
+    for path in maildir {
+        entries.extend(mailbox::read_maildir(path)?);
+    }
+
+    let mut matched = 0;
+    for entry in &entries {
+        let hits = engine.evaluate(&entry.message, now);
+        let subject = entry.message.subject();
+        if hits.is_empty() {
+            println!("{}: '{subject}' matches no filter", entry.location);
+            continue;
+        }
+
+        matched += 1;
+        for hit in hits {
+            let actions = hit
+                .filter
+                .then
+                .iter()
+                .map(engine::format_action)
+                .collect::<Vec<_>>();
+            println!(
+                "{}: '{subject}' matches '{}': {}",
+                entry.location,
+                hit.filter.title,
+                actions.join(", ")
+            );
+        }
+    }
+    eprintln!("{matched} of {} message(s) matched", entries.len());
+
+    Ok(())
+}
+
+fn apply_maildir(
+    maildir: &apply::Maildir,
+    folder: Option<&str>,
+    account: Option<&str>,
+    dry_run: bool,
+    journal: &Path,
+) -> Result<()> {
+    let config = apply::select_account(read_config()?, account)?;
+    let engine = engine::Engine::new(&config)?;
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs() as i64);
+
+    let source = match folder {
+        Some(folder) => maildir.folder(folder)?,
+        None => maildir.root.clone(),
+    };
+    let paths =
+        mailbox::maildir_paths(&source).map_err(|err| format!("{}: {err}", source.display()))?;
+
+    let mut journal = match dry_run {
+        true => None,
+        false => Some(apply::Journal::create(journal)?),
+    };
+    let result = apply_paths(maildir, &engine, now, &paths, journal.as_mut());
+    if let Some(journal) = journal {
+        journal.finish()?;
+    }
+    let changed = result?;
+
+    match dry_run {
+        true => eprintln!("would change {changed} message(s)"),
+        false => eprintln!("changed {changed} message(s), undo with `undo`"),
+    }
+
+    Ok(())
+}
+
+/// Applies the filters to the messages at `paths`, returning how many were changed. Without a
+/// journal, the changes are only printed.
+fn apply_paths(
+    maildir: &apply::Maildir,
+    engine: &engine::Engine,
+    now: i64,
+    paths: &[PathBuf],
+    mut journal: Option<&mut apply::Journal>,
+) -> Result<usize> {
+    let mut changed = 0;
+    for path in paths {
+        let entry = mailbox::read_maildir_message(path)?;
+        let hits = engine.evaluate(&entry.message, now);
+        let plan = apply::plan(maildir, path, &hits)?;
+
+        if !plan.skipped.is_empty() {
+            eprintln!(
+                "{}: skipped {}, which cannot be applied to a Maildir",
+                entry.location,
+                plan.skipped.join(", ")
+            );
+        }
+        if plan.operations.is_empty() {
+            continue;
+        }
+
+        changed += 1;
+        for operation in &plan.operations {
+            println!("{operation}");
+        }
+        if let Some(journal) = journal.as_deref_mut() {
+            apply::execute(&plan.operations, journal)?;
+        }
+    }
+
+    Ok(changed)
+}
+
+fn undo_apply(journal: &Path) -> Result<()> {
+    let operations = apply::undo(journal)?;
+    eprintln!("reverted {} operation(s)", operations.len());
+    Ok(())
+}
+
+fn install_config(client: Client, profile: Option<&str>, dir: Option<PathBuf>) -> Result<()> {
+    let config = read_config()?;
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs() as i64);
+
+    if profile.is_some() && !matches!(client, Client::Thunderbird) {
+        return Err(format!("--profile is not supported for {client:?}").into());
+    }
+
+    let installed = match client {
+        Client::Evolution => {
+            let dir = match dir {
+                Some(dir) => dir,
+                None => evolution_dir()?,
+            };
+            warn_dropped_fields(&config);
+            vec![install::evolution::install(config, &dir, now)?]
+        }
+        Client::Thunderbird => {
+            let profile = thunderbird_profile(dir, profile)?;
+            eprintln!("installing into profile '{}'", profile.name);
+            install::thunderbird::install(config, &profile.path, now)?
+        }
+    };
+
+    for installed in installed {
+        if let Some(backup) = &installed.backup {
+            eprintln!("backed up {}", backup.display());
+        }
+        eprintln!("wrote {}", installed.path.display());
+    }
+
+    Ok(())
+}
+
+/// Warns about the settings Evolution rules cannot store, which are left out.
+fn warn_dropped_fields(config: &Configuration) {
+    for warning in printer::evolution::dropped_fields(config) {
+        eprintln!("warning: {warning}");
+    }
+}
+
+fn diff_config(
+    client: Client,
+    target: Option<PathBuf>,
+    profile: Option<&str>,
+    dir: Option<PathBuf>,
+) -> Result<()> {
+    let mut config = read_config()?;
+
+    if profile.is_some() && !matches!(client, Client::Thunderbird) {
+        return Err(format!("--profile is not supported for {client:?}").into());
+    }
+
+    let targets = match (client, target) {
+        (Client::Evolution, target) => {
+            let dir = match dir {
+                Some(dir) => dir,
+                None => evolution_dir()?,
+            };
+            resolver::resolve_evolution_ids(&mut config, &dir.join("sources"))?;
+            let target = target.unwrap_or_else(|| dir.join("mail").join("filters.xml"));
+            vec![(target, config)]
+        }
+        (Client::Thunderbird, Some(target)) => vec![(target, config)],
+        (Client::Thunderbird, None) => {
+            let profile = thunderbird_profile(dir, profile)?;
+            install::thunderbird::find_files(config, &profile.path)?
+                .into_iter()
+                .map(|(path, account)| {
+                    let accounts = vec![account];
+                    (path, Configuration { accounts })
+                })
+                .collect()
+        }
+    };
+
+    let mut differ = 0;
+    for (target, config) in targets {
+        let installed = std::fs::read_to_string(&target)
+            .map_err(|err| format!("{}: {err}", target.display()))?;
+        let diffs = match client {
+            Client::Evolution => diff::diff_evolution(config, &installed)?,
+            Client::Thunderbird => diff::diff_thunderbird(config, &installed)?,
+        };
+
+        eprintln!("compared with {}", target.display());
+        for diff in &diffs {
+            println!("{diff}");
+        }
+        differ += diffs.len();
+    }
+
+    if differ > 0 {
+        return Err(diff::Error::Differs(differ).into());
+    }
+    eprintln!("no differences");
+    Ok(())
+}
+
+/// Returns the Thunderbird profile named `profile`, or the default one, in `dir`
+/// (`~/.thunderbird` by default).
+fn thunderbird_profile(
+    dir: Option<PathBuf>,
+    profile: Option<&str>,
+) -> Result<install::thunderbird::Profile> {
+    let dir = match dir {
+        Some(dir) => dir,
+        None => home_dir()?.join(".thunderbird"),
+    };
+    Ok(install::thunderbird::find_profile(&dir, profile)?)
+}
+
+fn home_dir() -> Result<PathBuf> {
+    match std::env::var_os("HOME") {
+        Some(home) if !home.is_empty() => Ok(PathBuf::from(home)),
+        _ => Err("HOME is not set, pass --dir".into()),
+    }
+}
+
+/// Returns Evolution's configuration directory, `~/.config/evolution` by default.
+fn evolution_dir() -> Result<PathBuf> {
+    let config = match std::env::var_os("XDG_CONFIG_HOME") {
+        Some(config) if !config.is_empty() => PathBuf::from(config),
+        _ => home_dir()?.join(".config"),
+    };
+    Ok(config.join("evolution"))
+}
+
+fn import_config(
+    format: ImportFormat,
+    file: Option<&Path>,
+    id: Option<&str>,
+    separator: Option<&str>,
+) -> Result<()> {
+    if id.is_some() && matches!(format, ImportFormat::Sieve) {
+        return Err(format!("--id is not supported for {format:?}").into());
+    }
+    if separator.is_some() && !matches!(format, ImportFormat::Sieve) {
+        return Err(format!("--separator is not supported for {format:?}").into());
+    }
+
+    let source = match file {
+        Some(file) => std::fs::read_to_string(file)?,
+        None => read_source()?,
+    };
+    let import = match format {
+        ImportFormat::Evolution => importer::evolution::import(&source, id)?,
+        ImportFormat::Sieve => importer::sieve::import(&source, separator)?,
+        ImportFormat::Thunderbird => importer::thunderbird::import(&source, id)?,
+    };
+
+    for warning in &import.warnings {
+        eprintln!("warning: {warning}");
+    }
+    print!("{}", serde_yaml::to_string(&import.config)?);
+    Ok(())
+}
+
+use std::{
+    io,
+    path::{Path, PathBuf},
+    process::ExitCode,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use clap::Parser;
+
+use crate::{
+    apply, configuration, diff, engine, importer, install, lint, mailbox, printer, resolver,
+    source::SourceMap, Configuration, Result,
+};
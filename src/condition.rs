@@ -0,0 +1,295 @@
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum Error {
+    #[error("conditions mix `all` and `any`, which cannot be expressed as a single list")]
+    MixedGrouping,
+}
+
+/// A single predicate, with any negation pushed down to it.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Test<'a> {
+    pub predicate: Predicate<'a>,
+    pub negated: bool,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Predicate<'a> {
+    /// A single value tested against a field. The matcher is always a positive one, see
+    /// [`Test::negated`].
+    String {
+        field: &'a Field,
+        matcher: Matcher,
+        value: &'a str,
+    },
+    State(StateTest<'a>),
+}
+
+impl Test<'_> {
+    /// Returns the configuration key for this test, e.g. `does_not_contain`, or `not starts_with`
+    /// for negations without a matcher of their own.
+    pub fn key(&self) -> String {
+        let (key, negated) = match self.predicate {
+            Predicate::String { matcher, .. } => match (self.negated, matcher.negate()) {
+                (true, Some(negated)) => (negated.key(), false),
+                (negated, _) => (matcher.key(), negated),
+            },
+            Predicate::State(state) => (state.key(), self.negated),
+        };
+        match negated {
+            true => format!("not {key}"),
+            false => key.to_owned(),
+        }
+    }
+}
+
+/// A flat list of tests combined by a single grouping, which is what mail clients support.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Flat<'a> {
+    pub grouping: Grouping,
+    pub tests: Vec<Test<'a>>,
+}
+
+/// Flattens the conditions of a filter into a single list of tests.
+///
+/// Negations are pushed down to the tests, nested groups of the same kind are merged and groups
+/// with a single member are unwrapped. Whatever still mixes `all` and `any` is an error.
+pub fn flatten(grouping: Grouping, when: &[When]) -> Result<Flat<'_>, Error> {
+    let children = when
+        .iter()
+        .map(|when| helpers::build(when, false))
+        .collect();
+
+    match helpers::group(grouping, children) {
+        Expr::Test(test) => Ok(Flat {
+            grouping,
+            tests: vec![test],
+        }),
+        Expr::Group(grouping, children) => {
+            let tests = children
+                .into_iter()
+                .map(|child| match child {
+                    Expr::Test(test) => Ok(test),
+                    Expr::Group(..) => Err(Error::MixedGrouping),
+                })
+                .collect::<Result<_, _>>()?;
+            Ok(Flat { grouping, tests })
+        }
+    }
+}
+
+/// A condition tree in negation normal form.
+enum Expr<'a> {
+    Group(Grouping, Vec<Expr<'a>>),
+    Test(Test<'a>),
+}
+
+mod helpers {
+
+    /// Builds the tree for `when`, negating it if `negated` is set.
+    pub fn build(when: &When, negated: bool) -> Expr<'_> {
+        let (grouping, children) = match when {
+            When::All(all) => (Grouping::All, &all.conditions[..]),
+            When::Any(any) => (Grouping::Any, &any.conditions[..]),
+            When::Not(not) => return build(&not.condition, !negated),
+            _ => return build_test(when, negated),
+        };
+
+        // De Morgan: a negated `all` is an `any` of negations and vice versa.
+        let grouping = match (grouping, negated) {
+            (grouping, false) => grouping,
+            (Grouping::All, true) => Grouping::Any,
+            (Grouping::Any, true) => Grouping::All,
+        };
+        let children = children.iter().map(|child| build(child, negated)).collect();
+        group(grouping, children)
+    }
+
+    /// Several values match when any of them does, or none of them for negated matchers.
+    fn build_test(when: &When, negated: bool) -> Expr<'_> {
+        let Some(test) = when.string_test() else {
+            let Some((state, holds)) = when.state_test() else {
+                unreachable!("groups are handled by build");
+            };
+            return Expr::Test(Test {
+                predicate: Predicate::State(state),
+                negated: negated == holds,
+            });
+        };
+
+        let negated = negated != test.matcher.is_negated();
+        let matcher = match test.matcher.is_negated() {
+            true => test.matcher.negate().unwrap_or(test.matcher),
+            false => test.matcher,
+        };
+        let grouping = match negated {
+            true => Grouping::All,
+            false => Grouping::Any,
+        };
+
+        let children = test
+            .values
+            .iter()
+            .map(|value| {
+                Expr::Test(Test {
+                    predicate: Predicate::String {
+                        field: test.field,
+                        matcher,
+                        value,
+                    },
+                    negated,
+                })
+            })
+            .collect();
+        group(grouping, children)
+    }
+
+    /// Merges children of the same grouping into the group and unwraps single members.
+    pub fn group(grouping: Grouping, children: Vec<Expr<'_>>) -> Expr<'_> {
+        let mut members = Vec::new();
+        for child in children {
+            match child {
+                Expr::Group(inner, nested) if inner == grouping => members.extend(nested),
+                child => members.push(child),
+            }
+        }
+
+        match members.len() {
+            1 => members.pop().unwrap_or_else(|| unreachable!()),
+            _ => Expr::Group(grouping, members),
+        }
+    }
+
+    use super::{Expr, Predicate, Test};
+    use crate::configuration::{Grouping, When};
+}
+
+#[cfg(test)]
+mod tests {
+    fn parse(source: &str) -> MessageFilter {
+        let config = crate::configuration::parse(source).unwrap();
+        config.accounts[0].message_filters[0].clone()
+    }
+
+    fn describe(flat: &Flat) -> Vec<String> {
+        flat.tests
+            .iter()
+            .map(|test| match test.predicate {
+                Predicate::String { value, .. } => format!("{} {value}", test.key()),
+                Predicate::State(_) => test.key(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_flatten_values() {
+        let filter = parse(
+            "\
+- message_filters:
+    - title: Github
+      when:
+        - field: from
+          ends_with: ['@github.com', '@noreply.github.com']
+      then: []
+",
+        );
+        let flat = flatten(filter.grouping, &filter.when).unwrap();
+
+        assert_eq!(flat.grouping, Grouping::Any);
+        assert_eq!(
+            describe(&flat),
+            ["ends_with @github.com", "ends_with @noreply.github.com"]
+        );
+    }
+
+    #[test]
+    fn test_flatten_negations() {
+        let filter = parse(
+            "\
+- message_filters:
+    - title: Humans
+      match: all
+      when:
+        - field: subject
+          starts_with: ['[ci]']
+        - not:
+            any:
+              - field: from
+                contains: [bot]
+              - field: from
+                does_not_end_with: ['@example.com']
+        - field: from
+          is_not: [a, b]
+      then: []
+",
+        );
+        let flat = flatten(filter.grouping, &filter.when).unwrap();
+
+        assert_eq!(flat.grouping, Grouping::All);
+        assert_eq!(
+            describe(&flat),
+            [
+                "starts_with [ci]",
+                "does_not_contain bot",
+                "ends_with @example.com",
+                "is_not a",
+                "is_not b",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_flatten_mixed_grouping() {
+        let filter = parse(
+            "\
+- message_filters:
+    - title: Mixed
+      when:
+        - field: subject
+          starts_with: ['[ci]']
+        - all:
+            - field: from
+              contains: [bot]
+            - not:
+                field: to
+                starts_with: [me]
+      then: []
+",
+        );
+
+        assert_eq!(
+            flatten(filter.grouping, &filter.when),
+            Err(Error::MixedGrouping)
+        );
+
+        let flat = flatten(Grouping::All, &filter.when).unwrap();
+        assert_eq!(
+            describe(&flat),
+            ["starts_with [ci]", "contains bot", "not starts_with me"]
+        );
+    }
+
+    #[test]
+    fn test_flatten_state() {
+        let filter = parse(
+            "\
+- message_filters:
+    - title: Old
+      match: all
+      when:
+        - has_attachment: false
+        - not:
+            junk: false
+        - not:
+            size: { greater_than: 5MB }
+      then: []
+",
+        );
+        let flat = flatten(filter.grouping, &filter.when).unwrap();
+
+        assert_eq!(describe(&flat), ["not has_attachment", "junk", "not size"]);
+    }
+
+    use super::*;
+    use crate::configuration::MessageFilter;
+}
+
+use crate::configuration::{Field, Grouping, Matcher, StateTest, When};
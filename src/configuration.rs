@@ -1,60 +1,1590 @@
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error(transparent)]
+    Yaml(#[from] serde_yaml::Error),
+    #[error("{}{message}", helpers::format_position(.position))]
+    Invalid {
+        message: String,
+        position: Option<Position>,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
 #[serde(transparent)]
 pub struct Configuration {
     pub accounts: Vec<Account>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(deny_unknown_fields)]
 pub struct Account {
+    /// Human readable label, used in diagnostics.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub account: Option<String>,
+    /// Address of the account, which stands in for a missing `evolution_id` (looked up in
+    /// Evolution's sources) and, with `imap_host`, for a missing `thunderbird_id`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    /// Host name of the IMAP server, as configured in Thunderbird.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub imap_host: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub evolution_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub thunderbird_id: Option<String>,
+    /// Where `print thunderbird --out-dir` writes this account's filters, relative to the
+    /// output directory and without `..`. Defaults to `<thunderbird_id>/msgFilterRules.dat`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thunderbird_path: Option<String>,
+    /// Mailbox hierarchy separator used by the Sieve printer, defaults to `/`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sieve_separator: Option<String>,
+    /// Reply templates by name, used by `reply_with`. Values are Thunderbird template URIs.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub templates: BTreeMap<String, String>,
     pub message_filters: Vec<MessageFilter>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(deny_unknown_fields)]
 pub struct MessageFilter {
     pub title: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Disabled filters are still written out, so they can be enabled in the client.
+    #[serde(
+        default = "helpers::default_enabled",
+        skip_serializing_if = "helpers::is_enabled"
+    )]
+    pub enabled: bool,
+    /// When the client runs this filter, defaults to incoming mail and on demand.
+    #[serde(
+        default = "helpers::default_run_on",
+        skip_serializing_if = "helpers::is_default_run_on"
+    )]
+    pub run_on: Vec<RunOn>,
+    /// Whether all or any of the conditions in `when` must hold, defaults to `any`.
+    #[serde(rename = "match", default, skip_serializing_if = "Grouping::is_any")]
+    pub grouping: Grouping,
     pub when: Vec<When>,
     pub then: Vec<Then>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Copy, Clone, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum RunOn {
+    Incoming,
+    Manual,
+    AfterSending,
+    Periodic,
+    Archiving,
+}
+
+impl RunOn {
+    /// Returns the configuration name of this trigger, e.g. `after_sending`.
+    pub fn key(self) -> &'static str {
+        match self {
+            RunOn::Incoming => "incoming",
+            RunOn::Manual => "manual",
+            RunOn::AfterSending => "after_sending",
+            RunOn::Periodic => "periodic",
+            RunOn::Archiving => "archiving",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Copy, Clone, PartialEq, Eq, Default, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Grouping {
+    All,
+    #[default]
+    Any,
+}
+
+impl Grouping {
+    pub fn is_any(&self) -> bool {
+        *self == Grouping::Any
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
 #[serde(untagged)]
 pub enum When {
+    All(All),
+    Any(Any),
+    Not(Not),
     Contains(Contains),
+    DoesNotContain(DoesNotContain),
+    Is(Is),
+    IsNot(IsNot),
+    StartsWith(StartsWith),
     EndsWith(EndsWith),
+    DoesNotEndWith(DoesNotEndWith),
+    MatchesRegex(MatchesRegex),
+    MatchesGlob(MatchesGlob),
+    Size(Size),
+    AgeDays(AgeDays),
+    Date(DateTest),
+    Status(StatusTest),
+    HasAttachment(HasAttachment),
+    Priority(PriorityTest),
+    Junk(Junk),
+    Tag(TagTest),
+}
+
+impl When {
+    /// Returns the field, matcher and values of this condition, `None` for groups.
+    pub fn string_test(&self) -> Option<StringTest<'_>> {
+        let (field, matcher, values) = match self {
+            When::All(_) | When::Any(_) | When::Not(_) => return None,
+            When::Size(_)
+            | When::AgeDays(_)
+            | When::Date(_)
+            | When::Status(_)
+            | When::HasAttachment(_)
+            | When::Priority(_)
+            | When::Junk(_)
+            | When::Tag(_) => return None,
+            When::Contains(cond) => (&cond.field, Matcher::Contains, &cond.values),
+            When::DoesNotContain(cond) => (&cond.field, Matcher::DoesNotContain, &cond.values),
+            When::Is(cond) => (&cond.field, Matcher::Is, &cond.values),
+            When::IsNot(cond) => (&cond.field, Matcher::IsNot, &cond.values),
+            When::StartsWith(cond) => (&cond.field, Matcher::StartsWith, &cond.values),
+            When::EndsWith(cond) => (&cond.field, Matcher::EndsWith, &cond.values),
+            When::DoesNotEndWith(cond) => (&cond.field, Matcher::DoesNotEndWith, &cond.values),
+            When::MatchesRegex(cond) => (&cond.field, Matcher::MatchesRegex, &cond.values),
+            When::MatchesGlob(cond) => (&cond.field, Matcher::MatchesGlob, &cond.values),
+        };
+
+        Some(StringTest {
+            field,
+            matcher,
+            values,
+        })
+    }
+
+    /// Builds the condition testing `field` with `matcher`, the inverse of
+    /// [`When::string_test`].
+    pub fn from_string_test(field: Field, matcher: Matcher, values: Vec<String>) -> When {
+        match matcher {
+            Matcher::Contains => When::Contains(Contains { field, values }),
+            Matcher::DoesNotContain => When::DoesNotContain(DoesNotContain { field, values }),
+            Matcher::Is => When::Is(Is { field, values }),
+            Matcher::IsNot => When::IsNot(IsNot { field, values }),
+            Matcher::StartsWith => When::StartsWith(StartsWith { field, values }),
+            Matcher::EndsWith => When::EndsWith(EndsWith { field, values }),
+            Matcher::DoesNotEndWith => When::DoesNotEndWith(DoesNotEndWith { field, values }),
+            Matcher::MatchesRegex => When::MatchesRegex(MatchesRegex { field, values }),
+            Matcher::MatchesGlob => When::MatchesGlob(MatchesGlob { field, values }),
+        }
+    }
+
+    /// Returns the message state this condition tests and whether it should hold, `None` for
+    /// string conditions and groups.
+    pub fn state_test(&self) -> Option<(StateTest<'_>, bool)> {
+        let test = match self {
+            When::Size(size) => (StateTest::Size(size.size), true),
+            When::AgeDays(age) => (StateTest::AgeDays(age.age_days), true),
+            When::Date(date) => (StateTest::Date(date.date), true),
+            When::Status(status) => (StateTest::Status(status.status), true),
+            When::HasAttachment(has) => (StateTest::HasAttachment, has.has_attachment),
+            When::Priority(priority) => (StateTest::Priority(priority.priority), true),
+            When::Junk(junk) => (StateTest::Junk, junk.junk),
+            When::Tag(tag) => (StateTest::Tag(&tag.tag), true),
+            _ => return None,
+        };
+        Some(test)
+    }
+}
+
+/// A condition on the state of a message rather than on one of its headers.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum StateTest<'a> {
+    Size(SizeComparison),
+    AgeDays(AgeComparison),
+    Date(DateComparison),
+    Status(Status),
+    HasAttachment,
+    Priority(Priority),
+    Junk,
+    Tag(&'a str),
+}
+
+impl StateTest<'_> {
+    /// Returns the configuration key of this condition, e.g. `has_attachment`.
+    pub fn key(&self) -> &'static str {
+        match self {
+            StateTest::Size(_) => "size",
+            StateTest::AgeDays(_) => "age_days",
+            StateTest::Date(_) => "date",
+            StateTest::Status(_) => "status",
+            StateTest::HasAttachment => "has_attachment",
+            StateTest::Priority(_) => "priority",
+            StateTest::Junk => "junk",
+            StateTest::Tag(_) => "tag",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Size {
+    #[serde(with = "serde_yaml::with::singleton_map")]
+    pub size: SizeComparison,
+}
+
+#[derive(Serialize, Deserialize, Copy, Clone, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum SizeComparison {
+    GreaterThan(ByteSize),
+    LessThan(ByteSize),
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct AgeDays {
+    #[serde(with = "serde_yaml::with::singleton_map")]
+    pub age_days: AgeComparison,
+}
+
+#[derive(Serialize, Deserialize, Copy, Clone, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum AgeComparison {
+    MoreThan(u32),
+    LessThan(u32),
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct DateTest {
+    #[serde(with = "serde_yaml::with::singleton_map")]
+    pub date: DateComparison,
+}
+
+#[derive(Serialize, Deserialize, Copy, Clone, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum DateComparison {
+    Before(Date),
+    After(Date),
+    On(Date),
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct StatusTest {
+    pub status: Status,
+}
+
+#[derive(Serialize, Deserialize, Copy, Clone, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Status {
+    Read,
+    Flagged,
+    Replied,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct HasAttachment {
+    pub has_attachment: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct PriorityTest {
+    pub priority: Priority,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Junk {
+    pub junk: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct TagTest {
+    pub tag: String,
+}
+
+/// A number of bytes, written as a plain number or with a `KB`, `MB` or `GB` suffix
+/// (powers of 1024), e.g. `5MB`.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub struct ByteSize(pub u64);
+
+impl ByteSize {
+    /// Returns the size in kilobytes, rounded up.
+    pub fn kilobytes(self) -> u64 {
+        self.0.div_ceil(1024)
+    }
+}
+
+impl std::str::FromStr for ByteSize {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let trimmed = value.trim();
+        let split = trimmed
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(trimmed.len());
+        let (number, unit) = trimmed.split_at(split);
+        let scale: u64 = match unit.trim().to_ascii_uppercase().as_str() {
+            "" | "B" => 1,
+            "K" | "KB" => 1 << 10,
+            "M" | "MB" => 1 << 20,
+            "G" | "GB" => 1 << 30,
+            _ => {
+                return Err(format!(
+                    "invalid size `{value}`, expected e.g. `500KB` or `5MB`"
+                ))
+            }
+        };
+        number
+            .parse::<u64>()
+            .ok()
+            .and_then(|number| number.checked_mul(scale))
+            .map(ByteSize)
+            .ok_or_else(|| format!("invalid size `{value}`, expected e.g. `500KB` or `5MB`"))
+    }
+}
+
+impl std::fmt::Display for ByteSize {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let units = [(1 << 30, "GB"), (1 << 20, "MB"), (1 << 10, "KB")];
+        match units
+            .iter()
+            .find(|(scale, _)| self.0 != 0 && self.0.is_multiple_of(*scale))
+        {
+            Some((scale, unit)) => write!(f, "{}{unit}", self.0 / scale),
+            None => write!(f, "{}", self.0),
+        }
+    }
+}
+
+impl Serialize for ByteSize {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for ByteSize {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        match helpers::NumberOrString::deserialize(deserializer)? {
+            helpers::NumberOrString::Number(bytes) => Ok(ByteSize(bytes)),
+            helpers::NumberOrString::String(value) => {
+                value.parse().map_err(serde::de::Error::custom)
+            }
+        }
+    }
+}
+
+/// A calendar date, written as `YYYY-MM-DD`.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub struct Date {
+    pub year: i32,
+    pub month: u32,
+    pub day: u32,
+}
+
+impl Date {
+    /// Returns the number of days since 1970-01-01.
+    pub fn days_since_epoch(self) -> i64 {
+        // Howard Hinnant's days_from_civil.
+        let year = i64::from(self.year) - i64::from(self.month <= 2);
+        let era = year.div_euclid(400);
+        let year_of_era = year - era * 400;
+        let month = i64::from(self.month);
+        let day_of_year =
+            (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + i64::from(self.day) - 1;
+        let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+        era * 146_097 + day_of_era - 719_468
+    }
+
+    /// Returns the date `days` days after 1970-01-01, the inverse of [`Date::days_since_epoch`].
+    pub fn from_days_since_epoch(days: i64) -> Date {
+        // Howard Hinnant's civil_from_days.
+        let days = days + 719_468;
+        let era = days.div_euclid(146_097);
+        let day_of_era = days - era * 146_097;
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let month_index = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+        let month = if month_index < 10 {
+            month_index + 3
+        } else {
+            month_index - 9
+        };
+        Date {
+            year: (year_of_era + era * 400 + i64::from(month <= 2)) as i32,
+            month: month as u32,
+            day: day as u32,
+        }
+    }
+}
+
+impl std::str::FromStr for Date {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid date `{value}`, expected `YYYY-MM-DD`");
+        let mut parts = value.trim().splitn(3, '-');
+        let mut next = || parts.next().and_then(|part| part.parse::<u32>().ok());
+        let (Some(year), Some(month), Some(day)) = (next(), next(), next()) else {
+            return Err(invalid());
+        };
+        let date = Date {
+            year: i32::try_from(year).map_err(|_| invalid())?,
+            month,
+            day,
+        };
+
+        let leap = date.year % 4 == 0 && (date.year % 100 != 0 || date.year % 400 == 0);
+        let days_in_month = match date.month {
+            1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+            4 | 6 | 9 | 11 => 30,
+            2 if leap => 29,
+            2 => 28,
+            _ => return Err(invalid()),
+        };
+        if !(1..=days_in_month).contains(&date.day) {
+            return Err(invalid());
+        }
+        Ok(date)
+    }
+}
+
+impl std::fmt::Display for Date {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+impl Serialize for Date {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Date {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct All {
+    #[serde(rename = "all")]
+    pub conditions: Vec<When>,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Any {
+    #[serde(rename = "any")]
+    pub conditions: Vec<When>,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Not {
+    #[serde(rename = "not")]
+    pub condition: Box<When>,
+}
+
+/// A condition testing a [`Field`] against a list of values. The condition holds when any of
+/// the values matches, or, for negated matchers, when none of them do.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct StringTest<'a> {
+    pub field: &'a Field,
+    pub matcher: Matcher,
+    pub values: &'a [String],
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum Matcher {
+    Contains,
+    DoesNotContain,
+    Is,
+    IsNot,
+    StartsWith,
+    EndsWith,
+    DoesNotEndWith,
+    MatchesRegex,
+    /// Shell-style pattern where `*` matches any run of characters and `?` a single one.
+    MatchesGlob,
+}
+
+impl Matcher {
+    /// Returns the configuration key of this matcher, e.g. `ends_with`.
+    pub fn key(self) -> &'static str {
+        match self {
+            Matcher::Contains => "contains",
+            Matcher::DoesNotContain => "does_not_contain",
+            Matcher::Is => "is",
+            Matcher::IsNot => "is_not",
+            Matcher::StartsWith => "starts_with",
+            Matcher::EndsWith => "ends_with",
+            Matcher::DoesNotEndWith => "does_not_end_with",
+            Matcher::MatchesRegex => "matches_regex",
+            Matcher::MatchesGlob => "matches_glob",
+        }
+    }
+
+    /// Returns `true` for the `does_not_*`/`is_not` matchers.
+    pub fn is_negated(self) -> bool {
+        matches!(
+            self,
+            Matcher::DoesNotContain | Matcher::IsNot | Matcher::DoesNotEndWith
+        )
+    }
+
+    /// Returns the matcher with the opposite meaning, if there is one.
+    pub fn negate(self) -> Option<Matcher> {
+        match self {
+            Matcher::Contains => Some(Matcher::DoesNotContain),
+            Matcher::DoesNotContain => Some(Matcher::Contains),
+            Matcher::Is => Some(Matcher::IsNot),
+            Matcher::IsNot => Some(Matcher::Is),
+            Matcher::EndsWith => Some(Matcher::DoesNotEndWith),
+            Matcher::DoesNotEndWith => Some(Matcher::EndsWith),
+            Matcher::StartsWith | Matcher::MatchesRegex | Matcher::MatchesGlob => None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(deny_unknown_fields)]
 pub struct EndsWith {
+    #[serde(with = "serde_yaml::with::singleton_map")]
     pub field: Field,
     #[serde(rename = "ends_with")]
     pub values: Vec<String>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(deny_unknown_fields)]
 pub struct Contains {
+    #[serde(with = "serde_yaml::with::singleton_map")]
     pub field: Field,
     #[serde(rename = "contains")]
     pub values: Vec<String>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct DoesNotContain {
+    #[serde(with = "serde_yaml::with::singleton_map")]
+    pub field: Field,
+    #[serde(rename = "does_not_contain")]
+    pub values: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Is {
+    #[serde(with = "serde_yaml::with::singleton_map")]
+    pub field: Field,
+    #[serde(rename = "is")]
+    pub values: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct IsNot {
+    #[serde(with = "serde_yaml::with::singleton_map")]
+    pub field: Field,
+    #[serde(rename = "is_not")]
+    pub values: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct StartsWith {
+    #[serde(with = "serde_yaml::with::singleton_map")]
+    pub field: Field,
+    #[serde(rename = "starts_with")]
+    pub values: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct DoesNotEndWith {
+    #[serde(with = "serde_yaml::with::singleton_map")]
+    pub field: Field,
+    #[serde(rename = "does_not_end_with")]
+    pub values: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct MatchesRegex {
+    #[serde(with = "serde_yaml::with::singleton_map")]
+    pub field: Field,
+    #[serde(rename = "matches_regex")]
+    pub values: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct MatchesGlob {
+    #[serde(with = "serde_yaml::with::singleton_map")]
+    pub field: Field,
+    #[serde(rename = "matches_glob")]
+    pub values: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Hash, Debug)]
+#[serde(rename_all = "snake_case")]
 pub enum Field {
-    #[serde(rename = "from")]
     From,
+    To,
+    Cc,
+    /// Either the `To` or the `Cc` header.
+    ToOrCc,
+    Bcc,
+    ReplyTo,
+    Subject,
+    Body,
+    ListId,
+    Sender,
+    /// An arbitrary header, written as `field: { header: X-Whatever }`.
+    Header(String),
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+impl Field {
+    /// Returns the name of the header this field reads, `None` for the body and `to_or_cc`.
+    pub fn header_name(&self) -> Option<&str> {
+        match self {
+            Field::From => Some("From"),
+            Field::To => Some("To"),
+            Field::Cc => Some("Cc"),
+            Field::ToOrCc | Field::Body => None,
+            Field::Bcc => Some("Bcc"),
+            Field::ReplyTo => Some("Reply-To"),
+            Field::Subject => Some("Subject"),
+            Field::ListId => Some("List-Id"),
+            Field::Sender => Some("Sender"),
+            Field::Header(name) => Some(name),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
 #[serde(untagged)]
 pub enum Then {
     MoveTo(MoveTo),
+    CopyTo(CopyTo),
+    Tag(Tag),
+    SetPriority(SetPriority),
+    Forward(Forward),
+    ReplyWith(ReplyWith),
+    RunProgram(RunProgram),
+    Simple(SimpleAction),
+}
+
+impl Then {
+    /// Returns the configuration key of this action, e.g. `move_to` or `mark_read`.
+    pub fn key(&self) -> &'static str {
+        match self {
+            Then::MoveTo(_) => "move_to",
+            Then::CopyTo(_) => "copy_to",
+            Then::Tag(_) => "tag",
+            Then::SetPriority(_) => "set_priority",
+            Then::Forward(_) => "forward",
+            Then::ReplyWith(_) => "reply_with",
+            Then::RunProgram(_) => "run_program",
+            Then::Simple(action) => action.key(),
+        }
+    }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(deny_unknown_fields)]
 pub struct MoveTo {
     #[serde(rename = "move_to")]
     pub folder: String,
 }
 
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct CopyTo {
+    #[serde(rename = "copy_to")]
+    pub folder: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Tag {
+    /// The client's tag (Thunderbird) or label (Evolution) key, e.g. `$label1`.
+    #[serde(rename = "tag", alias = "label")]
+    pub tag: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct SetPriority {
+    #[serde(rename = "set_priority")]
+    pub priority: Priority,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Forward {
+    #[serde(rename = "forward")]
+    pub to: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct ReplyWith {
+    /// Name of a template in the account's `templates`.
+    #[serde(rename = "reply_with")]
+    pub template: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct RunProgram {
+    #[serde(rename = "run_program")]
+    pub command: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub args: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Copy, Clone, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Priority {
+    Lowest,
+    Low,
+    Normal,
+    High,
+    Highest,
+}
+
+impl Priority {
+    /// Returns the configuration name of this priority, e.g. `high`.
+    pub fn key(self) -> &'static str {
+        match self {
+            Priority::Lowest => "lowest",
+            Priority::Low => "low",
+            Priority::Normal => "normal",
+            Priority::High => "high",
+            Priority::Highest => "highest",
+        }
+    }
+}
+
+/// Actions without arguments, written as a bare string, e.g. `- mark_read`.
+#[derive(Serialize, Deserialize, Copy, Clone, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum SimpleAction {
+    Delete,
+    MarkRead,
+    MarkUnread,
+    Flag,
+    MarkJunk,
+    MarkNotJunk,
+    StopProcessing,
+}
+
+impl SimpleAction {
+    /// Returns the configuration name of this action, e.g. `mark_read`.
+    pub fn key(self) -> &'static str {
+        match self {
+            SimpleAction::Delete => "delete",
+            SimpleAction::MarkRead => "mark_read",
+            SimpleAction::MarkUnread => "mark_unread",
+            SimpleAction::Flag => "flag",
+            SimpleAction::MarkJunk => "mark_junk",
+            SimpleAction::MarkNotJunk => "mark_not_junk",
+            SimpleAction::StopProcessing => "stop_processing",
+        }
+    }
+}
+
+/// Parses a YAML configuration, rejecting unknown keys with a suggestion and a source location.
+pub fn parse(source: &str) -> Result<Configuration, Error> {
+    let value: serde_yaml::Value = serde_yaml::from_str(source)?;
+    helpers::check_schema(&value, &SourceMap::parse(source))?;
+
+    Ok(serde_yaml::from_value(value)?)
+}
+
+const ACCOUNT_KEYS: &[&str] = &[
+    "account",
+    "email",
+    "imap_host",
+    "evolution_id",
+    "thunderbird_id",
+    "thunderbird_path",
+    "sieve_separator",
+    "templates",
+    "message_filters",
+];
+const FILTER_KEYS: &[&str] = &[
+    "title",
+    "description",
+    "enabled",
+    "run_on",
+    "match",
+    "when",
+    "then",
+];
+const GROUP_KEYS: &[&str] = &["all", "any", "not"];
+const CONDITION_KEYS: &[&str] = &["field"];
+const STATE_KEYS: &[&str] = &[
+    "size",
+    "age_days",
+    "date",
+    "status",
+    "has_attachment",
+    "priority",
+    "junk",
+    "tag",
+];
+const MATCHER_KEYS: &[&str] = &[
+    "contains",
+    "does_not_contain",
+    "is",
+    "is_not",
+    "starts_with",
+    "ends_with",
+    "does_not_end_with",
+    "matches_regex",
+    "matches_glob",
+];
+const FIELD_NAMES: &[&str] = &[
+    "from", "to", "cc", "to_or_cc", "bcc", "reply_to", "subject", "body", "list_id", "sender",
+    "header",
+];
+const ACTION_KEYS: &[&str] = &[
+    "move_to",
+    "copy_to",
+    "tag",
+    "label",
+    "set_priority",
+    "forward",
+    "reply_with",
+    "run_program",
+];
+const ACTION_ARGUMENT_KEYS: &[&str] = &["args"];
+const SIMPLE_ACTIONS: &[&str] = &[
+    "delete",
+    "mark_read",
+    "mark_unread",
+    "flag",
+    "mark_junk",
+    "mark_not_junk",
+    "stop_processing",
+];
+
+mod helpers {
+
+    /// Sizes may be written as a plain number of bytes or as a string with a unit.
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    pub enum NumberOrString {
+        Number(u64),
+        String(String),
+    }
+
+    pub fn default_enabled() -> bool {
+        true
+    }
+
+    pub fn is_enabled(enabled: &bool) -> bool {
+        *enabled
+    }
+
+    pub fn default_run_on() -> Vec<RunOn> {
+        vec![RunOn::Incoming, RunOn::Manual]
+    }
+
+    pub fn is_default_run_on(run_on: &[RunOn]) -> bool {
+        run_on == default_run_on()
+    }
+
+    /// Walks the raw YAML and reports the first unknown or missing key. Anything that isn't
+    /// shaped like a configuration at all is left to the deserializer to report.
+    pub fn check_schema(value: &Value, source: &SourceMap) -> Result<(), Error> {
+        let Some(accounts) = value.as_sequence() else {
+            return Ok(());
+        };
+
+        for (account_index, account) in accounts.iter().enumerate() {
+            let Some(account) = account.as_mapping() else {
+                continue;
+            };
+            let account_path = path![account_index];
+            let context = match account.get("account").and_then(Value::as_str) {
+                Some(name) => format!("account '{name}'"),
+                None => format!("account #{}", account_index + 1),
+            };
+            check_keys(account, ACCOUNT_KEYS, &account_path, &context, source)?;
+            if let Some(thunderbird_path) = account.get("thunderbird_path").and_then(Value::as_str)
+            {
+                check_relative_path(
+                    thunderbird_path,
+                    "thunderbird_path",
+                    &account_path,
+                    &context,
+                    source,
+                )?;
+            }
+
+            let filters = account
+                .get("message_filters")
+                .and_then(Value::as_sequence)
+                .into_iter()
+                .flatten();
+            for (filter_index, filter) in filters.enumerate() {
+                let Some(filter) = filter.as_mapping() else {
+                    continue;
+                };
+                let filter_path =
+                    [&account_path[..], &path!["message_filters", filter_index]].concat();
+                let context = match filter.get("title").and_then(Value::as_str) {
+                    Some(title) => format!("filter '{title}'"),
+                    None => format!("filter #{} of {context}", filter_index + 1),
+                };
+                check_keys(filter, FILTER_KEYS, &filter_path, &context, source)?;
+
+                check_conditions(
+                    filter.get("when"),
+                    &[&filter_path[..], &path!["when"]].concat(),
+                    &context,
+                    source,
+                )?;
+                check_actions(
+                    filter.get("then"),
+                    &[&filter_path[..], &path!["then"]].concat(),
+                    &context,
+                    source,
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks every action in a `then` list, which is either a bare action name or a mapping
+    /// with exactly one action key.
+    fn check_actions(
+        actions: Option<&Value>,
+        path: &[Segment],
+        context: &str,
+        source: &SourceMap,
+    ) -> Result<(), Error> {
+        let items = actions.and_then(Value::as_sequence).into_iter().flatten();
+        for (index, item) in items.enumerate() {
+            let item_path = [path, &path![index]].concat();
+            match item {
+                Value::String(name) if !SIMPLE_ACTIONS.contains(&name.as_str()) => {
+                    let mut message = format!("unknown action `{name}` in {context}");
+                    if let Some(suggestion) = suggest(name, &[SIMPLE_ACTIONS, ACTION_KEYS].concat())
+                    {
+                        message.push_str(&format!(", did you mean `{suggestion}`?"));
+                    }
+                    return Err(Error::Invalid {
+                        message,
+                        position: source.position(&item_path),
+                    });
+                }
+                Value::Mapping(action) => {
+                    if let Some(name) = SIMPLE_ACTIONS.iter().find(|key| action.contains_key(**key))
+                    {
+                        return Err(Error::Invalid {
+                            message: format!(
+                                "`{name}` in {context} takes no value, write `- {name}` instead"
+                            ),
+                            position: source.position(&[&item_path[..], &path![*name]].concat()),
+                        });
+                    }
+                    check_keys(
+                        action,
+                        &[ACTION_KEYS, ACTION_ARGUMENT_KEYS].concat(),
+                        &item_path,
+                        context,
+                        source,
+                    )?;
+                    check_exclusive(action, ACTION_KEYS, "then", &item_path, context, source)?;
+                    if action.contains_key("args") && !action.contains_key("run_program") {
+                        return Err(Error::Invalid {
+                            message: format!("`args` in {context} only applies to `run_program`"),
+                            position: source.position(&[&item_path[..], &path!["args"]].concat()),
+                        });
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks every condition in a `when`, `all` or `any` list.
+    fn check_conditions(
+        conditions: Option<&Value>,
+        path: &[Segment],
+        context: &str,
+        source: &SourceMap,
+    ) -> Result<(), Error> {
+        let items = conditions
+            .and_then(Value::as_sequence)
+            .into_iter()
+            .flatten();
+        for (index, item) in items.enumerate() {
+            if let Some(condition) = item.as_mapping() {
+                check_condition(condition, &[path, &path![index]].concat(), context, source)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks a single condition, which is either a group (`all`, `any`, `not`) or a field
+    /// with exactly one matcher.
+    fn check_condition(
+        condition: &Mapping,
+        path: &[Segment],
+        context: &str,
+        source: &SourceMap,
+    ) -> Result<(), Error> {
+        if let Some(group) = GROUP_KEYS.iter().find(|key| condition.contains_key(**key)) {
+            check_keys(condition, &[group], path, context, source)?;
+
+            let group_path = [path, &path![*group]].concat();
+            return match condition.get(group) {
+                Some(Value::Mapping(inner)) => check_condition(inner, &group_path, context, source),
+                inner => check_conditions(inner, &group_path, context, source),
+            };
+        }
+
+        if let Some(state) = STATE_KEYS.iter().find(|key| condition.contains_key(**key)) {
+            return check_keys(condition, &[state], path, context, source);
+        }
+
+        check_keys(
+            condition,
+            &[CONDITION_KEYS, MATCHER_KEYS, GROUP_KEYS, STATE_KEYS].concat(),
+            path,
+            context,
+            source,
+        )?;
+        check_exclusive(condition, MATCHER_KEYS, "when", path, context, source)?;
+        check_field(
+            condition.get("field"),
+            &[path, &path!["field"]].concat(),
+            context,
+            source,
+        )
+    }
+
+    /// Requires exactly one of the `exclusive` keys in a `name` entry.
+    fn check_exclusive(
+        item: &Mapping,
+        exclusive: &[&str],
+        name: &str,
+        path: &[Segment],
+        context: &str,
+        source: &SourceMap,
+    ) -> Result<(), Error> {
+        let found: Vec<&str> = exclusive
+            .iter()
+            .copied()
+            .filter(|key| item.contains_key(*key))
+            .collect();
+        if found.len() == 1 {
+            return Ok(());
+        }
+
+        let expected = format_keys(exclusive);
+        let message = match found.len() {
+            0 => format!("`{name}` entry in {context} needs one of {expected}"),
+            _ => format!(
+                "`{name}` entry in {context} has {}, expected only one of {expected}",
+                format_keys(&found)
+            ),
+        };
+        Err(Error::Invalid {
+            message,
+            position: source.position(path),
+        })
+    }
+
+    /// Checks the `field` of a condition, which is either a name or `{ header: NAME }`.
+    fn check_field(
+        field: Option<&Value>,
+        path: &[Segment],
+        context: &str,
+        source: &SourceMap,
+    ) -> Result<(), Error> {
+        match field {
+            Some(Value::String(name)) if name == "header" => Err(Error::Invalid {
+                message: format!(
+                    "`header` in {context} needs a name, e.g. `field: {{ header: X-Whatever }}`"
+                ),
+                position: source.position(path),
+            }),
+            Some(Value::String(name)) if !FIELD_NAMES.contains(&name.as_str()) => {
+                let mut message = format!("unknown field `{name}` in {context}");
+                if let Some(suggestion) = suggest(name, FIELD_NAMES) {
+                    message.push_str(&format!(", did you mean `{suggestion}`?"));
+                }
+                Err(Error::Invalid {
+                    message,
+                    position: source.position(path),
+                })
+            }
+            Some(Value::Mapping(field)) => check_keys(field, &["header"], path, context, source),
+            _ => Ok(()),
+        }
+    }
+
+    /// Checks that the `key` of the mapping at `path` is a relative path without `..`, so that
+    /// joining it to a directory stays inside that directory.
+    fn check_relative_path(
+        value: &str,
+        key: &str,
+        path: &[Segment],
+        context: &str,
+        source: &SourceMap,
+    ) -> Result<(), Error> {
+        let inside = Path::new(value)
+            .components()
+            .all(|component| matches!(component, Component::Normal(_) | Component::CurDir));
+        if inside {
+            return Ok(());
+        }
+        Err(Error::Invalid {
+            message: format!("`{key}` in {context} must be a relative path without `..`"),
+            position: source.position(&[path, &path![key]].concat()),
+        })
+    }
+
+    fn check_keys(
+        mapping: &Mapping,
+        known: &[&str],
+        path: &[Segment],
+        context: &str,
+        source: &SourceMap,
+    ) -> Result<(), Error> {
+        for key in mapping.keys() {
+            let Some(key) = key.as_str() else {
+                continue;
+            };
+            if known.contains(&key) {
+                continue;
+            }
+
+            let mut message = format!("unknown key `{key}` in {context}");
+            if let Some(suggestion) = suggest(key, known) {
+                message.push_str(&format!(", did you mean `{suggestion}`?"));
+            }
+            return Err(Error::Invalid {
+                message,
+                position: source.position(&[path, &path![key]].concat()),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Returns the known key closest to `key`, if it is close enough to be a typo.
+    fn suggest<'a>(key: &str, known: &[&'a str]) -> Option<&'a str> {
+        known
+            .iter()
+            .map(|candidate| (strsim::levenshtein(key, candidate), *candidate))
+            .filter(|(distance, _)| *distance <= 2.max(key.len() / 3))
+            .min_by_key(|(distance, _)| *distance)
+            .map(|(_, candidate)| candidate)
+    }
+
+    fn format_keys(keys: &[&str]) -> String {
+        keys.iter()
+            .map(|key| format!("`{key}`"))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    pub fn format_position(position: &Option<Position>) -> String {
+        position
+            .map(|position| format!("{position}: "))
+            .unwrap_or_default()
+    }
+
+    use std::path::{Component, Path};
+
+    use serde::Deserialize;
+    use serde_yaml::{Mapping, Value};
+
+    use super::{
+        Error, RunOn, ACCOUNT_KEYS, ACTION_ARGUMENT_KEYS, ACTION_KEYS, CONDITION_KEYS, FIELD_NAMES,
+        FILTER_KEYS, GROUP_KEYS, MATCHER_KEYS, SIMPLE_ACTIONS, STATE_KEYS,
+    };
+    use crate::{
+        path,
+        source::{Position, Segment, SourceMap},
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    fn parse_err(source: &str) -> String {
+        parse(source).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn test_parse_example() {
+        let config = parse(include_str!("../example.yaml")).unwrap();
+        assert_eq!(
+            config.accounts[0].account.as_deref(),
+            Some("jdoe@example.com")
+        );
+    }
+
+    #[test]
+    fn test_unknown_matcher_key() {
+        let source = "\
+- account: jdoe@example.com
+  message_filters:
+    - title: Github
+      when:
+        - field: from
+          ends_whit: ['@github.com']
+      then:
+        - move_to: Github
+";
+        assert_eq!(
+            parse_err(source),
+            "6:11: unknown key `ends_whit` in filter 'Github', did you mean `ends_with`?"
+        );
+    }
+
+    #[test]
+    fn test_unknown_account_key() {
+        let source = "\
+- evolution: abc
+  colour: red
+  message_filters: []
+";
+        assert_eq!(
+            parse_err(source),
+            "1:3: unknown key `evolution` in account #1, did you mean `evolution_id`?"
+        );
+
+        let source = "\
+- colour: red
+  message_filters: []
+";
+        assert_eq!(parse_err(source), "1:3: unknown key `colour` in account #1");
+    }
+
+    #[test]
+    fn test_thunderbird_path_outside_the_directory() {
+        for thunderbird_path in ["../../../tmp/evil", "/tmp/evil", "ImapMail/../../evil"] {
+            let source = format!(
+                "\
+- thunderbird_id: work
+  thunderbird_path: {thunderbird_path}
+  message_filters: []
+"
+            );
+            assert_eq!(
+                parse_err(&source),
+                "2:3: `thunderbird_path` in account #1 must be a relative path without `..`"
+            );
+        }
+
+        let source = "\
+- thunderbird_id: work
+  thunderbird_path: ./ImapMail/work/msgFilterRules.dat
+  message_filters: []
+";
+        assert!(parse(source).is_ok());
+    }
+
+    #[test]
+    fn test_missing_and_conflicting_matchers() {
+        let source = "\
+- message_filters:
+    - title: Github
+      when:
+        - field: from
+      then: []
+";
+        assert_eq!(
+            parse_err(source),
+            "4:11: `when` entry in filter 'Github' needs one of `contains`, `does_not_contain`, `is`, `is_not`, `starts_with`, `ends_with`, `does_not_end_with`, `matches_regex`, `matches_glob`"
+        );
+
+        let source = "\
+- message_filters:
+    - title: Github
+      when:
+        - field: from
+          contains: [a]
+          ends_with: [b]
+      then: []
+";
+        assert_eq!(
+            parse_err(source),
+            "4:11: `when` entry in filter 'Github' has `contains`, `ends_with`, expected only one of `contains`, `does_not_contain`, `is`, `is_not`, `starts_with`, `ends_with`, `does_not_end_with`, `matches_regex`, `matches_glob`"
+        );
+    }
+
+    #[test]
+    fn test_fields() {
+        let source = "\
+- message_filters:
+    - title: Lists
+      when:
+        - field: { header: List-Id }
+          contains: [rust]
+        - field: to_or_cc
+          contains: [me]
+      then: []
+";
+        let config = parse(source).unwrap();
+        let when = &config.accounts[0].message_filters[0].when;
+        assert_eq!(
+            when[0],
+            When::Contains(Contains {
+                field: Field::Header("List-Id".to_owned()),
+                values: vec!["rust".to_owned()],
+            })
+        );
+        assert_eq!(
+            when[1],
+            When::Contains(Contains {
+                field: Field::ToOrCc,
+                values: vec!["me".to_owned()],
+            })
+        );
+
+        let source = "\
+- message_filters:
+    - title: Lists
+      when:
+        - field: subjet
+          contains: [rust]
+      then: []
+";
+        assert_eq!(
+            parse_err(source),
+            "4:11: unknown field `subjet` in filter 'Lists', did you mean `subject`?"
+        );
+    }
+
+    #[test]
+    fn test_nested_conditions() {
+        let source = "\
+- message_filters:
+    - title: Humans
+      match: all
+      when:
+        - not:
+            any:
+              - field: from
+                contains: [bot]
+      then: []
+";
+        let filter = &parse(source).unwrap().accounts[0].message_filters[0];
+        assert_eq!(filter.grouping, Grouping::All);
+        assert_eq!(
+            filter.when[0],
+            When::Not(Not {
+                condition: Box::new(When::Any(Any {
+                    conditions: vec![When::Contains(Contains {
+                        field: Field::From,
+                        values: vec!["bot".to_owned()],
+                    })],
+                })),
+            })
+        );
+
+        let source = "\
+- message_filters:
+    - title: Humans
+      when:
+        - all:
+            - field: from
+              contians: [bot]
+      then: []
+";
+        assert_eq!(
+            parse_err(source),
+            "6:15: unknown key `contians` in filter 'Humans', did you mean `contains`?"
+        );
+
+        let source = "\
+- message_filters:
+    - title: Humans
+      when:
+        - any: []
+          field: from
+      then: []
+";
+        assert_eq!(
+            parse_err(source),
+            "5:11: unknown key `field` in filter 'Humans'"
+        );
+    }
+
+    #[test]
+    fn test_actions() {
+        let source = "\
+- message_filters:
+    - title: Newsletters
+      when: []
+      then:
+        - label: $label1
+        - set_priority: low
+        - mark_read
+";
+        let config = parse(source).unwrap();
+        assert_eq!(
+            config.accounts[0].message_filters[0].then,
+            [
+                Then::Tag(Tag {
+                    tag: "$label1".to_owned()
+                }),
+                Then::SetPriority(SetPriority {
+                    priority: Priority::Low
+                }),
+                Then::Simple(SimpleAction::MarkRead),
+            ]
+        );
+
+        let source = "\
+- message_filters:
+    - title: Newsletters
+      when: []
+      then:
+        - mark_raed
+";
+        assert_eq!(
+            parse_err(source),
+            "5:11: unknown action `mark_raed` in filter 'Newsletters', did you mean `mark_read`?"
+        );
+
+        let source = "\
+- message_filters:
+    - title: Newsletters
+      when: []
+      then:
+        - delete: true
+";
+        assert_eq!(
+            parse_err(source),
+            "5:11: `delete` in filter 'Newsletters' takes no value, write `- delete` instead"
+        );
+
+        let source = "\
+- message_filters:
+    - title: Newsletters
+      when: []
+      then:
+        - forward: jane@example.com
+          args: [x]
+";
+        assert_eq!(
+            parse_err(source),
+            "6:11: `args` in filter 'Newsletters' only applies to `run_program`"
+        );
+    }
+
+    #[test]
+    fn test_state_conditions() {
+        let source = "\
+- message_filters:
+    - title: Old
+      when:
+        - size: { greater_than: 5MB }
+        - age_days: { more_than: 30 }
+        - date: { before: 2026-01-31 }
+        - status: flagged
+        - has_attachment: true
+        - priority: high
+        - junk: false
+        - tag: $label1
+      then: []
+";
+        let config = parse(source).unwrap();
+        assert_eq!(
+            config.accounts[0].message_filters[0].when,
+            [
+                When::Size(Size {
+                    size: SizeComparison::GreaterThan(ByteSize(5 * 1024 * 1024)),
+                }),
+                When::AgeDays(AgeDays {
+                    age_days: AgeComparison::MoreThan(30),
+                }),
+                When::Date(DateTest {
+                    date: DateComparison::Before(Date {
+                        year: 2026,
+                        month: 1,
+                        day: 31,
+                    }),
+                }),
+                When::Status(StatusTest {
+                    status: Status::Flagged,
+                }),
+                When::HasAttachment(HasAttachment {
+                    has_attachment: true,
+                }),
+                When::Priority(PriorityTest {
+                    priority: Priority::High,
+                }),
+                When::Junk(Junk { junk: false }),
+                When::Tag(TagTest {
+                    tag: "$label1".to_owned(),
+                }),
+            ]
+        );
+
+        assert_eq!("1500".parse::<ByteSize>(), Ok(ByteSize(1500)));
+        assert_eq!("2k".parse::<ByteSize>(), Ok(ByteSize(2048)));
+        assert!("5 parsecs".parse::<ByteSize>().is_err());
+        assert!("2026-02-30".parse::<Date>().is_err());
+        assert_eq!(
+            "1970-01-02"
+                .parse::<Date>()
+                .map(|date| date.days_since_epoch()),
+            Ok(1)
+        );
+        for date in ["1969-12-31", "2000-02-29", "2026-10-18"] {
+            let parsed = date.parse::<Date>().unwrap();
+            assert_eq!(
+                Date::from_days_since_epoch(parsed.days_since_epoch()),
+                parsed
+            );
+        }
+
+        let source = "\
+- message_filters:
+    - title: Old
+      when:
+        - junk: true
+          field: from
+      then: []
+";
+        assert_eq!(
+            parse_err(source),
+            "5:11: unknown key `field` in filter 'Old'"
+        );
+    }
+
+    use super::*;
+}
+
+use std::collections::BTreeMap;
+
 use serde::{Deserialize, Serialize};
+
+use crate::source::{Position, SourceMap};
@@ -1,3 +1,19 @@
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum Error {
+    #[error("line {0}: expected `name=\"value\"`")]
+    InvalidLine(usize),
+    #[error("line {0}: unterminated value")]
+    Unterminated(usize),
+}
+
+/// One `name="value"` line of a filter list, with the line it starts on.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DatEntry {
+    pub line: usize,
+    pub name: String,
+    pub value: String,
+}
+
 pub struct DatDocument {
     lines: Vec<String>,
 }
@@ -10,15 +26,103 @@ impl DatDocument {
         doc
     }
 
-    pub fn to_string(&self) -> String {
-        self.lines.join("\n")
+    /// Appends the filters of `other`, leaving out its header.
+    pub fn extend(&mut self, other: DatDocument) {
+        self.lines.extend(other.lines.into_iter().skip(2));
     }
 
     pub fn append<S>(&mut self, name: &str, value: S)
     where
         S: std::fmt::Display,
     {
-        self.lines.push(format!("{name}=\"{value}\""));
+        self.lines
+            .push(format!("{name}=\"{}\"", escape(&value.to_string())));
+    }
+}
+
+/// Reads the entries of a filter list. Values may span several lines, as Thunderbird writes
+/// multi-line descriptions verbatim.
+pub fn parse(source: &str) -> Result<Vec<DatEntry>, Error> {
+    let mut entries = Vec::new();
+    let mut lines = source.lines().enumerate();
+
+    while let Some((index, line)) = lines.next() {
+        let line_number = index + 1;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let Some((name, rest)) = line.split_once('=') else {
+            return Err(Error::InvalidLine(line_number));
+        };
+        let Some(mut rest) = rest.strip_prefix('"').map(str::to_owned) else {
+            return Err(Error::InvalidLine(line_number));
+        };
+
+        let value = loop {
+            if let Some(value) = unescape(&rest) {
+                break value;
+            }
+            let Some((_, next)) = lines.next() else {
+                return Err(Error::Unterminated(line_number));
+            };
+            rest.push('\n');
+            rest.push_str(next);
+        };
+
+        entries.push(DatEntry {
+            line: line_number,
+            name: name.trim().to_owned(),
+            value,
+        });
+    }
+
+    Ok(entries)
+}
+
+/// Splits `entries` into the header before the first filter and the filters, each starting
+/// with its `name`.
+pub fn split_filters(entries: Vec<DatEntry>) -> (Vec<DatEntry>, Vec<Vec<DatEntry>>) {
+    let mut header = Vec::new();
+    let mut filters: Vec<Vec<DatEntry>> = Vec::new();
+    for entry in entries {
+        match filters.last_mut() {
+            _ if entry.name == "name" => filters.push(vec![entry]),
+            Some(filter) => filter.push(entry),
+            None => header.push(entry),
+        }
+    }
+    (header, filters)
+}
+
+/// Reverses [`escape`] up to the closing quote, `None` if `value` has none.
+fn unescape(value: &str) -> Option<String> {
+    let mut unescaped = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => unescaped.push(chars.next()?),
+            '"' => return Some(unescaped),
+            c => unescaped.push(c),
+        }
+    }
+    None
+}
+
+/// Escapes quotes and backslashes the way Thunderbird's filter list reader expects.
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if c == '"' || c == '\\' {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+impl std::fmt::Display for DatDocument {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.lines.join("\n"))
     }
 }
 
@@ -31,5 +135,39 @@ mod tests {
         assert_eq!(doc.lines[0], "foo=\"bar\"");
     }
 
+    #[test]
+    fn append_escapes_quotes_and_backslashes() {
+        let mut doc = DatDocument { lines: Vec::new() };
+        doc.append("condition", r#"AND (from,contains,"a\b")"#);
+        assert_eq!(doc.lines[0], r#"condition="AND (from,contains,\"a\\b\")""#);
+    }
+
+    #[test]
+    fn parse_reads_escaped_and_multiline_values() {
+        let source = "version=\"9\"\nname=\"a \\\"b\\\" \\\\c\"\ndescription=\"one\ntwo\"\n";
+        assert_eq!(
+            parse(source).unwrap(),
+            [
+                DatEntry {
+                    line: 1,
+                    name: "version".to_owned(),
+                    value: "9".to_owned(),
+                },
+                DatEntry {
+                    line: 2,
+                    name: "name".to_owned(),
+                    value: r#"a "b" \c"#.to_owned(),
+                },
+                DatEntry {
+                    line: 3,
+                    name: "description".to_owned(),
+                    value: "one\ntwo".to_owned(),
+                },
+            ]
+        );
+        assert_eq!(parse("name=bare"), Err(Error::InvalidLine(1)));
+        assert_eq!(parse("name=\"open"), Err(Error::Unterminated(1)));
+    }
+
     use super::*;
 }
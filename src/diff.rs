@@ -0,0 +1,443 @@
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("{0} filter(s) differ")]
+    Differs(usize),
+}
+
+/// A filter that differs between the installed file and what the configuration generates.
+#[derive(Debug, PartialEq)]
+pub struct FilterDiff {
+    /// The client's account id, for clients that keep all accounts in one file.
+    pub account: Option<String>,
+    pub title: String,
+    pub change: Change,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Change {
+    /// Generated, but not installed.
+    Added,
+    /// Installed, but no longer generated.
+    Removed,
+    /// Installed with other settings, one line per difference, e.g. `- then: move_to Old`.
+    Changed(Vec<String>),
+    /// Installed, but not written by `install`, which keeps it as it is.
+    Unmanaged,
+}
+
+/// Compares the filters of `installed` with those of `generated`, matching them by title.
+///
+/// Both sides should have gone through the same client's importer, so that what the client
+/// cannot tell apart compares equal.
+pub fn diff_filters(
+    account: Option<&str>,
+    installed: &[MessageFilter],
+    generated: &[MessageFilter],
+) -> Vec<FilterDiff> {
+    let mut diffs = Vec::new();
+    let mut push = |title: &str, change| {
+        diffs.push(FilterDiff {
+            account: account.map(str::to_owned),
+            title: title.to_owned(),
+            change,
+        })
+    };
+
+    for filter in generated {
+        match installed.iter().find(|other| other.title == filter.title) {
+            None => push(&filter.title, Change::Added),
+            Some(other) => {
+                let lines = helpers::compare(other, filter);
+                if !lines.is_empty() {
+                    push(&filter.title, Change::Changed(lines));
+                }
+            }
+        }
+    }
+    for filter in installed {
+        if !generated.iter().any(|other| other.title == filter.title) {
+            push(&filter.title, Change::Removed);
+        }
+    }
+
+    diffs
+}
+
+/// Compares a `msgFilterRules.dat` with the filters `config` generates for it. The filters
+/// `print thunderbird --merge-into` would replace are compared, the others follow as
+/// [`Change::Unmanaged`].
+pub fn diff_thunderbird(mut config: Configuration, installed: &str) -> Result<Vec<FilterDiff>> {
+    config
+        .accounts
+        .retain(|account| resolver::thunderbird_id(account).is_some());
+
+    let unmanaged = printer::thunderbird::unmanaged_filters(installed)?;
+    let installed = printer::thunderbird::managed_filters(installed)?;
+    let generated = printer::thunderbird::print_config(config)?;
+
+    let installed = importer::thunderbird::import(&installed, None)?.config;
+    let generated = importer::thunderbird::import(&generated, None)?.config;
+    let mut diffs = diff_filters(
+        None,
+        &helpers::all_filters(installed),
+        &helpers::all_filters(generated),
+    );
+    diffs.extend(unmanaged.into_iter().map(|title| FilterDiff {
+        account: None,
+        title,
+        change: Change::Unmanaged,
+    }));
+    Ok(diffs)
+}
+
+/// Compares an Evolution `filters.xml` with the rules `config` generates, account by account.
+/// Accounts need an `evolution_id`, see [`resolver::resolve_evolution_ids`].
+pub fn diff_evolution(mut config: Configuration, installed: &str) -> Result<Vec<FilterDiff>> {
+    config
+        .accounts
+        .retain(|account| account.evolution_id.is_some());
+
+    let generated = printer::evolution::print_config(config, false)?;
+
+    let installed = importer::evolution::import(installed, None)?.config;
+    let generated = importer::evolution::import(&generated, None)?.config;
+
+    let mut ids = Vec::new();
+    for account in generated.accounts.iter().chain(&installed.accounts) {
+        if !ids.contains(&account.evolution_id) {
+            ids.push(account.evolution_id.clone());
+        }
+    }
+
+    let filters = |config: &Configuration, id: &Option<String>| {
+        config
+            .accounts
+            .iter()
+            .filter(|account| account.evolution_id == *id)
+            .flat_map(|account| account.message_filters.clone())
+            .collect::<Vec<_>>()
+    };
+    let diffs = ids
+        .iter()
+        .flat_map(|id| {
+            diff_filters(
+                id.as_deref(),
+                &filters(&installed, id),
+                &filters(&generated, id),
+            )
+        })
+        .collect();
+
+    Ok(diffs)
+}
+
+impl std::fmt::Display for FilterDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let sign = match self.change {
+            Change::Added => '+',
+            Change::Removed => '-',
+            Change::Changed(_) => '~',
+            Change::Unmanaged => '?',
+        };
+        write!(f, "{sign} filter '{}'", self.title)?;
+        if let Some(account) = &self.account {
+            write!(f, " in account `{account}`")?;
+        }
+        if self.change == Change::Unmanaged {
+            f.write_str(", not managed by mail-message-filters")?;
+        }
+        if let Change::Changed(lines) = &self.change {
+            for line in lines {
+                write!(f, "\n    {line}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+mod helpers {
+    pub fn all_filters(config: Configuration) -> Vec<MessageFilter> {
+        config
+            .accounts
+            .into_iter()
+            .flat_map(|account| account.message_filters)
+            .collect()
+    }
+
+    /// Lists what differs from `installed` to `generated`: changed settings as
+    /// `name: old -> new`, and conditions and actions that only one of them has as
+    /// `- when: ...` or `+ then: ...`.
+    pub fn compare(installed: &MessageFilter, generated: &MessageFilter) -> Vec<String> {
+        let mut lines = Vec::new();
+        let mut setting = |name: &str, old: String, new: String| {
+            if old != new {
+                lines.push(format!("{name}: {old} -> {new}"));
+            }
+        };
+        setting(
+            "enabled",
+            installed.enabled.to_string(),
+            generated.enabled.to_string(),
+        );
+        setting(
+            "description",
+            format_option(&installed.description),
+            format_option(&generated.description),
+        );
+        setting(
+            "run_on",
+            format_run_on(&installed.run_on),
+            format_run_on(&generated.run_on),
+        );
+        setting(
+            "match",
+            format_grouping(installed.grouping).to_owned(),
+            format_grouping(generated.grouping).to_owned(),
+        );
+
+        let when = |filter: &MessageFilter| filter.when.iter().map(format_when).collect();
+        lines.extend(compare_lists("when", when(installed), when(generated)));
+
+        let then = |filter: &MessageFilter| filter.then.iter().map(engine::format_action).collect();
+        let (old, new): (Vec<_>, Vec<_>) = (then(installed), then(generated));
+        let changed = compare_lists("then", old.clone(), new.clone());
+        if changed.is_empty() && old != new {
+            lines.push(format!("then: {} -> {}", old.join(", "), new.join(", ")));
+        }
+        lines.extend(changed);
+
+        lines
+    }
+
+    /// Returns `- name: item` for each item only in `old` and `+ name: item` for each item only
+    /// in `new`, counting repeated items.
+    fn compare_lists(name: &str, old: Vec<String>, mut new: Vec<String>) -> Vec<String> {
+        let mut removed = Vec::new();
+        for item in old {
+            match new.iter().position(|other| *other == item) {
+                Some(index) => {
+                    new.remove(index);
+                }
+                None => removed.push(format!("- {name}: {item}")),
+            }
+        }
+        removed.extend(new.into_iter().map(|item| format!("+ {name}: {item}")));
+        removed
+    }
+
+    fn format_option(value: &Option<String>) -> String {
+        match value {
+            Some(value) => format_string(value),
+            None => "none".to_owned(),
+        }
+    }
+
+    fn format_run_on(run_on: &[RunOn]) -> String {
+        let keys = run_on.iter().map(|run_on| run_on.key()).collect::<Vec<_>>();
+        format!("[{}]", keys.join(", "))
+    }
+
+    fn format_grouping(grouping: Grouping) -> &'static str {
+        match grouping {
+            Grouping::All => "all",
+            Grouping::Any => "any",
+        }
+    }
+
+    /// Formats a condition on one line, like YAML's flow style, e.g.
+    /// `field: from, ends_with: ['@github.com']`.
+    pub fn format_when(when: &When) -> String {
+        match serde_yaml::to_value(when) {
+            Ok(Value::Mapping(mapping)) => format_entries(&mapping),
+            Ok(value) => format_value(&value),
+            Err(err) => err.to_string(),
+        }
+    }
+
+    fn format_value(value: &Value) -> String {
+        match value {
+            Value::Mapping(mapping) => format!("{{{}}}", format_entries(mapping)),
+            Value::Sequence(items) => {
+                let items = items.iter().map(format_value).collect::<Vec<_>>();
+                format!("[{}]", items.join(", "))
+            }
+            Value::String(value) => format_string(value),
+            Value::Tagged(tagged) => format!("{{{}: {}}}", tagged.tag, format_value(&tagged.value)),
+            Value::Null | Value::Bool(_) | Value::Number(_) => format_scalar(value),
+        }
+    }
+
+    fn format_entries(mapping: &Mapping) -> String {
+        let entries = mapping
+            .iter()
+            .map(|(key, value)| format!("{}: {}", format_value(key), format_value(value)))
+            .collect::<Vec<_>>();
+        entries.join(", ")
+    }
+
+    /// Formats a scalar as YAML would, quoted only where needed.
+    fn format_scalar<T: serde::Serialize + ?Sized>(value: &T) -> String {
+        serde_yaml::to_string(value)
+            .map(|yaml| yaml.trim_end().to_owned())
+            .unwrap_or_default()
+    }
+
+    /// Formats a string for a flow collection, single-quoted where YAML would quote it or where
+    /// it has a flow indicator.
+    fn format_string(value: &str) -> String {
+        let plain = format_scalar(value);
+        if plain == value && !value.contains([',', '[', ']', '{', '}']) {
+            return plain;
+        }
+        format!("'{}'", value.replace('\'', "''"))
+    }
+
+    use serde_yaml::{Mapping, Value};
+
+    use crate::{
+        configuration::{Configuration, Grouping, MessageFilter, RunOn, When},
+        engine,
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    const CONFIG: &str = "\
+- thunderbird_id: thunderbird
+  evolution_id: evolution
+  message_filters:
+    - title: Github
+      when:
+        - field: from
+          ends_with: ['@github.com']
+      then:
+        - move_to: Github
+        - mark_read
+    - title: Spam
+      when:
+        - field: subject
+          contains: ['[SPAM]']
+      then:
+        - delete
+";
+
+    fn changed_config() -> Configuration {
+        let mut config = crate::configuration::parse(CONFIG).unwrap();
+        let filters = &mut config.accounts[0].message_filters;
+        filters[0].enabled = false;
+        filters[0].when.push(When::Contains(Contains {
+            field: Field::Subject,
+            values: vec!["build failed".to_owned()],
+        }));
+        filters[0].then.reverse();
+        filters.remove(1);
+        filters.push(MessageFilter {
+            title: "Amazon".to_owned(),
+            ..filters[0].clone()
+        });
+        config
+    }
+
+    #[test]
+    fn test_diff_thunderbird() {
+        let mine = "version=\"9\"\nlogging=\"no\"\nname=\"Mine\"\nenabled=\"yes\"\ntype=\"1\"\n\
+                    action=\"Delete\"\ncondition=\"ALL\"";
+        let config = crate::configuration::parse(CONFIG).unwrap();
+        let installed = printer::thunderbird::merge_config(config.clone(), mine).unwrap();
+
+        let merged = printer::thunderbird::merge_config(config.clone(), "").unwrap();
+        assert_eq!(diff_thunderbird(config.clone(), &merged).unwrap(), []);
+
+        let diffs = diff_thunderbird(changed_config(), &installed).unwrap();
+        assert_eq!(
+            diffs.iter().map(ToString::to_string).collect::<Vec<_>>(),
+            [
+                "~ filter 'Github'\n    \
+                 enabled: true -> false\n    \
+                 + when: field: subject, contains: [build failed]\n    \
+                 then: move_to Github, mark_read -> mark_read, move_to Github",
+                "+ filter 'Amazon'",
+                "- filter 'Spam'",
+                "? filter 'Mine', not managed by mail-message-filters",
+            ]
+        );
+
+        let printed = printer::thunderbird::print_config(config.clone()).unwrap();
+        assert_eq!(
+            diff_thunderbird(config, &printed)
+                .unwrap()
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            [
+                "+ filter 'Github'",
+                "+ filter 'Spam'",
+                "? filter 'Github', not managed by mail-message-filters",
+                "? filter 'Spam', not managed by mail-message-filters",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_diff_evolution() {
+        let config = crate::configuration::parse(CONFIG).unwrap();
+        let mut installed = Vec::new();
+        printer::evolution::print_document(config.clone())
+            .unwrap()
+            .write(&mut installed, true)
+            .unwrap();
+        let installed = String::from_utf8(installed).unwrap();
+
+        assert_eq!(diff_evolution(config, &installed).unwrap(), []);
+
+        let diffs = diff_evolution(changed_config(), &installed).unwrap();
+        assert_eq!(
+            diffs
+                .iter()
+                .map(|diff| (diff.account.as_deref(), diff.title.as_str()))
+                .collect::<Vec<_>>(),
+            [
+                (Some("evolution"), "Github"),
+                (Some("evolution"), "Amazon"),
+                (Some("evolution"), "Spam"),
+            ]
+        );
+        assert_eq!(
+            diffs[1].to_string(),
+            "+ filter 'Amazon' in account `evolution`"
+        );
+    }
+
+    #[test]
+    fn test_format_when() {
+        let config = crate::configuration::parse(
+            "\
+- thunderbird_id: thunderbird
+  message_filters:
+    - title: Nested
+      when:
+        - not:
+            any:
+              - field: { header: X-Spam }
+                is: ['yes', 'a, b']
+              - size: { greater_than: 1M }
+      then:
+        - delete
+",
+        )
+        .unwrap();
+        assert_eq!(
+            helpers::format_when(&config.accounts[0].message_filters[0].when[0]),
+            "not: {any: [{field: {header: X-Spam}, is: [yes, 'a, b']}, \
+             {size: {greater_than: 1MB}}]}"
+        );
+    }
+
+    use super::*;
+    use crate::configuration::{Contains, Field, When};
+}
+
+use crate::{
+    configuration::{Configuration, MessageFilter},
+    importer, printer, resolver, Result,
+};
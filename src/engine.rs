@@ -0,0 +1,450 @@
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("filter '{filter}': invalid pattern `{pattern}`: {source}")]
+    InvalidPattern {
+        filter: String,
+        pattern: String,
+        source: regex::Error,
+    },
+}
+
+/// A filter that matches a message.
+#[derive(Debug, PartialEq)]
+pub struct Hit<'a> {
+    pub account: &'a Account,
+    pub filter: &'a MessageFilter,
+}
+
+/// Evaluates the filters of a configuration against messages, the way the clients would.
+pub struct Engine<'a> {
+    config: &'a Configuration,
+    /// Compiled `matches_regex` and `matches_glob` values, by regular expression.
+    patterns: HashMap<String, Regex>,
+}
+
+impl<'a> Engine<'a> {
+    /// Prepares `config` for evaluation, failing on values that are not valid patterns.
+    pub fn new(config: &'a Configuration) -> Result<Self, Error> {
+        let mut patterns = HashMap::new();
+
+        for filter in config
+            .accounts
+            .iter()
+            .flat_map(|account| &account.message_filters)
+        {
+            for when in &filter.when {
+                helpers::compile_patterns(when, &mut patterns).map_err(|(pattern, source)| {
+                    Error::InvalidPattern {
+                        filter: filter.title.clone(),
+                        pattern,
+                        source,
+                    }
+                })?;
+            }
+        }
+
+        Ok(Engine { config, patterns })
+    }
+
+    /// Returns the enabled filters that match `message`, in order, per account. Processing of
+    /// an account ends with the first matching filter that has a `stop_processing` action.
+    ///
+    /// `now` is in seconds since the epoch and used for `age_days`.
+    pub fn evaluate(&self, message: &Message, now: i64) -> Vec<Hit<'a>> {
+        let mut hits = Vec::new();
+
+        for account in &self.config.accounts {
+            for filter in account
+                .message_filters
+                .iter()
+                .filter(|filter| filter.enabled)
+            {
+                if !self.matches(filter, message, now) {
+                    continue;
+                }
+                hits.push(Hit { account, filter });
+                if filter
+                    .then
+                    .contains(&Then::Simple(SimpleAction::StopProcessing))
+                {
+                    break;
+                }
+            }
+        }
+
+        hits
+    }
+
+    /// Returns `true` if the conditions of `filter` hold for `message`.
+    pub fn matches(&self, filter: &MessageFilter, message: &Message, now: i64) -> bool {
+        let mut conditions = filter.when.iter();
+        let mut holds = |when| self.holds(when, message, now);
+        match filter.grouping {
+            Grouping::All => conditions.all(&mut holds),
+            Grouping::Any => conditions.any(&mut holds),
+        }
+    }
+
+    fn holds(&self, when: &When, message: &Message, now: i64) -> bool {
+        match when {
+            When::All(all) => return all.conditions.iter().all(|c| self.holds(c, message, now)),
+            When::Any(any) => return any.conditions.iter().any(|c| self.holds(c, message, now)),
+            When::Not(not) => return !self.holds(&not.condition, message, now),
+            _ => {}
+        }
+
+        if let Some((state, expected)) = when.state_test() {
+            return helpers::state_holds(state, message, now) == expected;
+        }
+        let Some(test) = when.string_test() else {
+            unreachable!("groups are handled above");
+        };
+
+        // Negated matchers hold when their positive counterpart matches none of the values.
+        let (matcher, negated) = match test.matcher.is_negated() {
+            true => (test.matcher.negate().unwrap_or(test.matcher), true),
+            false => (test.matcher, false),
+        };
+        let candidates = helpers::candidates(test.field, message);
+        let found = test.values.iter().any(|value| {
+            candidates
+                .iter()
+                .any(|candidate| self.value_matches(matcher, candidate, value))
+        });
+        found != negated
+    }
+
+    /// Compares like the clients do by default, ignoring case.
+    fn value_matches(&self, matcher: Matcher, candidate: &str, value: &str) -> bool {
+        let regex = match matcher {
+            Matcher::MatchesRegex => value.to_owned(),
+            Matcher::MatchesGlob => glob_to_regex(value),
+            _ => {
+                let (candidate, value) = (candidate.to_lowercase(), value.to_lowercase());
+                return match matcher {
+                    Matcher::Contains | Matcher::DoesNotContain => candidate.contains(&value),
+                    Matcher::Is | Matcher::IsNot => candidate == value,
+                    Matcher::StartsWith => candidate.starts_with(&value),
+                    Matcher::EndsWith | Matcher::DoesNotEndWith => candidate.ends_with(&value),
+                    Matcher::MatchesRegex | Matcher::MatchesGlob => unreachable!(),
+                };
+            }
+        };
+        self.patterns
+            .get(&regex)
+            .is_some_and(|regex| regex.is_match(candidate))
+    }
+}
+
+/// Formats an action for reports, e.g. `move_to Archive` or `mark_read`.
+pub fn format_action(action: &Then) -> String {
+    match action {
+        Then::MoveTo(MoveTo { folder }) | Then::CopyTo(CopyTo { folder }) => {
+            format!("{} {folder}", action.key())
+        }
+        Then::Tag(Tag { tag }) => format!("tag {tag}"),
+        Then::SetPriority(SetPriority { priority }) => {
+            format!("set_priority {}", priority.key())
+        }
+        Then::Forward(Forward { to }) => format!("forward {to}"),
+        Then::ReplyWith(ReplyWith { template }) => format!("reply_with {template}"),
+        Then::RunProgram(RunProgram { command, args }) => {
+            [&["run_program".to_owned(), command.clone()][..], args]
+                .concat()
+                .join(" ")
+        }
+        Then::Simple(action) => action.key().to_owned(),
+    }
+}
+
+mod helpers {
+    /// Compiles the patterns in `when` into `patterns`, returning the first invalid one.
+    pub fn compile_patterns(
+        when: &When,
+        patterns: &mut HashMap<String, Regex>,
+    ) -> Result<(), (String, regex::Error)> {
+        match when {
+            When::All(All { conditions }) | When::Any(Any { conditions }) => {
+                for when in conditions {
+                    compile_patterns(when, patterns)?;
+                }
+            }
+            When::Not(not) => compile_patterns(&not.condition, patterns)?,
+            _ => {}
+        }
+
+        let Some(test) = when.string_test() else {
+            return Ok(());
+        };
+        for value in test.values {
+            let pattern = match test.matcher {
+                Matcher::MatchesRegex => value.clone(),
+                Matcher::MatchesGlob => glob_to_regex(value),
+                _ => continue,
+            };
+            if patterns.contains_key(&pattern) {
+                continue;
+            }
+            let regex = RegexBuilder::new(&pattern)
+                .case_insensitive(true)
+                .build()
+                .map_err(|err| (value.clone(), err))?;
+            patterns.insert(pattern, regex);
+        }
+        Ok(())
+    }
+
+    /// Returns the texts a field test looks at. Address fields yield each address and display
+    /// name, so `is: [jane@example.com]` and `contains: [Jane]` both work.
+    pub fn candidates(field: &Field, message: &Message) -> Vec<String> {
+        let headers = match field {
+            Field::Body => return vec![message.body().to_owned()],
+            Field::ToOrCc => &["To", "Cc"][..],
+            Field::From => &["From"],
+            Field::To => &["To"],
+            Field::Cc => &["Cc"],
+            Field::Bcc => &["Bcc"],
+            Field::ReplyTo => &["Reply-To"],
+            Field::Sender => &["Sender"],
+            Field::Subject | Field::ListId | Field::Header(_) => {
+                let name = field.header_name().unwrap_or_default();
+                return message.header(name).collect();
+            }
+        };
+
+        headers
+            .iter()
+            .flat_map(|header| message.addresses(header))
+            .flat_map(|address| [Some(address.email), address.name])
+            .flatten()
+            .collect()
+    }
+
+    pub fn state_holds(state: StateTest, message: &Message, now: i64) -> bool {
+        const DAY: i64 = 24 * 60 * 60;
+
+        match state {
+            StateTest::Size(SizeComparison::GreaterThan(size)) => message.size() > size.0,
+            StateTest::Size(SizeComparison::LessThan(size)) => message.size() < size.0,
+            StateTest::AgeDays(age) => message.date().is_some_and(|date| {
+                let days = (now - date.seconds) / DAY;
+                match age {
+                    AgeComparison::MoreThan(limit) => days > i64::from(limit),
+                    AgeComparison::LessThan(limit) => days < i64::from(limit),
+                }
+            }),
+            StateTest::Date(comparison) => message.date().is_some_and(|date| match comparison {
+                DateComparison::Before(limit) => date.date < limit,
+                DateComparison::After(limit) => date.date > limit,
+                DateComparison::On(limit) => date.date == limit,
+            }),
+            StateTest::Status(Status::Read) => message.flags.seen,
+            StateTest::Status(Status::Flagged) => message.flags.flagged,
+            StateTest::Status(Status::Replied) => message.flags.replied,
+            StateTest::HasAttachment => message.has_attachment(),
+            StateTest::Priority(priority) => message.priority() == priority,
+            StateTest::Junk => message.is_junk(),
+            StateTest::Tag(tag) => message
+                .keywords()
+                .iter()
+                .any(|keyword| keyword.eq_ignore_ascii_case(tag)),
+        }
+    }
+
+    use std::collections::HashMap;
+
+    use regex::{Regex, RegexBuilder};
+
+    use crate::{
+        configuration::{
+            AgeComparison, All, Any, DateComparison, Field, Matcher, SizeComparison, StateTest,
+            Status, When,
+        },
+        message::Message,
+        pattern::glob_to_regex,
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    const NOW: i64 = 1_767_225_600; // 2026-01-01
+
+    fn message(headers: &str) -> Message {
+        Message::parse(format!("{headers}\n\nHello\n").as_bytes())
+    }
+
+    fn titles(hits: &[Hit]) -> Vec<String> {
+        hits.iter().map(|hit| hit.filter.title.clone()).collect()
+    }
+
+    #[test]
+    fn test_evaluate() {
+        let config = crate::configuration::parse(
+            "\
+- thunderbird_id: work
+  message_filters:
+    - title: Github
+      when:
+        - field: from
+          ends_with: ['@github.com']
+      then:
+        - move_to: Github
+        - stop_processing
+    - title: Humans
+      match: all
+      when:
+        - not:
+            field: from
+            contains: [bot, noreply]
+        - field: subject
+          matches_glob: ['Re: *']
+      then:
+        - flag
+    - title: Disabled
+      enabled: false
+      when:
+        - field: subject
+          contains: [re]
+      then:
+        - delete
+",
+        )
+        .unwrap();
+        let engine = Engine::new(&config).unwrap();
+
+        let hits = engine.evaluate(&message("From: GitHub <noreply@github.com>"), NOW);
+        assert_eq!(titles(&hits), ["Github"]);
+
+        let hits = engine.evaluate(
+            &message("From: \"Jane\" <jane@example.com>\nSubject: RE: lunch"),
+            NOW,
+        );
+        assert_eq!(titles(&hits), ["Humans"]);
+
+        let hits = engine.evaluate(
+            &message("From: Build Bot <ci@example.com>\nSubject: Re: lunch"),
+            NOW,
+        );
+        assert!(hits.is_empty());
+    }
+
+    #[test]
+    fn test_negated_and_address_matchers() {
+        let config = crate::configuration::parse(
+            "\
+- thunderbird_id: work
+  message_filters:
+    - title: Outside
+      match: all
+      when:
+        - field: to_or_cc
+          does_not_end_with: ['@example.com']
+        - field: from
+          is: [Jane]
+        - field: { header: X-Mailer }
+          matches_regex: ['^mutt']
+      then: []
+",
+        )
+        .unwrap();
+        let engine = Engine::new(&config).unwrap();
+        let filter = &config.accounts[0].message_filters[0];
+
+        let outside = message("From: Jane <jane@x.org>\nTo: bob@x.org\nX-Mailer: Mutt/2.2");
+        assert!(engine.matches(filter, &outside, NOW));
+
+        let inside = message("From: Jane <jane@x.org>\nCc: me@example.com\nX-Mailer: Mutt");
+        assert!(!engine.matches(filter, &inside, NOW));
+    }
+
+    #[test]
+    fn test_state_conditions() {
+        let config = crate::configuration::parse(
+            "\
+- thunderbird_id: work
+  message_filters:
+    - title: Old
+      match: all
+      when:
+        - age_days: { more_than: 30 }
+        - date: { before: 2025-12-01 }
+        - size: { less_than: 1KB }
+        - priority: high
+        - junk: false
+        - not:
+            status: read
+      then: []
+",
+        )
+        .unwrap();
+        let engine = Engine::new(&config).unwrap();
+        let filter = &config.accounts[0].message_filters[0];
+
+        let old = message("Date: Sat, 1 Nov 2025 10:00:00 +0000\nX-Priority: 2");
+        assert!(engine.matches(filter, &old, NOW));
+
+        let read = message("Date: Sat, 1 Nov 2025 10:00:00 +0000\nX-Priority: 2\nStatus: RO");
+        assert!(!engine.matches(filter, &read, NOW));
+
+        let recent = message("Date: Sun, 28 Dec 2025 10:00:00 +0000\nX-Priority: 2");
+        assert!(!engine.matches(filter, &recent, NOW));
+    }
+
+    #[test]
+    fn test_invalid_pattern() {
+        let config = crate::configuration::parse(
+            "\
+- thunderbird_id: work
+  message_filters:
+    - title: Broken
+      when:
+        - field: subject
+          matches_regex: ['(unclosed']
+      then: []
+",
+        )
+        .unwrap();
+
+        assert!(Engine::new(&config)
+            .err()
+            .unwrap()
+            .to_string()
+            .starts_with("filter 'Broken': invalid pattern `(unclosed`: "));
+    }
+
+    #[test]
+    fn test_format_action() {
+        assert_eq!(
+            format_action(&Then::MoveTo(MoveTo {
+                folder: "Archive".to_owned()
+            })),
+            "move_to Archive"
+        );
+        assert_eq!(
+            format_action(&Then::RunProgram(RunProgram {
+                command: "notify-send".to_owned(),
+                args: vec!["Mail".to_owned()],
+            })),
+            "run_program notify-send Mail"
+        );
+        assert_eq!(
+            format_action(&Then::Simple(SimpleAction::MarkRead)),
+            "mark_read"
+        );
+    }
+
+    use super::*;
+}
+
+use std::collections::HashMap;
+
+use regex::Regex;
+
+use crate::{
+    configuration::{
+        Account, Configuration, CopyTo, Forward, Grouping, Matcher, MessageFilter, MoveTo,
+        ReplyWith, RunProgram, SetPriority, SimpleAction, Tag, Then, When,
+    },
+    message::Message,
+    pattern::glob_to_regex,
+};
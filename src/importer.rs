@@ -0,0 +1,105 @@
+pub mod evolution;
+pub mod sieve;
+pub mod thunderbird;
+
+/// A configuration read from a client's filter file.
+#[derive(Debug, PartialEq)]
+pub struct Import {
+    pub config: Configuration,
+    /// Everything that could not be carried over.
+    pub warnings: Vec<Warning>,
+}
+
+/// Something in the imported file that has no equivalent in the configuration.
+#[derive(Debug, PartialEq)]
+pub struct Warning {
+    /// The filter it belongs to, `None` for file-level problems.
+    pub filter: Option<String>,
+    pub message: String,
+}
+
+impl std::fmt::Display for Warning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.filter {
+            Some(filter) => write!(f, "filter '{filter}': {}", self.message),
+            None => f.write_str(&self.message),
+        }
+    }
+}
+
+/// Merges adjacent conditions on the same field and matcher into one with several values,
+/// where that keeps their meaning: positive matchers in an `any` group and negated ones in an
+/// `all` group. This undoes the one-test-per-value layout of the clients.
+pub fn merge_values(grouping: Grouping, conditions: Vec<When>) -> Vec<When> {
+    let mut merged: Vec<When> = Vec::new();
+
+    for when in conditions {
+        let mergeable = |test: StringTest| test.matcher.is_negated() == (grouping == Grouping::All);
+        let previous = merged.last().and_then(When::string_test);
+        match (previous, when.string_test()) {
+            (Some(previous), Some(test))
+                if previous.field == test.field
+                    && previous.matcher == test.matcher
+                    && mergeable(test) =>
+            {
+                let values = [previous.values, test.values].concat();
+                let when = When::from_string_test(test.field.clone(), test.matcher, values);
+                if let Some(last) = merged.last_mut() {
+                    *last = when;
+                }
+            }
+            _ => merged.push(when),
+        }
+    }
+
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    fn contains(field: Field, value: &str) -> When {
+        When::from_string_test(field, Matcher::Contains, vec![value.to_owned()])
+    }
+
+    fn is_not(field: Field, value: &str) -> When {
+        When::from_string_test(field, Matcher::IsNot, vec![value.to_owned()])
+    }
+
+    #[test]
+    fn test_merge_values() {
+        let conditions = vec![
+            contains(Field::From, "a"),
+            contains(Field::From, "b"),
+            contains(Field::To, "c"),
+            is_not(Field::To, "d"),
+            is_not(Field::To, "e"),
+        ];
+
+        assert_eq!(
+            merge_values(Grouping::Any, conditions.clone()),
+            [
+                When::from_string_test(
+                    Field::From,
+                    Matcher::Contains,
+                    vec!["a".to_owned(), "b".to_owned()]
+                ),
+                contains(Field::To, "c"),
+                is_not(Field::To, "d"),
+                is_not(Field::To, "e"),
+            ]
+        );
+        assert_eq!(
+            merge_values(Grouping::All, conditions)[3],
+            When::from_string_test(
+                Field::To,
+                Matcher::IsNot,
+                vec!["d".to_owned(), "e".to_owned()]
+            )
+        );
+    }
+
+    use super::*;
+    use crate::configuration::{Field, Matcher};
+}
+
+use crate::configuration::{Configuration, Grouping, StringTest, When};
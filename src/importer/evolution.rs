@@ -0,0 +1,849 @@
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error(transparent)]
+    Xml(#[from] reader::Error),
+    #[error("expected `<filteroptions>`, found `<{0}>`")]
+    NotFilterOptions(String),
+}
+
+/// Reads an Evolution `filters.xml`. Rules are grouped into accounts by the account of the
+/// `folder://ACCOUNT/...` URIs they refer to; rules without folders belong to the account of
+/// their rule set. With `evolution_id`, everything goes into a single account of that id.
+///
+/// Rules that exist once per source, as the printer writes filters that also run after
+/// sending, are merged back into one filter. Rules with conditions that cannot be expressed
+/// are skipped and actions without an equivalent are dropped, each with a warning.
+pub fn import(source: &str, evolution_id: Option<&str>) -> Result<Import, Error> {
+    let root = reader::parse(source)?;
+    if root.tag() != "filteroptions" {
+        return Err(Error::NotFilterOptions(root.tag().to_owned()));
+    }
+
+    let mut importer = helpers::Importer {
+        accounts: Vec::new(),
+        warnings: Vec::new(),
+    };
+    let mut ruleset_account = evolution_id.map(str::to_owned);
+    for ruleset in root
+        .children()
+        .iter()
+        .filter(|child| child.tag() == "ruleset")
+    {
+        let rules = ruleset
+            .children()
+            .iter()
+            .filter(|child| child.tag() == "rule");
+        if evolution_id.is_none() {
+            let account = rules
+                .clone()
+                .flat_map(helpers::folder_uris)
+                .find_map(helpers::parse_folder)
+                .map(|(account, _)| account.to_owned());
+            ruleset_account = account.or(ruleset_account);
+        }
+
+        for rule in rules {
+            let account = match evolution_id {
+                Some(_) => ruleset_account.clone(),
+                None => helpers::folder_uris(rule)
+                    .find_map(helpers::parse_folder)
+                    .map(|(account, _)| account.to_owned())
+                    .or_else(|| ruleset_account.clone()),
+            };
+            importer.import_rule(account, rule);
+        }
+    }
+
+    if importer.accounts.is_empty() {
+        importer.account_mut(evolution_id.map(str::to_owned));
+    }
+    if importer
+        .accounts
+        .iter()
+        .any(|account| account.evolution_id.is_none())
+    {
+        importer.warnings.push(Warning {
+            filter: None,
+            message: "no folder names the account, set `evolution_id` or pass `--id`".to_owned(),
+        });
+    }
+
+    Ok(Import {
+        config: Configuration {
+            accounts: importer.accounts,
+        },
+        warnings: importer.warnings,
+    })
+}
+
+mod helpers {
+    pub struct Importer {
+        pub accounts: Vec<Account>,
+        pub warnings: Vec<Warning>,
+    }
+
+    impl Importer {
+        /// Converts a `<rule>` and adds it to the account `evolution_id`.
+        pub fn import_rule(&mut self, evolution_id: Option<String>, rule: &XmlElement) {
+            let Some(title) = rule.child("title").map(|title| title.text().to_owned()) else {
+                self.warnings.push(Warning {
+                    filter: None,
+                    message: "skipped a rule without a title".to_owned(),
+                });
+                return;
+            };
+
+            let run_on = match rule.attr("source").unwrap_or("incoming") {
+                "incoming" => vec![RunOn::Incoming, RunOn::Manual],
+                "demand" => vec![RunOn::Manual],
+                "outgoing" => vec![RunOn::AfterSending],
+                source => {
+                    self.warn(
+                        &title,
+                        format!("skipped, source `{source}` has no equivalent"),
+                    );
+                    return;
+                }
+            };
+            let grouping = match rule.attr("grouping") {
+                Some("all") => Grouping::All,
+                _ => Grouping::Any,
+            };
+
+            let parts = rule.child("partset").map_or(&[][..], XmlElement::children);
+            let when = match parts
+                .iter()
+                .map(convert_part)
+                .collect::<Result<Vec<_>, _>>()
+            {
+                Ok(when) => merge_values(grouping, when),
+                Err(reason) => {
+                    self.warn(&title, format!("skipped, {reason}"));
+                    return;
+                }
+            };
+
+            let mut then = Vec::new();
+            for part in rule
+                .child("actionset")
+                .map_or(&[][..], XmlElement::children)
+            {
+                match convert_action(evolution_id.as_deref(), part) {
+                    Ok(action) => then.push(action),
+                    Err(message) => self.warn(&title, message),
+                }
+            }
+
+            let filter = MessageFilter {
+                title,
+                description: None,
+                enabled: rule.attr("enabled") != Some("false"),
+                run_on,
+                grouping,
+                when,
+                then,
+            };
+            let filters = &mut self.account_mut(evolution_id).message_filters;
+            let same_rule = filters.iter_mut().find(|other| {
+                MessageFilter {
+                    run_on: filter.run_on.clone(),
+                    ..(*other).clone()
+                } == filter
+                    && !other
+                        .run_on
+                        .iter()
+                        .any(|run_on| filter.run_on.contains(run_on))
+            });
+            match same_rule {
+                Some(other) => other.run_on.extend(filter.run_on),
+                None => filters.push(filter),
+            }
+        }
+
+        pub fn account_mut(&mut self, evolution_id: Option<String>) -> &mut Account {
+            let index = match self
+                .accounts
+                .iter()
+                .position(|account| account.evolution_id == evolution_id)
+            {
+                Some(index) => index,
+                None => {
+                    self.accounts.push(Account {
+                        account: None,
+                        email: None,
+                        imap_host: None,
+                        evolution_id,
+                        thunderbird_id: None,
+                        thunderbird_path: None,
+                        sieve_separator: None,
+                        templates: BTreeMap::new(),
+                        message_filters: Vec::new(),
+                    });
+                    self.accounts.len() - 1
+                }
+            };
+            &mut self.accounts[index]
+        }
+
+        fn warn(&mut self, filter: &str, message: String) {
+            self.warnings.push(Warning {
+                filter: Some(filter.to_owned()),
+                message,
+            });
+        }
+    }
+
+    /// Returns the folder URIs of the folder actions of `rule`.
+    pub fn folder_uris(rule: &XmlElement) -> impl Iterator<Item = &str> + Clone {
+        rule.child("actionset")
+            .into_iter()
+            .flat_map(XmlElement::children)
+            .flat_map(XmlElement::children)
+            .filter_map(|value| value.child("folder"))
+            .filter_map(|folder| folder.attr("uri"))
+    }
+
+    /// Splits `folder://ACCOUNT/INBOX/Github` into the account and the folder path, the
+    /// inverse of `format_folder`.
+    pub fn parse_folder(uri: &str) -> Option<(&str, &str)> {
+        let (account, folder) = uri.strip_prefix("folder://")?.split_once('/')?;
+        (!account.is_empty() && !folder.is_empty()).then_some((account, folder))
+    }
+
+    /// Returns the `<value>` of `part` named `name`.
+    fn value<'a>(part: &'a XmlElement, name: &str) -> Option<&'a XmlElement> {
+        part.children()
+            .iter()
+            .find(|value| value.tag() == "value" && value.attr("name") == Some(name))
+    }
+
+    /// Returns the first option of `part`, e.g. the match type.
+    fn option(part: &XmlElement) -> Option<&str> {
+        part.children()
+            .iter()
+            .find(|value| value.attr("type") == Some("option"))
+            .and_then(|value| value.attr("value"))
+    }
+
+    fn option_value<'a>(part: &'a XmlElement, name: &str) -> Option<&'a str> {
+        value(part, name).and_then(|value| value.attr("value"))
+    }
+
+    /// Converts a condition `<part>`, the inverse of `format_test`.
+    fn convert_part(part: &XmlElement) -> Result<When, String> {
+        let unsupported = || {
+            let name = part.attr("name").unwrap_or_default();
+            match option(part) {
+                Some(option) => format!("cannot import condition `{name} {option}`"),
+                None => format!("cannot import condition `{name}`"),
+            }
+        };
+        let name = part.attr("name").unwrap_or_default();
+
+        let field = match name {
+            "sender" => Field::From,
+            "to" => Field::To,
+            "cc" => Field::Cc,
+            "bcc" => Field::Bcc,
+            "recipients" => Field::ToOrCc,
+            "subject" => Field::Subject,
+            "body" => Field::Body,
+            "mlist" => Field::ListId,
+            "header" => {
+                let header = value(part, "header-field")
+                    .and_then(|value| value.child("string"))
+                    .map(XmlElement::text)
+                    .ok_or_else(unsupported)?;
+                match header.to_ascii_lowercase().as_str() {
+                    "reply-to" => Field::ReplyTo,
+                    "sender" => Field::Sender,
+                    _ => Field::Header(header.to_owned()),
+                }
+            }
+            _ => return convert_state_part(name, part).ok_or_else(unsupported),
+        };
+
+        let (matcher, negated) = match option(part) {
+            Some("contains") => (Matcher::Contains, false),
+            Some("not contains") => (Matcher::Contains, true),
+            Some("is") => (Matcher::Is, false),
+            Some("is not") => (Matcher::Is, true),
+            Some("starts with") => (Matcher::StartsWith, false),
+            Some("not starts with") => (Matcher::StartsWith, true),
+            Some("ends with") => (Matcher::EndsWith, false),
+            Some("not ends with") => (Matcher::EndsWith, true),
+            Some("regex") => (Matcher::MatchesRegex, false),
+            _ => return Err(unsupported()),
+        };
+        let needle = part
+            .children()
+            .iter()
+            .filter(|value| value.attr("name") != Some("header-field"))
+            .find_map(|value| value.child("string"))
+            .map(|string| string.text().to_owned())
+            .ok_or_else(unsupported)?;
+
+        Ok(match (negated, matcher.negate()) {
+            (false, _) => When::from_string_test(field, matcher, vec![needle]),
+            (true, Some(matcher)) => When::from_string_test(field, matcher, vec![needle]),
+            (true, None) => not(When::from_string_test(field, matcher, vec![needle])),
+        })
+    }
+
+    /// Converts a `size`, `sent-date`, `status`, `attachments` or `label` part, the inverse
+    /// of `format_state`.
+    fn convert_state_part(name: &str, part: &XmlElement) -> Option<When> {
+        const DAY: i64 = 24 * 60 * 60;
+
+        let when = match name {
+            "size" => {
+                let kilobytes = value(part, "versus")?
+                    .attr("integer")?
+                    .parse::<u64>()
+                    .ok()?;
+                let size = ByteSize(kilobytes.checked_mul(1024)?);
+                When::Size(Size {
+                    size: match option_value(part, "size-type")? {
+                        "greater-than" => SizeComparison::GreaterThan(size),
+                        "less-than" => SizeComparison::LessThan(size),
+                        _ => return None,
+                    },
+                })
+            }
+            "sent-date" => {
+                let datespec = value(part, "versus")?.child("datespec")?;
+                let seconds = datespec.attr("value")?.parse::<i64>().ok()?;
+                match (
+                    datespec.attr("type")?,
+                    option_value(part, "date-spec-type")?,
+                ) {
+                    ("1", option) => {
+                        let date = Date::from_days_since_epoch(seconds.div_euclid(DAY));
+                        let (date, negated) = match option {
+                            "before" => (DateComparison::Before(date), false),
+                            "after" => (DateComparison::After(date), false),
+                            "is" => (DateComparison::On(date), false),
+                            "is-not" => (DateComparison::On(date), true),
+                            _ => return None,
+                        };
+                        let when = When::Date(DateTest { date });
+                        match negated {
+                            true => not(when),
+                            false => when,
+                        }
+                    }
+                    ("2", option) => {
+                        let days = u32::try_from(seconds / DAY).ok()?;
+                        When::AgeDays(AgeDays {
+                            age_days: match option {
+                                "before" => AgeComparison::MoreThan(days),
+                                "after" => AgeComparison::LessThan(days),
+                                _ => return None,
+                            },
+                        })
+                    }
+                    _ => return None,
+                }
+            }
+            "status" => {
+                let negated = match option_value(part, "match-type")? {
+                    "is" => false,
+                    "is not" => true,
+                    _ => return None,
+                };
+                let status = match option_value(part, "flag")? {
+                    "Seen" => Status::Read,
+                    "Flagged" => Status::Flagged,
+                    "Answered" => Status::Replied,
+                    "Junk" => return Some(When::Junk(Junk { junk: !negated })),
+                    _ => return None,
+                };
+                let when = When::Status(StatusTest { status });
+                match negated {
+                    true => not(when),
+                    false => when,
+                }
+            }
+            "attachments" => When::HasAttachment(HasAttachment {
+                has_attachment: match option_value(part, "match-type")? {
+                    "exist" => true,
+                    "not exist" => false,
+                    _ => return None,
+                },
+            }),
+            "label" => {
+                let when = When::Tag(TagTest {
+                    tag: option_value(part, "versus")?.to_owned(),
+                });
+                match option_value(part, "label-type")? {
+                    "is" => when,
+                    "is not" => not(when),
+                    _ => return None,
+                }
+            }
+            _ => return None,
+        };
+        Some(when)
+    }
+
+    fn not(when: When) -> When {
+        When::Not(Not {
+            condition: Box::new(when),
+        })
+    }
+
+    /// Converts an action `<part>`, the inverse of `format_action`.
+    fn convert_action(evolution_id: Option<&str>, part: &XmlElement) -> Result<Then, String> {
+        let name = part.attr("name").unwrap_or_default();
+        let missing = || format!("dropped action `{name}` without a value");
+        let folder = || -> Result<String, String> {
+            let uri = value(part, "folder")
+                .and_then(|value| value.child("folder"))
+                .and_then(|folder| folder.attr("uri"))
+                .ok_or_else(missing)?;
+            match parse_folder(uri) {
+                Some((account, folder)) if Some(account) == evolution_id => Ok(folder.to_owned()),
+                _ => Err(format!(
+                    "dropped folder `{uri}`, which is not in this account"
+                )),
+            }
+        };
+        let input = |input: &str| {
+            value(part, input)
+                .and_then(|value| value.child(input))
+                .map(|text| text.text().to_owned())
+                .ok_or_else(missing)
+        };
+
+        let action = match (name, option_value(part, "flag")) {
+            ("move-to-folder", _) => Then::MoveTo(MoveTo { folder: folder()? }),
+            ("copy-to-folder", _) => Then::CopyTo(CopyTo { folder: folder()? }),
+            ("set-label", _) => Then::Tag(Tag {
+                tag: option_value(part, "label").ok_or_else(missing)?.to_owned(),
+            }),
+            ("forward", _) => Then::Forward(Forward {
+                to: input("address")?,
+            }),
+            ("shell-command", _) => {
+                let line = input("command")?;
+                let mut words = split_command(&line)
+                    .filter(|words| !words.is_empty())
+                    .ok_or_else(|| format!("dropped command `{line}`, which cannot be split"))?;
+                Then::RunProgram(RunProgram {
+                    command: words.remove(0),
+                    args: words,
+                })
+            }
+            ("delete", _) => Then::Simple(SimpleAction::Delete),
+            ("stop", _) => Then::Simple(SimpleAction::StopProcessing),
+            ("set-status", Some("Seen")) => Then::Simple(SimpleAction::MarkRead),
+            ("set-status", Some("Flagged")) => Then::Simple(SimpleAction::Flag),
+            ("set-status", Some("Junk")) => Then::Simple(SimpleAction::MarkJunk),
+            ("unset-status", Some("Seen")) => Then::Simple(SimpleAction::MarkUnread),
+            ("unset-status", Some("Junk")) => Then::Simple(SimpleAction::MarkNotJunk),
+            _ => return Err(format!("dropped action `{name}`, which has no equivalent")),
+        };
+        Ok(action)
+    }
+
+    /// Splits a shell command line into words, the inverse of `format_command`. Understands
+    /// single and double quotes and backslash escapes; `None` for unterminated quotes.
+    pub fn split_command(line: &str) -> Option<Vec<String>> {
+        let mut words = Vec::new();
+        let mut word: Option<String> = None;
+        let mut chars = line.chars();
+
+        while let Some(c) = chars.next() {
+            match c {
+                c if c.is_whitespace() => words.extend(word.take()),
+                '\'' => {
+                    let word = word.get_or_insert_with(String::new);
+                    loop {
+                        match chars.next()? {
+                            '\'' => break,
+                            c => word.push(c),
+                        }
+                    }
+                }
+                '"' => {
+                    let word = word.get_or_insert_with(String::new);
+                    loop {
+                        match chars.next()? {
+                            '"' => break,
+                            '\\' => match chars.next()? {
+                                c @ ('"' | '\\' | '$' | '`') => word.push(c),
+                                c => word.extend(['\\', c]),
+                            },
+                            c => word.push(c),
+                        }
+                    }
+                }
+                '\\' => word.get_or_insert_with(String::new).push(chars.next()?),
+                c => word.get_or_insert_with(String::new).push(c),
+            }
+        }
+        words.extend(word);
+
+        Some(words)
+    }
+
+    use std::collections::BTreeMap;
+
+    use crate::{
+        configuration::{
+            Account, AgeComparison, AgeDays, ByteSize, CopyTo, Date, DateComparison, DateTest,
+            Field, Forward, Grouping, HasAttachment, Junk, Matcher, MessageFilter, MoveTo, Not,
+            RunOn, RunProgram, SimpleAction, Size, SizeComparison, Status, StatusTest, Tag,
+            TagTest, Then, When,
+        },
+        importer::{merge_values, Warning},
+        xml::XmlElement,
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_import() {
+        let source = r#"<?xml version="1.0"?>
+<filteroptions>
+  <ruleset>
+    <rule enabled="true" grouping="any" source="incoming">
+      <title>Github</title>
+      <partset>
+        <part name="sender">
+          <value name="sender-type" type="option" value="ends with"/>
+          <value name="sender" type="string"><string>@github.com</string></value>
+        </part>
+        <part name="sender">
+          <value name="sender-type" type="option" value="ends with"/>
+          <value name="sender" type="string"><string>@noreply.github.com</string></value>
+        </part>
+      </partset>
+      <actionset>
+        <part name="move-to-folder">
+          <value name="folder" type="folder"><folder uri="folder://1234/INBOX/Github"/></value>
+        </part>
+        <part name="set-status"><value name="flag" type="option" value="Seen"/></part>
+        <part name="beep"/>
+      </actionset>
+    </rule>
+    <rule enabled="false" grouping="all" source="demand">
+      <title>Old &amp; big</title>
+      <partset>
+        <part name="size">
+          <value name="size-type" type="option" value="greater-than"/>
+          <value name="versus" type="integer" integer="5120"/>
+        </part>
+        <part name="sent-date">
+          <value name="date-spec-type" type="option" value="before"/>
+          <value name="versus" type="datespec"><datespec type="2" value="2592000"/></value>
+        </part>
+        <part name="status">
+          <value name="match-type" type="option" value="is not"/>
+          <value name="flag" type="option" value="Flagged"/>
+        </part>
+      </partset>
+      <actionset>
+        <part name="shell-command">
+          <value name="command" type="command"><command>archive --to 'Old mail'</command></value>
+        </part>
+      </actionset>
+    </rule>
+    <rule enabled="true" grouping="any" source="incoming">
+      <title>Sounds</title>
+      <partset>
+        <part name="sender">
+          <value name="sender-type" type="option" value="sounds like"/>
+          <value name="sender" type="string"><string>smith</string></value>
+        </part>
+      </partset>
+      <actionset/>
+    </rule>
+  </ruleset>
+</filteroptions>
+"#;
+        let import = import(source, None).unwrap();
+
+        assert_eq!(
+            serde_yaml::to_string(&import.config).unwrap(),
+            "\
+- evolution_id: '1234'
+  message_filters:
+  - title: Github
+    when:
+    - field: from
+      ends_with:
+      - '@github.com'
+      - '@noreply.github.com'
+    then:
+    - move_to: INBOX/Github
+    - mark_read
+  - title: Old & big
+    enabled: false
+    run_on:
+    - manual
+    match: all
+    when:
+    - size:
+        greater_than: 5MB
+    - age_days:
+        more_than: 30
+    - not:
+        status: flagged
+    then:
+    - run_program: archive
+      args:
+      - --to
+      - Old mail
+"
+        );
+        assert_eq!(
+            import
+                .warnings
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            [
+                "filter 'Github': dropped action `beep`, which has no equivalent",
+                "filter 'Sounds': skipped, cannot import condition `sender sounds like`",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_split_command() {
+        assert_eq!(
+            helpers::split_command(r#"notify 'it'\''s' "a \"b\"" c\ d"#),
+            Some(vec![
+                "notify".to_owned(),
+                "it's".to_owned(),
+                r#"a "b""#.to_owned(),
+                "c d".to_owned(),
+            ])
+        );
+        assert_eq!(helpers::split_command("notify 'open"), None);
+    }
+
+    fn string_condition() -> impl Strategy<Value = When> {
+        let field = prop_oneof![
+            Just(Field::From),
+            Just(Field::To),
+            Just(Field::Cc),
+            Just(Field::Bcc),
+            Just(Field::ToOrCc),
+            Just(Field::Subject),
+            Just(Field::Body),
+            Just(Field::ListId),
+            Just(Field::ReplyTo),
+            Just(Field::Sender),
+            "X-[A-Z][a-z]{1,6}".prop_map(Field::Header),
+        ];
+        let matcher = prop_oneof![
+            Just(Matcher::Contains),
+            Just(Matcher::DoesNotContain),
+            Just(Matcher::Is),
+            Just(Matcher::IsNot),
+            Just(Matcher::StartsWith),
+            Just(Matcher::EndsWith),
+            Just(Matcher::DoesNotEndWith),
+            Just(Matcher::MatchesRegex),
+        ];
+        (field, matcher, "[ -~]{1,12}", any::<bool>()).prop_map(
+            |(field, matcher, value, negated)| {
+                let when = When::from_string_test(field, matcher, vec![value]);
+                match negated && matcher == Matcher::StartsWith {
+                    true => When::Not(Not {
+                        condition: Box::new(when),
+                    }),
+                    false => when,
+                }
+            },
+        )
+    }
+
+    fn state_condition() -> impl Strategy<Value = When> {
+        let date = (-20_000..40_000i64).prop_map(Date::from_days_since_epoch);
+        let negate = |when: When| {
+            When::Not(Not {
+                condition: Box::new(when),
+            })
+        };
+        prop_oneof![
+            (1..10_000u64, any::<bool>()).prop_map(|(kilobytes, greater)| {
+                let size = ByteSize(kilobytes * 1024);
+                When::Size(Size {
+                    size: match greater {
+                        true => SizeComparison::GreaterThan(size),
+                        false => SizeComparison::LessThan(size),
+                    },
+                })
+            }),
+            (0..5000u32, any::<bool>()).prop_map(|(days, more)| When::AgeDays(AgeDays {
+                age_days: match more {
+                    true => AgeComparison::MoreThan(days),
+                    false => AgeComparison::LessThan(days),
+                },
+            })),
+            (date, 0..4).prop_map(move |(date, kind)| match kind {
+                0 => When::Date(DateTest {
+                    date: DateComparison::Before(date),
+                }),
+                1 => When::Date(DateTest {
+                    date: DateComparison::After(date),
+                }),
+                2 => When::Date(DateTest {
+                    date: DateComparison::On(date),
+                }),
+                _ => negate(When::Date(DateTest {
+                    date: DateComparison::On(date),
+                })),
+            }),
+            (
+                prop_oneof![
+                    Just(Status::Read),
+                    Just(Status::Flagged),
+                    Just(Status::Replied)
+                ],
+                any::<bool>()
+            )
+                .prop_map(move |(status, negated)| {
+                    let when = When::Status(StatusTest { status });
+                    if negated {
+                        negate(when)
+                    } else {
+                        when
+                    }
+                }),
+            any::<bool>()
+                .prop_map(|has_attachment| When::HasAttachment(HasAttachment { has_attachment })),
+            any::<bool>().prop_map(|junk| When::Junk(Junk { junk })),
+            ("\\$label[0-9]", any::<bool>()).prop_map(move |(tag, negated)| {
+                let when = When::Tag(TagTest { tag });
+                if negated {
+                    negate(when)
+                } else {
+                    when
+                }
+            }),
+        ]
+    }
+
+    fn action() -> impl Strategy<Value = Then> {
+        let folder = "[A-Za-z]{1,6}(/[A-Za-z ]{1,6})?";
+        prop_oneof![
+            folder.prop_map(|folder| Then::MoveTo(MoveTo { folder })),
+            folder.prop_map(|folder| Then::CopyTo(CopyTo { folder })),
+            "\\$label[0-9]".prop_map(|tag| Then::Tag(Tag { tag })),
+            "[a-z]{1,6}@example\\.com".prop_map(|to| Then::Forward(Forward { to })),
+            ("[a-z]{1,6}", prop::collection::vec("[ -~]{0,8}", 0..3))
+                .prop_map(|(command, args)| Then::RunProgram(RunProgram { command, args })),
+            prop_oneof![
+                Just(SimpleAction::Delete),
+                Just(SimpleAction::MarkRead),
+                Just(SimpleAction::MarkUnread),
+                Just(SimpleAction::Flag),
+                Just(SimpleAction::MarkJunk),
+                Just(SimpleAction::MarkNotJunk),
+                Just(SimpleAction::StopProcessing),
+            ]
+            .prop_map(Then::Simple),
+        ]
+    }
+
+    fn filter() -> impl Strategy<Value = MessageFilter> {
+        let run_on = prop_oneof![
+            Just(vec![RunOn::Incoming, RunOn::Manual]),
+            Just(vec![RunOn::Manual]),
+            Just(vec![RunOn::AfterSending]),
+            Just(vec![RunOn::Incoming, RunOn::Manual, RunOn::AfterSending]),
+            Just(vec![RunOn::Manual, RunOn::AfterSending]),
+        ];
+        let grouping = prop_oneof![Just(Grouping::All), Just(Grouping::Any)];
+        let when = prop::collection::vec(prop_oneof![string_condition(), state_condition()], 1..5);
+        (
+            "[ -~]{1,16}",
+            any::<bool>(),
+            run_on,
+            grouping,
+            when,
+            prop::collection::vec(action(), 0..4),
+        )
+            .prop_map(
+                |(title, enabled, run_on, grouping, when, then)| MessageFilter {
+                    title,
+                    description: None,
+                    enabled,
+                    run_on,
+                    grouping,
+                    when,
+                    then,
+                },
+            )
+    }
+
+    proptest! {
+        #[test]
+        fn test_round_trip(filters in prop::collection::vec(filter(), 0..6)) {
+            let account = Account {
+                account: None,
+                email: None,
+                imap_host: None,
+                evolution_id: Some("1234".to_owned()),
+                thunderbird_id: None,
+                thunderbird_path: None,
+                sieve_separator: None,
+                templates: BTreeMap::new(),
+                message_filters: filters
+                    .into_iter()
+                    .enumerate()
+                    .map(|(index, filter)| MessageFilter {
+                        // Titles are unique, so rules are never merged across filters.
+                        title: format!("{index} {}", filter.title),
+                        ..filter
+                    })
+                    .collect(),
+            };
+            let config = Configuration { accounts: vec![account] };
+            let document = printer::evolution::print_document(config.clone()).unwrap();
+
+            let mut import = import(&document.to_string(), Some("1234")).unwrap();
+
+            // Conditions are printed one value per part and merged again on import, and
+            // filters only run after sending come last, from the outgoing rule set.
+            let mut expected = config;
+            for filter in &mut expected.accounts[0].message_filters {
+                filter.when = merge_values(filter.grouping, filter.when.clone());
+            }
+            for config in [&mut expected, &mut import.config] {
+                config.accounts[0]
+                    .message_filters
+                    .sort_by(|a, b| a.title.cmp(&b.title));
+            }
+            prop_assert_eq!(import.warnings, []);
+            prop_assert_eq!(import.config, expected);
+        }
+    }
+
+    use std::collections::BTreeMap;
+
+    use proptest::prelude::*;
+
+    use super::*;
+    use crate::{
+        configuration::{
+            Account, AgeComparison, AgeDays, ByteSize, CopyTo, Date, DateComparison, DateTest,
+            Field, Forward, Grouping, HasAttachment, Junk, Matcher, MessageFilter, MoveTo, Not,
+            RunOn, RunProgram, SimpleAction, Size, SizeComparison, Status, StatusTest, Tag,
+            TagTest, Then, When,
+        },
+        importer::merge_values,
+        printer,
+    };
+}
+
+use crate::{
+    configuration::Configuration,
+    importer::{Import, Warning},
+    xml::reader,
+};
@@ -0,0 +1,899 @@
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error(transparent)]
+    Sieve(#[from] sieve::Error),
+}
+
+/// Reads a Sieve script into a single account. Each top-level `if`, `elsif` and `else` becomes
+/// a filter, titled by a preceding `# rule:[...]` comment as Roundcube writes them; `elsif`
+/// and `else` carry the negated tests of the branches before them. Mailbox names are split on
+/// `separator`, which defaults to `/`.
+///
+/// Rules with tests that cannot be expressed are skipped and actions without an equivalent
+/// are dropped, each with a warning.
+pub fn import(source: &str, separator: Option<&str>) -> Result<Import, Error> {
+    let commands = sieve::parse(source)?;
+    let mut importer = helpers::Importer {
+        separator: separator.unwrap_or("/").to_owned(),
+        warnings: Vec::new(),
+    };
+
+    let mut message_filters = Vec::new();
+    // The tests of the branches of the current `if` chain, `None` after a skipped branch.
+    let mut chain: Option<Vec<When>> = None;
+    for command in &commands {
+        match command.name.as_str() {
+            "if" | "elsif" | "else" => {
+                message_filters.extend(importer.import_rule(command, &mut chain));
+            }
+            "require" | "keep" => chain = None,
+            name => {
+                chain = None;
+                importer.warnings.push(Warning {
+                    filter: None,
+                    message: format!(
+                        "skipped `{name}` on line {}, which is outside of a rule",
+                        command.line
+                    ),
+                });
+            }
+        }
+    }
+
+    Ok(Import {
+        config: Configuration {
+            accounts: vec![Account {
+                account: None,
+                email: None,
+                imap_host: None,
+                evolution_id: None,
+                thunderbird_id: None,
+                thunderbird_path: None,
+                sieve_separator: separator
+                    .filter(|separator| *separator != "/")
+                    .map(str::to_owned),
+                templates: BTreeMap::new(),
+                message_filters,
+            }],
+        },
+        warnings: importer.warnings,
+    })
+}
+
+mod helpers {
+    pub struct Importer {
+        pub separator: String,
+        pub warnings: Vec<Warning>,
+    }
+
+    impl Importer {
+        /// Converts an `if`, `elsif` or `else` command, updating the tests of the `if` chain.
+        pub fn import_rule(
+            &mut self,
+            command: &Command,
+            chain: &mut Option<Vec<When>>,
+        ) -> Option<MessageFilter> {
+            let (title, description) = parse_comments(&command.comments);
+            let title = title.unwrap_or_else(|| format!("Rule on line {}", command.line));
+
+            let previous = match (command.name.as_str(), chain.take()) {
+                ("if", _) => Some(Vec::new()),
+                (_, previous) => previous,
+            };
+            let Some(previous) = previous else {
+                self.warn(
+                    &title,
+                    "skipped, it continues a rule that was skipped".to_owned(),
+                );
+                return None;
+            };
+
+            let (test, enabled) = match (command.tests.first(), command.inline_comments.first()) {
+                (None, _) => (None, true),
+                // Roundcube disables rules by replacing the test and moving it to a comment.
+                (Some(test), Some(comment)) if test.name == "false" => {
+                    match sieve::parse_test(comment.trim()) {
+                        Ok(test) => (Some(test), false),
+                        Err(err) => {
+                            self.warn(&title, format!("skipped, cannot read disabled test: {err}"));
+                            return None;
+                        }
+                    }
+                }
+                (Some(test), _) => (Some(test.clone()), true),
+            };
+            let condition = match test.as_ref().map(convert_test).transpose() {
+                Ok(condition) => condition,
+                Err(reason) => {
+                    self.warn(&title, format!("skipped, {reason}"));
+                    return None;
+                }
+            };
+
+            let mut when: Vec<When> = previous.iter().cloned().map(negate).collect();
+            if let Some(condition) = &condition {
+                let mut branches = previous;
+                branches.push(condition.clone());
+                if command.name != "else" {
+                    *chain = Some(branches);
+                }
+                when.push(condition.clone());
+            }
+            let (grouping, when) = match (when.len(), when.pop()) {
+                (_, None) => {
+                    self.warn(&title, "skipped, it has no test".to_owned());
+                    return None;
+                }
+                (1, Some(When::All(all))) => (Grouping::All, all.conditions),
+                (1, Some(When::Any(any))) => (Grouping::Any, any.conditions),
+                (1, Some(when)) => (Grouping::Any, vec![when]),
+                (_, Some(last)) => {
+                    when.push(last);
+                    let when = when.into_iter().flat_map(|when| match when {
+                        When::All(all) => all.conditions,
+                        when => vec![when],
+                    });
+                    (Grouping::All, when.collect())
+                }
+            };
+
+            let then = match self.convert_actions(&title, command.block.as_deref().unwrap_or(&[])) {
+                Ok(then) => then,
+                Err(reason) => {
+                    self.warn(&title, format!("skipped, {reason}"));
+                    return None;
+                }
+            };
+
+            Some(MessageFilter {
+                title,
+                description,
+                enabled,
+                run_on: vec![RunOn::Incoming, RunOn::Manual],
+                grouping,
+                when: merge_values(grouping, when),
+                then,
+            })
+        }
+
+        /// Converts the commands of a rule's block, the inverse of `format_action`. A `keep`
+        /// turns `fileinto` into a copy.
+        fn convert_actions(&mut self, title: &str, block: &[Command]) -> Result<Vec<Then>, String> {
+            let mut then = Vec::new();
+            let mut keep = false;
+
+            for command in block {
+                let arguments = Arguments::new(&command.arguments);
+                let dropped = |reason: &str| {
+                    format!(
+                        "dropped `{}` on line {}, {reason}",
+                        command.name, command.line
+                    )
+                };
+
+                let actions = match command.name.as_str() {
+                    "fileinto" if arguments.has("flags") || arguments.has("mailboxid") => {
+                        Err(dropped("its options have no equivalent"))
+                    }
+                    "fileinto" => {
+                        match arguments.positional.first().and_then(|list| list.first()) {
+                            Some(mailbox) => {
+                                let folder =
+                                    mailbox.split(self.separator.as_str()).collect::<Vec<_>>();
+                                let folder = folder.join("/");
+                                Ok(vec![match arguments.has("copy") {
+                                    true => Then::CopyTo(CopyTo { folder }),
+                                    false => Then::MoveTo(MoveTo { folder }),
+                                }])
+                            }
+                            None => Err(dropped("it names no mailbox")),
+                        }
+                    }
+                    "redirect" => {
+                        match arguments.positional.first().and_then(|list| list.first()) {
+                            Some(to) => Ok(vec![Then::Forward(Forward { to: to.clone() })]),
+                            None => Err(dropped("it names no address")),
+                        }
+                    }
+                    "discard" => Ok(vec![Then::Simple(SimpleAction::Delete)]),
+                    "stop" => Ok(vec![Then::Simple(SimpleAction::StopProcessing)]),
+                    "keep" if arguments.has("flags") => {
+                        Err(dropped("its flags have no equivalent"))
+                    }
+                    "keep" => {
+                        keep = true;
+                        Ok(Vec::new())
+                    }
+                    "addflag" | "setflag" | "removeflag" => {
+                        let add = command.name != "removeflag";
+                        match arguments.positional.as_slice() {
+                            [flags] => flag_names(flags)
+                                .map(|flag| convert_flag_action(flag, add))
+                                .collect::<Option<Vec<_>>>()
+                                .ok_or_else(|| dropped("removing these flags has no equivalent")),
+                            _ => Err(dropped("variables have no equivalent")),
+                        }
+                    }
+                    "if" | "elsif" | "else" => {
+                        return Err(format!(
+                            "nested `{}` on line {} cannot be expressed",
+                            command.name, command.line
+                        ))
+                    }
+                    _ => Err(dropped("which has no equivalent")),
+                };
+
+                match actions {
+                    Ok(actions) => then.extend(actions),
+                    Err(message) => self.warn(title, message),
+                }
+            }
+
+            if keep {
+                for action in &mut then {
+                    if let Then::MoveTo(move_to) = action {
+                        *action = Then::CopyTo(CopyTo {
+                            folder: std::mem::take(&mut move_to.folder),
+                        });
+                    }
+                }
+            }
+            Ok(then)
+        }
+
+        fn warn(&mut self, filter: &str, message: String) {
+            self.warnings.push(Warning {
+                filter: Some(filter.to_owned()),
+                message,
+            });
+        }
+    }
+
+    /// Reads the title from a `rule:[...]` comment and the description from the comments after
+    /// it, as written by the printer.
+    pub fn parse_comments(comments: &[String]) -> (Option<String>, Option<String>) {
+        let Some(index) = comments
+            .iter()
+            .rposition(|comment| comment.trim().starts_with("rule:["))
+        else {
+            return (None, None);
+        };
+
+        let title = comments[index]
+            .trim()
+            .strip_prefix("rule:[")
+            .and_then(|title| title.strip_suffix(']'))
+            .map(str::to_owned);
+        let description = comments[index + 1..]
+            .iter()
+            .map(|line| line.strip_prefix(' ').unwrap_or(line))
+            .collect::<Vec<_>>()
+            .join("\n");
+        (title, (!description.is_empty()).then_some(description))
+    }
+
+    /// Tagged arguments and the string lists that are not the value of a tag.
+    pub struct Arguments<'a> {
+        pub tags: Vec<&'a str>,
+        pub values: Vec<(&'a str, &'a [String])>,
+        pub positional: Vec<&'a [String]>,
+        pub numbers: Vec<u64>,
+    }
+
+    impl<'a> Arguments<'a> {
+        /// Tags followed by a value of their own, from the base spec and common extensions.
+        const VALUE_TAGS: &'static [&'static str] = &[
+            "comparator",
+            "value",
+            "count",
+            "zone",
+            "flags",
+            "mailboxid",
+            "specialuse",
+            "type",
+            "subtype",
+            "contenttype",
+            "param",
+        ];
+
+        pub fn new(arguments: &'a [Argument]) -> Self {
+            let mut parsed = Arguments {
+                tags: Vec::new(),
+                values: Vec::new(),
+                positional: Vec::new(),
+                numbers: Vec::new(),
+            };
+            let mut arguments = arguments.iter().peekable();
+            while let Some(argument) = arguments.next() {
+                match argument {
+                    Argument::Tag(tag) => {
+                        parsed.tags.push(tag);
+                        if Self::VALUE_TAGS.contains(&tag.as_str()) {
+                            if let Some(Argument::Strings(value)) =
+                                arguments.next_if(|next| matches!(next, Argument::Strings(_)))
+                            {
+                                parsed.values.push((tag, value));
+                            }
+                        }
+                    }
+                    Argument::Strings(strings) => parsed.positional.push(strings),
+                    Argument::Number(number) => parsed.numbers.push(*number),
+                }
+            }
+            parsed
+        }
+
+        pub fn has(&self, tag: &str) -> bool {
+            self.tags.contains(&tag)
+        }
+
+        pub fn value(&self, tag: &str) -> Option<&'a str> {
+            self.values
+                .iter()
+                .find(|(name, _)| *name == tag)
+                .and_then(|(_, value)| value.first())
+                .map(String::as_str)
+        }
+    }
+
+    /// Converts a test, the inverse of `format_test`.
+    pub fn convert_test(test: &Test) -> Result<When, String> {
+        let unsupported = || format!("cannot import test `{}` on line {}", test.name, test.line);
+        let arguments = Arguments::new(&test.arguments);
+
+        let when = match test.name.as_str() {
+            "allof" | "anyof" => {
+                let mut conditions = test
+                    .tests
+                    .iter()
+                    .map(convert_test)
+                    .collect::<Result<Vec<_>, _>>()?;
+                match (conditions.len(), test.name.as_str()) {
+                    (1, _) => conditions.remove(0),
+                    (_, "allof") => When::All(All {
+                        conditions: merge_values(Grouping::All, conditions),
+                    }),
+                    _ => When::Any(Any {
+                        conditions: merge_values(Grouping::Any, conditions),
+                    }),
+                }
+            }
+            "true" => When::All(All {
+                conditions: Vec::new(),
+            }),
+            "false" => When::Any(Any {
+                conditions: Vec::new(),
+            }),
+            "not" => match test.tests.as_slice() {
+                [test] => negate(convert_test(test)?),
+                _ => return Err(unsupported()),
+            },
+            "size" => {
+                let size = ByteSize(*arguments.numbers.first().ok_or_else(unsupported)?);
+                When::Size(Size {
+                    size: match (arguments.has("over"), arguments.has("under")) {
+                        (true, false) => SizeComparison::GreaterThan(size),
+                        (false, true) => SizeComparison::LessThan(size),
+                        _ => return Err(unsupported()),
+                    },
+                })
+            }
+            "header" | "address" | "body" => {
+                convert_string_test(test, &arguments).ok_or_else(unsupported)?
+            }
+            "hasflag" => convert_hasflag(&arguments).ok_or_else(unsupported)?,
+            "date" => convert_date(&arguments).ok_or_else(unsupported)?,
+            _ => return Err(unsupported()),
+        };
+        Ok(when)
+    }
+
+    fn convert_string_test(test: &Test, arguments: &Arguments) -> Option<When> {
+        if arguments.has("value") || arguments.has("count") {
+            return None;
+        }
+        if arguments.has("mime") {
+            // The printer's attachment test, anything else on MIME parts has no equivalent.
+            let attachment = arguments.has("anychild")
+                && arguments.has("contains")
+                && arguments.positional
+                    == [
+                        &["Content-Disposition".to_owned()][..],
+                        &["attachment".to_owned()],
+                    ];
+            return attachment.then_some(When::HasAttachment(HasAttachment {
+                has_attachment: true,
+            }));
+        }
+
+        let (headers, keys): (&[String], &[String]) =
+            match (test.name.as_str(), &arguments.positional[..]) {
+                ("body", [keys]) if !arguments.has("content") => (&[], keys),
+                ("header" | "address", [headers, keys]) => (headers, keys),
+                _ => return None,
+            };
+
+        if let (Some(priority), true) = (convert_priority(headers, keys), arguments.has("matches"))
+        {
+            return Some(priority);
+        }
+
+        let fields = match headers {
+            [] => vec![Field::Body],
+            [a, b] if a.eq_ignore_ascii_case("to") && b.eq_ignore_ascii_case("cc") => {
+                vec![Field::ToOrCc]
+            }
+            headers => headers
+                .iter()
+                .map(|header| convert_header(header))
+                .collect(),
+        };
+
+        let mut conditions = Vec::new();
+        for field in fields {
+            for key in keys {
+                let (matcher, value) = match () {
+                    _ if arguments.has("domain") && arguments.has("is") => {
+                        (Matcher::EndsWith, format!("@{key}"))
+                    }
+                    _ if arguments.has("localpart") && arguments.has("is") => {
+                        (Matcher::StartsWith, format!("{key}@"))
+                    }
+                    _ if arguments.has("domain") || arguments.has("localpart") => return None,
+                    _ if arguments.has("contains") => (Matcher::Contains, key.clone()),
+                    _ if arguments.has("matches") => convert_pattern(key),
+                    _ if arguments.has("regex") => (Matcher::MatchesRegex, key.clone()),
+                    _ => (Matcher::Is, key.clone()),
+                };
+                conditions.push(When::from_string_test(field.clone(), matcher, vec![value]));
+            }
+        }
+
+        let mut conditions = merge_values(Grouping::Any, conditions);
+        match conditions.len() {
+            1 => conditions.pop(),
+            _ => Some(When::Any(Any { conditions })),
+        }
+    }
+
+    fn convert_header(header: &str) -> Field {
+        match header.to_ascii_lowercase().as_str() {
+            "from" => Field::From,
+            "to" => Field::To,
+            "cc" => Field::Cc,
+            "bcc" => Field::Bcc,
+            "reply-to" => Field::ReplyTo,
+            "sender" => Field::Sender,
+            "subject" => Field::Subject,
+            "list-id" => Field::ListId,
+            _ => Field::Header(header.to_owned()),
+        }
+    }
+
+    /// Recognizes the printer's `header :matches "X-Priority" "1*"` priority test.
+    fn convert_priority(headers: &[String], keys: &[String]) -> Option<When> {
+        let ([header], [key]) = (headers, keys) else {
+            return None;
+        };
+        if !header.eq_ignore_ascii_case("x-priority") {
+            return None;
+        }
+        let priority = match key.as_str() {
+            "1*" => Priority::Highest,
+            "2*" => Priority::High,
+            "3*" => Priority::Normal,
+            "4*" => Priority::Low,
+            "5*" => Priority::Lowest,
+            _ => return None,
+        };
+        Some(When::Priority(PriorityTest { priority }))
+    }
+
+    /// A piece of a `:matches` pattern.
+    #[derive(Copy, Clone, PartialEq)]
+    enum Piece {
+        Star,
+        Question,
+        Literal(char),
+    }
+
+    /// Maps a `:matches` pattern to the simplest matcher: a literal, prefix, suffix or infix
+    /// becomes `is`, `starts_with`, `ends_with` or `contains`, anything else a glob, or a
+    /// regular expression if it matches wildcards literally.
+    pub fn convert_pattern(pattern: &str) -> (Matcher, String) {
+        let mut pieces = Vec::new();
+        let mut chars = pattern.chars();
+        while let Some(c) = chars.next() {
+            pieces.push(match c {
+                '*' => Piece::Star,
+                '?' => Piece::Question,
+                '\\' => Piece::Literal(chars.next().unwrap_or('\\')),
+                c => Piece::Literal(c),
+            });
+        }
+
+        let literal = |pieces: &[Piece]| -> Option<String> {
+            pieces
+                .iter()
+                .map(|piece| match piece {
+                    Piece::Literal(c) => Some(*c),
+                    _ => None,
+                })
+                .collect()
+        };
+        let first = pieces.first() == Some(&Piece::Star);
+        let last = pieces.len() > 1 && pieces.last() == Some(&Piece::Star);
+        let inner = &pieces[usize::from(first)..pieces.len() - usize::from(last)];
+        if let Some(value) = literal(inner).filter(|value| !value.is_empty() || !first) {
+            let matcher = match (first, last) {
+                (false, false) => Matcher::Is,
+                (false, true) => Matcher::StartsWith,
+                (true, false) => Matcher::EndsWith,
+                (true, true) => Matcher::Contains,
+            };
+            return (matcher, value);
+        }
+
+        let literal_wildcard = pieces
+            .iter()
+            .any(|piece| matches!(piece, Piece::Literal('*' | '?')));
+        match literal_wildcard {
+            false => {
+                let glob = pieces
+                    .iter()
+                    .map(|piece| match piece {
+                        Piece::Star => '*',
+                        Piece::Question => '?',
+                        Piece::Literal(c) => *c,
+                    })
+                    .collect();
+                (Matcher::MatchesGlob, glob)
+            }
+            true => {
+                let mut regex = "^".to_owned();
+                for piece in pieces {
+                    match piece {
+                        Piece::Star => regex.push_str(".*"),
+                        Piece::Question => regex.push('.'),
+                        Piece::Literal(c) => regex.push_str(&regex::escape(&c.to_string())),
+                    }
+                }
+                regex.push('$');
+                (Matcher::MatchesRegex, regex)
+            }
+        }
+    }
+
+    fn convert_hasflag(arguments: &Arguments) -> Option<When> {
+        if arguments
+            .tags
+            .iter()
+            .any(|tag| !matches!(*tag, "is" | "comparator"))
+        {
+            return None;
+        }
+        let [flags] = arguments.positional.as_slice() else {
+            return None;
+        };
+
+        let mut conditions = flag_names(flags)
+            .map(|flag| match flag.to_ascii_lowercase().as_str() {
+                "\\seen" => When::Status(StatusTest {
+                    status: Status::Read,
+                }),
+                "\\flagged" => When::Status(StatusTest {
+                    status: Status::Flagged,
+                }),
+                "\\answered" => When::Status(StatusTest {
+                    status: Status::Replied,
+                }),
+                "$junk" => When::Junk(Junk { junk: true }),
+                "$notjunk" => When::Junk(Junk { junk: false }),
+                _ => When::Tag(TagTest {
+                    tag: flag.to_owned(),
+                }),
+            })
+            .collect::<Vec<_>>();
+        match conditions.len() {
+            0 => None,
+            1 => conditions.pop(),
+            _ => Some(When::Any(Any { conditions })),
+        }
+    }
+
+    /// Recognizes the printer's `date` tests on the `date` header.
+    fn convert_date(arguments: &Arguments) -> Option<When> {
+        let [header, part, date] = arguments.positional.as_slice() else {
+            return None;
+        };
+        if *header != ["date"] || *part != ["date"] || date.len() != 1 {
+            return None;
+        }
+        let date = date[0].parse().ok()?;
+
+        let date = match (arguments.value("value"), arguments.has("is")) {
+            (Some("lt"), false) => DateComparison::Before(date),
+            (Some("gt"), false) => DateComparison::After(date),
+            (None, true) => DateComparison::On(date),
+            _ => return None,
+        };
+        Some(When::Date(DateTest { date }))
+    }
+
+    /// Splits flag arguments, which may hold several space-separated flags each.
+    fn flag_names(flags: &[String]) -> impl Iterator<Item = &str> {
+        flags.iter().flat_map(|flags| flags.split_whitespace())
+    }
+
+    fn convert_flag_action(flag: &str, add: bool) -> Option<Then> {
+        let action = match (flag.to_ascii_lowercase().as_str(), add) {
+            ("\\seen", true) => SimpleAction::MarkRead,
+            ("\\seen", false) => SimpleAction::MarkUnread,
+            ("\\flagged", true) => SimpleAction::Flag,
+            ("$junk", true) => SimpleAction::MarkJunk,
+            ("$notjunk", true) => SimpleAction::MarkNotJunk,
+            (_, true) => {
+                return Some(Then::Tag(Tag {
+                    tag: flag.to_owned(),
+                }))
+            }
+            (_, false) => return None,
+        };
+        Some(Then::Simple(action))
+    }
+
+    /// Negates a condition, preferring negated matchers and flipped flags over `not` and
+    /// pushing it into groups.
+    pub fn negate(when: When) -> When {
+        match when {
+            When::Not(not) => *not.condition,
+            When::All(all) => When::Any(Any {
+                conditions: all.conditions.into_iter().map(negate).collect(),
+            }),
+            When::Any(any) => When::All(All {
+                conditions: any.conditions.into_iter().map(negate).collect(),
+            }),
+            When::HasAttachment(HasAttachment { has_attachment }) => {
+                When::HasAttachment(HasAttachment {
+                    has_attachment: !has_attachment,
+                })
+            }
+            When::Junk(Junk { junk }) => When::Junk(Junk { junk: !junk }),
+            when => match when
+                .string_test()
+                .and_then(|test| Some((test.field.clone(), test.matcher.negate()?, test.values)))
+            {
+                Some((field, matcher, values)) => {
+                    When::from_string_test(field, matcher, values.to_vec())
+                }
+                None => When::Not(Not {
+                    condition: Box::new(when),
+                }),
+            },
+        }
+    }
+
+    use crate::{
+        configuration::{
+            All, Any, ByteSize, CopyTo, DateComparison, DateTest, Field, Forward, Grouping,
+            HasAttachment, Junk, Matcher, MessageFilter, MoveTo, Not, Priority, PriorityTest,
+            RunOn, SimpleAction, Size, SizeComparison, Status, StatusTest, Tag, TagTest, Then,
+            When,
+        },
+        importer::{merge_values, Warning},
+        sieve::{self, Argument, Command, Test},
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_import() {
+        let source = r#"require ["fileinto", "imap4flags", "regex"];
+
+# rule:[Github]
+# Notifications
+# and more
+if anyof (address :matches "from" ["*@github.com", "*@noreply.github.com"],
+          header :contains "list-id" "github") {
+    fileinto "Dev.Github";
+    addflag "\\Seen";
+}
+elsif header :regex "subject" "^v[0-9]+$" {
+    fileinto :copy "Releases";
+    stop;
+}
+else {
+    keep;
+    fileinto "Other";
+}
+
+# rule:[Off]
+if false # not hasflag ["\\Flagged", "$label1"]
+{
+    vacation "away";
+    discard;
+}
+"#;
+        let import = import(source, Some(".")).unwrap();
+
+        assert_eq!(
+            serde_yaml::to_string(&import.config).unwrap(),
+            r#"- sieve_separator: .
+  message_filters:
+  - title: Github
+    description: |-
+      Notifications
+      and more
+    when:
+    - field: from
+      ends_with:
+      - '@github.com'
+      - '@noreply.github.com'
+    - field: list_id
+      contains:
+      - github
+    then:
+    - move_to: Dev/Github
+    - mark_read
+  - title: Rule on line 11
+    match: all
+    when:
+    - field: from
+      does_not_end_with:
+      - '@github.com'
+      - '@noreply.github.com'
+    - field: list_id
+      does_not_contain:
+      - github
+    - field: subject
+      matches_regex:
+      - ^v[0-9]+$
+    then:
+    - copy_to: Releases
+    - stop_processing
+  - title: Rule on line 15
+    match: all
+    when:
+    - field: from
+      does_not_end_with:
+      - '@github.com'
+      - '@noreply.github.com'
+    - field: list_id
+      does_not_contain:
+      - github
+    - not:
+        field: subject
+        matches_regex:
+        - ^v[0-9]+$
+    then:
+    - copy_to: Other
+  - title: Off
+    enabled: false
+    match: all
+    when:
+    - not:
+        status: flagged
+    - not:
+        tag: $label1
+    then:
+    - delete
+"#
+        );
+        assert_eq!(
+            import
+                .warnings
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            ["filter 'Off': dropped `vacation` on line 23, which has no equivalent"]
+        );
+    }
+
+    #[test]
+    fn test_import_reports_unsupported() {
+        let source = r#"fileinto "Early";
+if exists "X-Spam" { discard; }
+elsif size :over 1M { discard; }
+if header :contains "subject" "x" {
+    if true { stop; }
+}
+"#;
+        let import = import(source, None).unwrap();
+
+        assert_eq!(import.config.accounts[0].message_filters, []);
+        assert_eq!(
+            import
+                .warnings
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            [
+                "skipped `fileinto` on line 1, which is outside of a rule",
+                "filter 'Rule on line 2': skipped, cannot import test `exists` on line 2",
+                "filter 'Rule on line 3': skipped, it continues a rule that was skipped",
+                "filter 'Rule on line 4': skipped, nested `if` on line 5 cannot be expressed",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_convert_pattern() {
+        let pattern = |pattern| helpers::convert_pattern(pattern);
+        assert_eq!(pattern("a\\*b"), (Matcher::Is, "a*b".to_owned()));
+        assert_eq!(pattern("[ci]*"), (Matcher::StartsWith, "[ci]".to_owned()));
+        assert_eq!(pattern("*@x.org"), (Matcher::EndsWith, "@x.org".to_owned()));
+        assert_eq!(pattern("*bot*"), (Matcher::Contains, "bot".to_owned()));
+        assert_eq!(
+            pattern("build-?*"),
+            (Matcher::MatchesGlob, "build-?*".to_owned())
+        );
+        assert_eq!(
+            pattern("a?\\*"),
+            (Matcher::MatchesRegex, "^a.\\*$".to_owned())
+        );
+        assert_eq!(pattern("*"), (Matcher::MatchesGlob, "*".to_owned()));
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let source = r#"
+- sieve_separator: .
+  message_filters:
+    - title: Large
+      description: |-
+        Old newsletters
+        with attachments
+      enabled: false
+      match: all
+      when:
+        - size:
+            greater_than: 5MB
+        - date:
+            before: 2026-01-01
+        - has_attachment: true
+        - not:
+            status: read
+        - priority: high
+        - field: subject
+          starts_with: ["[news]"]
+      then:
+        - copy_to: Archive/News
+        - tag: $label2
+        - forward: jane@example.com
+        - mark_read
+        - stop_processing
+    - title: Junk
+      when:
+        - junk: true
+        - field: to_or_cc
+          is: ["spam@example.com"]
+        - field: { header: X-Spam-Flag }
+          does_not_contain: ["NO"]
+      then:
+        - mark_not_junk
+        - delete
+    - title: Never
+      when: []
+      then:
+        - delete
+"#;
+        let config = crate::configuration::parse(source).unwrap();
+        let script = printer::sieve::print_config(config.clone()).unwrap();
+
+        let import = import(&script, Some(".")).unwrap();
+        assert_eq!(import.warnings, []);
+        assert_eq!(import.config, config);
+    }
+
+    use super::*;
+    use crate::{configuration::Matcher, printer};
+}
+
+use std::collections::BTreeMap;
+
+use crate::{
+    configuration::{Account, Configuration, When},
+    importer::{Import, Warning},
+    sieve,
+};
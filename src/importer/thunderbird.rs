@@ -0,0 +1,747 @@
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error(transparent)]
+    Dat(#[from] dat::Error),
+    #[error("line {0}: `{1}` before the first filter")]
+    OutsideFilter(usize, String),
+}
+
+/// Reads a `msgFilterRules.dat` into a single account. The account's `thunderbird_id` is
+/// `thunderbird_id` if given, otherwise taken from the first folder the filters refer to.
+///
+/// Filters with conditions that cannot be expressed are skipped and actions without an
+/// equivalent are dropped, each with a warning.
+pub fn import(source: &str, thunderbird_id: Option<&str>) -> Result<Import, Error> {
+    let (header, filters) = dat::split_filters(dat::parse(source)?);
+    if let Some(entry) = header
+        .into_iter()
+        .find(|entry| !matches!(entry.name.as_str(), "version" | "logging"))
+    {
+        return Err(Error::OutsideFilter(entry.line, entry.name));
+    }
+
+    let thunderbird_id = thunderbird_id.map(str::to_owned).or_else(|| {
+        filters
+            .iter()
+            .flatten()
+            .filter(|entry| entry.name == "actionValue")
+            .find_map(|entry| helpers::parse_folder(&entry.value))
+            .map(|(account, _)| account.to_owned())
+    });
+    let mut importer = helpers::Importer {
+        thunderbird_id,
+        templates: BTreeMap::new(),
+        warnings: Vec::new(),
+    };
+    let message_filters = filters
+        .iter()
+        .filter_map(|entries| importer.import_filter(entries))
+        .collect();
+
+    Ok(Import {
+        config: Configuration {
+            accounts: vec![Account {
+                account: None,
+                email: None,
+                imap_host: None,
+                evolution_id: None,
+                thunderbird_id: importer.thunderbird_id,
+                thunderbird_path: None,
+                sieve_separator: None,
+                templates: importer.templates,
+                message_filters,
+            }],
+        },
+        warnings: importer.warnings,
+    })
+}
+
+mod helpers {
+    pub struct Importer {
+        pub thunderbird_id: Option<String>,
+        pub templates: BTreeMap<String, String>,
+        pub warnings: Vec<Warning>,
+    }
+
+    impl Importer {
+        /// Converts the entries of one filter, starting with its `name`.
+        pub fn import_filter(&mut self, entries: &[DatEntry]) -> Option<MessageFilter> {
+            let name = &entries[0].value;
+            let title = name.strip_suffix(MANAGED_MARKER).unwrap_or(name).to_owned();
+            let mut filter = MessageFilter {
+                title: title.clone(),
+                description: None,
+                enabled: true,
+                run_on: vec![RunOn::Incoming, RunOn::Manual],
+                grouping: Grouping::Any,
+                when: Vec::new(),
+                then: Vec::new(),
+            };
+            let mut condition = None;
+
+            let mut entries = entries[1..].iter().peekable();
+            while let Some(entry) = entries.next() {
+                match entry.name.as_str() {
+                    "enabled" => filter.enabled = entry.value != "no",
+                    "description" if !entry.value.is_empty() => {
+                        filter.description = Some(entry.value.clone());
+                    }
+                    "description" => {}
+                    "type" => filter.run_on = self.import_type(&title, &entry.value),
+                    "action" => {
+                        let value = entries
+                            .next_if(|entry| entry.name == "actionValue")
+                            .map(|entry| entry.value.as_str());
+                        match self.import_action(&entry.value, value) {
+                            Ok(action) => filter.then.push(action),
+                            Err(message) => self.warn(&title, message),
+                        }
+                    }
+                    "condition" => condition = Some(&entry.value),
+                    name => self.warn(&title, format!("ignored `{name}` on line {}", entry.line)),
+                }
+            }
+
+            let Some(condition) = condition else {
+                self.warn(&title, "skipped, it has no condition".to_owned());
+                return None;
+            };
+            match parse_condition(condition).and_then(|terms| convert_terms(&terms)) {
+                Ok((grouping, when)) => {
+                    filter.grouping = grouping;
+                    filter.when = when;
+                    Some(filter)
+                }
+                Err(reason) => {
+                    self.warn(&title, format!("skipped, {reason}"));
+                    None
+                }
+            }
+        }
+
+        fn warn(&mut self, filter: &str, message: String) {
+            self.warnings.push(Warning {
+                filter: Some(filter.to_owned()),
+                message,
+            });
+        }
+
+        /// Reads the `nsMsgFilterType` bitmask, the inverse of `format_type`.
+        fn import_type(&mut self, filter: &str, value: &str) -> Vec<RunOn> {
+            let Ok(mask) = value.parse::<u32>() else {
+                self.warn(filter, format!("ignored invalid type `{value}`"));
+                return vec![RunOn::Incoming, RunOn::Manual];
+            };
+
+            let flags = [
+                (0x1, RunOn::Incoming),
+                (0x10, RunOn::Manual),
+                (0x40, RunOn::AfterSending),
+                (0x80, RunOn::Archiving),
+                (0x100, RunOn::Periodic),
+            ];
+            let known = flags.iter().fold(0, |known, (flag, _)| known | flag);
+            if mask & !known != 0 {
+                self.warn(
+                    filter,
+                    format!("ignored filter type bits {:#x}", mask & !known),
+                );
+            }
+
+            flags
+                .iter()
+                .filter(|(flag, _)| mask & flag != 0)
+                .map(|(_, run_on)| *run_on)
+                .collect()
+        }
+
+        /// Converts an `action`/`actionValue` pair, the inverse of `format_action`.
+        fn import_action(&mut self, name: &str, value: Option<&str>) -> Result<Then, String> {
+            let value = || value.ok_or_else(|| format!("dropped action `{name}` without a value"));
+
+            let action = match name {
+                "Move to folder" => Then::MoveTo(MoveTo {
+                    folder: self.import_folder(value()?)?,
+                }),
+                "Copy to folder" => Then::CopyTo(CopyTo {
+                    folder: self.import_folder(value()?)?,
+                }),
+                "AddTag" => Then::Tag(Tag {
+                    tag: value()?.to_owned(),
+                }),
+                "Change priority" => Then::SetPriority(SetPriority {
+                    priority: parse_priority(value()?).ok_or_else(|| {
+                        format!("dropped unknown priority `{}`", value().unwrap_or_default())
+                    })?,
+                }),
+                "Forward" => Then::Forward(Forward {
+                    to: value()?.to_owned(),
+                }),
+                "Reply" => Then::ReplyWith(ReplyWith {
+                    template: self.import_template(value()?),
+                }),
+                "Delete" => Then::Simple(SimpleAction::Delete),
+                "Mark read" => Then::Simple(SimpleAction::MarkRead),
+                "Mark unread" => Then::Simple(SimpleAction::MarkUnread),
+                "Mark flagged" => Then::Simple(SimpleAction::Flag),
+                "JunkScore" if value()? == "100" => Then::Simple(SimpleAction::MarkJunk),
+                "JunkScore" if value()? == "0" => Then::Simple(SimpleAction::MarkNotJunk),
+                "Stop execution" => Then::Simple(SimpleAction::StopProcessing),
+                _ => return Err(format!("dropped action `{name}`, which has no equivalent")),
+            };
+            Ok(action)
+        }
+
+        /// Returns the folder of a folder URI in the imported account.
+        fn import_folder(&self, uri: &str) -> Result<String, String> {
+            match parse_folder(uri) {
+                Some((account, folder)) if Some(account) == self.thunderbird_id.as_deref() => {
+                    Ok(folder.to_owned())
+                }
+                _ => Err(format!(
+                    "dropped folder `{uri}`, which is not in this account"
+                )),
+            }
+        }
+
+        /// Adds `uri` to the account's templates, returning its name.
+        fn import_template(&mut self, uri: &str) -> String {
+            if let Some((name, _)) = self.templates.iter().find(|(_, other)| *other == uri) {
+                return name.clone();
+            }
+            let name = format!("template{}", self.templates.len() + 1);
+            self.templates.insert(name.clone(), uri.to_owned());
+            name
+        }
+    }
+
+    /// Splits a folder URI, e.g. `imap://user@host/INBOX/Github`, into the account and the
+    /// folder path.
+    pub fn parse_folder(uri: &str) -> Option<(&str, &str)> {
+        let (_, rest) = uri.split_once("://")?;
+        let (account, folder) = rest.split_once('/')?;
+        (!account.is_empty() && !folder.is_empty()).then_some((account, folder))
+    }
+
+    fn parse_priority(value: &str) -> Option<Priority> {
+        let priority = match value {
+            "Lowest" => Priority::Lowest,
+            "Low" => Priority::Low,
+            "Normal" => Priority::Normal,
+            "High" => Priority::High,
+            "Highest" => Priority::Highest,
+            _ => return None,
+        };
+        Some(priority)
+    }
+
+    /// A search term, or a parenthesized group of them.
+    #[derive(Debug, PartialEq)]
+    pub enum Term {
+        Test {
+            attribute: String,
+            /// Custom headers are written as quoted attributes.
+            custom: bool,
+            operator: String,
+            value: String,
+        },
+        Group(Vec<(Operator, Term)>),
+    }
+
+    /// How a term joins the terms before it.
+    #[derive(Copy, Clone, Debug, PartialEq)]
+    pub enum Operator {
+        And,
+        Or,
+    }
+
+    /// Parses a search term list, e.g. `AND (from,contains,a) OR ((to,is,b) AND (cc,is,c))`,
+    /// the inverse of `format_condition`.
+    pub fn parse_condition(condition: &str) -> Result<Vec<(Operator, Term)>, String> {
+        if condition.trim() == "ALL" {
+            return Err("it matches all messages, which has no equivalent".to_owned());
+        }
+
+        let mut parser = Parser {
+            chars: condition.chars().collect(),
+            position: 0,
+        };
+        parser
+            .terms(false)
+            .map_err(|reason| format!("cannot read condition `{condition}`: {reason}"))
+    }
+
+    struct Parser {
+        chars: Vec<char>,
+        position: usize,
+    }
+
+    impl Parser {
+        fn terms(&mut self, nested: bool) -> Result<Vec<(Operator, Term)>, String> {
+            let mut terms = Vec::new();
+
+            loop {
+                self.skip_whitespace();
+                match self.peek() {
+                    None if nested => return Err("missing `)`".to_owned()),
+                    None => return Ok(terms),
+                    Some(')') if nested => {
+                        self.position += 1;
+                        return Ok(terms);
+                    }
+                    _ => {}
+                }
+
+                let operator = match () {
+                    _ if self.eat("AND") => Operator::And,
+                    _ if self.eat("OR") => Operator::Or,
+                    _ if terms.is_empty() => Operator::And,
+                    _ => return Err(format!("expected `AND` or `OR` at {}", self.position)),
+                };
+                self.skip_whitespace();
+                if !self.eat("(") {
+                    return Err(format!("expected `(` at {}", self.position));
+                }
+                self.skip_whitespace();
+
+                let starts_group = self.peek() == Some('(')
+                    || self.rest().starts_with("AND ")
+                    || self.rest().starts_with("OR ");
+                let term = match starts_group {
+                    true => Term::Group(self.terms(true)?),
+                    false => self.test()?,
+                };
+                terms.push((operator, term));
+            }
+        }
+
+        /// Reads `attribute,operator,value)`.
+        fn test(&mut self) -> Result<Term, String> {
+            let custom = self.peek() == Some('"');
+            let attribute = match custom {
+                true => self.quoted()?,
+                false => self.until(',')?,
+            };
+            if !custom && attribute.is_empty() {
+                return Err(format!("missing attribute at {}", self.position));
+            }
+            if !self.eat(",") {
+                return Err(format!("expected `,` at {}", self.position));
+            }
+            let operator = self.until(',')?;
+            self.position += 1;
+            let value = match self.peek() {
+                Some('"') => self.quoted()?,
+                _ => self.until(')')?,
+            };
+            if !self.eat(")") {
+                return Err(format!("expected `)` at {}", self.position));
+            }
+
+            Ok(Term::Test {
+                attribute: attribute.trim().to_owned(),
+                custom,
+                operator: operator.trim().to_owned(),
+                value,
+            })
+        }
+
+        /// Reads a quoted string in which only quotes are escaped, as `\"`, the inverse of
+        /// `format_value`. Other backslashes are kept.
+        fn quoted(&mut self) -> Result<String, String> {
+            self.position += 1;
+            let mut value = String::new();
+            loop {
+                match self.next() {
+                    Some('\\') if self.peek() == Some('"') => value.extend(self.next()),
+                    Some('"') => return Ok(value),
+                    Some(c) => value.push(c),
+                    None => return Err("unterminated quoted value".to_owned()),
+                }
+            }
+        }
+
+        /// Reads up to, but not including, `end`.
+        fn until(&mut self, end: char) -> Result<String, String> {
+            let start = self.position;
+            while self.peek() != Some(end) {
+                if self.next().is_none() {
+                    return Err(format!("expected `{end}`"));
+                }
+            }
+            Ok(self.chars[start..self.position].iter().collect())
+        }
+
+        fn eat(&mut self, token: &str) -> bool {
+            let matches = self.rest().starts_with(token);
+            if matches {
+                self.position += token.chars().count();
+            }
+            matches
+        }
+
+        fn rest(&self) -> String {
+            self.chars[self.position..].iter().collect()
+        }
+
+        fn peek(&self) -> Option<char> {
+            self.chars.get(self.position).copied()
+        }
+
+        fn next(&mut self) -> Option<char> {
+            let c = self.peek();
+            self.position += 1;
+            c
+        }
+
+        fn skip_whitespace(&mut self) {
+            while self.peek().is_some_and(char::is_whitespace) {
+                self.position += 1;
+            }
+        }
+    }
+
+    /// Converts a term list into a grouping and conditions. Thunderbird only offers lists that
+    /// are all `AND` or all `OR`, so anything else is reported.
+    pub fn convert_terms(terms: &[(Operator, Term)]) -> Result<(Grouping, Vec<When>), String> {
+        let operators = terms.iter().skip(1).map(|(operator, _)| *operator);
+        let grouping = match (
+            operators.clone().all(|operator| operator == Operator::And),
+            operators.clone().all(|operator| operator == Operator::Or),
+        ) {
+            (true, true) => Grouping::Any,
+            (true, false) => Grouping::All,
+            (false, true) => Grouping::Any,
+            (false, false) => return Err("its condition mixes `AND` and `OR`".to_owned()),
+        };
+
+        let mut conditions = Vec::new();
+        for (_, term) in terms {
+            let when = match term {
+                Term::Test {
+                    attribute,
+                    custom,
+                    operator,
+                    value,
+                } => convert_test(attribute, *custom, operator, value).ok_or_else(|| {
+                    format!("cannot import condition `({attribute},{operator},{value})`")
+                })?,
+                Term::Group(terms) => match convert_terms(terms)? {
+                    (_, mut conditions) if conditions.len() == 1 => conditions.remove(0),
+                    (Grouping::All, conditions) => When::All(All { conditions }),
+                    (Grouping::Any, conditions) => When::Any(Any { conditions }),
+                },
+            };
+            conditions.push(when);
+        }
+
+        Ok((grouping, merge_values(grouping, conditions)))
+    }
+
+    /// Converts a single search term, the inverse of `format_term`.
+    fn convert_test(attribute: &str, custom: bool, operator: &str, value: &str) -> Option<When> {
+        let field = match attribute {
+            _ if custom => match attribute.to_ascii_lowercase().as_str() {
+                "bcc" => Field::Bcc,
+                "reply-to" => Field::ReplyTo,
+                "list-id" => Field::ListId,
+                "sender" => Field::Sender,
+                _ => Field::Header(attribute.to_owned()),
+            },
+            "from" => Field::From,
+            "to" => Field::To,
+            "cc" => Field::Cc,
+            "to or cc" => Field::ToOrCc,
+            "subject" => Field::Subject,
+            "body" => Field::Body,
+            _ => return convert_state_test(attribute, operator, value),
+        };
+
+        let matcher = match operator {
+            "contains" => Matcher::Contains,
+            "doesn't contain" => Matcher::DoesNotContain,
+            "is" => Matcher::Is,
+            "isn't" => Matcher::IsNot,
+            "begins with" => Matcher::StartsWith,
+            "ends with" => Matcher::EndsWith,
+            _ => return None,
+        };
+        Some(When::from_string_test(
+            field,
+            matcher,
+            vec![value.to_owned()],
+        ))
+    }
+
+    fn convert_state_test(attribute: &str, operator: &str, value: &str) -> Option<When> {
+        let negated = match operator {
+            "isn't" | "doesn't contain" => true,
+            "is" | "contains" | "is greater than" | "is less than" | "is before" | "is after" => {
+                false
+            }
+            _ => return None,
+        };
+
+        let when = match (attribute, operator) {
+            ("size", "is greater than" | "is less than") => {
+                let size = ByteSize(value.parse::<u64>().ok()?.checked_mul(1024)?);
+                When::Size(Size {
+                    size: match operator {
+                        "is greater than" => SizeComparison::GreaterThan(size),
+                        _ => SizeComparison::LessThan(size),
+                    },
+                })
+            }
+            ("age in days", "is greater than" | "is less than") => {
+                let days = value.parse().ok()?;
+                When::AgeDays(AgeDays {
+                    age_days: match operator {
+                        "is greater than" => AgeComparison::MoreThan(days),
+                        _ => AgeComparison::LessThan(days),
+                    },
+                })
+            }
+            ("date", "is before" | "is after" | "is" | "isn't") => {
+                let date = parse_date(value)?;
+                When::Date(DateTest {
+                    date: match operator {
+                        "is before" => DateComparison::Before(date),
+                        "is after" => DateComparison::After(date),
+                        _ => DateComparison::On(date),
+                    },
+                })
+            }
+            ("status", "is" | "isn't") => When::Status(StatusTest {
+                status: match value {
+                    "read" => Status::Read,
+                    "flagged" => Status::Flagged,
+                    "replied" => Status::Replied,
+                    _ => return None,
+                },
+            }),
+            ("has attachment status", "is" | "isn't") if value == "true" => {
+                When::HasAttachment(HasAttachment {
+                    has_attachment: !negated,
+                })
+            }
+            ("priority", "is" | "isn't") => When::Priority(PriorityTest {
+                priority: parse_priority(value)?,
+            }),
+            ("junk status", "is" | "isn't") if value == "2" => When::Junk(Junk { junk: !negated }),
+            ("tag", "contains" | "doesn't contain") => When::Tag(TagTest {
+                tag: value.to_owned(),
+            }),
+            _ => return None,
+        };
+
+        let negate = negated && !matches!(when, When::HasAttachment(_) | When::Junk(_));
+        Some(match negate {
+            true => When::Not(Not {
+                condition: Box::new(when),
+            }),
+            false => when,
+        })
+    }
+
+    /// Parses a date like `01-Jan-2026`, the inverse of `format_date`.
+    fn parse_date(value: &str) -> Option<Date> {
+        const MONTHS: [&str; 12] = [
+            "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+        ];
+        let mut parts = value.split('-');
+        let (day, month, year) = (parts.next()?, parts.next()?, parts.next()?);
+        let month = MONTHS.iter().position(|name| *name == month)? + 1;
+        format!("{year}-{month:02}-{day:0>2}").parse().ok()
+    }
+
+    use std::collections::BTreeMap;
+
+    use crate::{
+        configuration::{
+            AgeComparison, AgeDays, All, Any, ByteSize, CopyTo, Date, DateComparison, DateTest,
+            Field, Forward, Grouping, HasAttachment, Junk, Matcher, MessageFilter, MoveTo, Not,
+            Priority, PriorityTest, ReplyWith, RunOn, SetPriority, SimpleAction, Size,
+            SizeComparison, Status, StatusTest, Tag, TagTest, Then, When,
+        },
+        dat::DatEntry,
+        importer::{merge_values, Warning},
+        printer::thunderbird::MANAGED_MARKER,
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_import() {
+        let source = r#"version="9"
+logging="no"
+name="Github"
+enabled="yes"
+type="17"
+action="Move to folder"
+actionValue="imap://jdoe@mail.example.com/Github"
+action="Mark read"
+condition="OR (from,ends with,@github.com) OR (from,ends with,@noreply.github.com)"
+name="Not for me"
+enabled="no"
+description="Sorted by hand"
+type="16"
+action="AddTag"
+actionValue="$label1"
+action="Reply"
+actionValue="mailbox://nobody@Local%20Folders/Templates?messageId=1"
+condition="AND (\"List-Id\",isn't,a) AND (\"List-Id\",isn't,b) AND ((subject,contains,x) OR (subject,contains,y)) AND (status,isn't,read)"
+"#;
+        let import = import(source, None).unwrap();
+        let config = serde_yaml::to_string(&import.config).unwrap();
+
+        assert_eq!(
+            config,
+            "\
+- thunderbird_id: jdoe@mail.example.com
+  templates:
+    template1: mailbox://nobody@Local%20Folders/Templates?messageId=1
+  message_filters:
+  - title: Github
+    when:
+    - field: from
+      ends_with:
+      - '@github.com'
+      - '@noreply.github.com'
+    then:
+    - move_to: Github
+    - mark_read
+  - title: Not for me
+    description: Sorted by hand
+    enabled: false
+    run_on:
+    - manual
+    match: all
+    when:
+    - field: list_id
+      is_not:
+      - a
+      - b
+    - field: subject
+      contains:
+      - x
+      - y
+    - not:
+        status: read
+    then:
+    - tag: $label1
+    - reply_with: template1
+"
+        );
+        assert_eq!(import.warnings, []);
+        assert_eq!(crate::configuration::parse(&config).unwrap(), import.config);
+    }
+
+    #[test]
+    fn test_import_reports_unsupported() {
+        let source = r#"name="Everything"
+action="Mark read"
+condition="ALL"
+name="Address book"
+action="Mark read"
+condition="AND (from,is in ab,moz-abmdbdirectory://abook.mab)"
+name="Mixed"
+action="Mark read"
+condition="AND (from,is,a) OR (to,is,b) AND (cc,is,c)"
+name="Threads"
+type="3"
+action="Watch thread"
+action="Move to folder"
+actionValue="imap://other@example.com/Archive"
+action="Stop execution"
+condition="AND (size,is greater than,100)"
+"#;
+        let import = import(source, Some("me@example.com")).unwrap();
+        let warnings = import
+            .warnings
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            warnings,
+            [
+                "filter 'Everything': skipped, it matches all messages, which has no equivalent",
+                "filter 'Address book': skipped, cannot import condition \
+                 `(from,is in ab,moz-abmdbdirectory://abook.mab)`",
+                "filter 'Mixed': skipped, its condition mixes `AND` and `OR`",
+                "filter 'Threads': ignored filter type bits 0x2",
+                "filter 'Threads': dropped action `Watch thread`, which has no equivalent",
+                "filter 'Threads': dropped folder `imap://other@example.com/Archive`, \
+                 which is not in this account",
+            ]
+        );
+
+        let filters = &import.config.accounts[0].message_filters;
+        assert_eq!(filters.len(), 1);
+        assert_eq!(filters[0].run_on, [RunOn::Incoming]);
+        assert_eq!(
+            filters[0].when,
+            [When::Size(Size {
+                size: SizeComparison::GreaterThan(ByteSize(100 * 1024)),
+            })]
+        );
+        assert_eq!(
+            filters[0].then,
+            [Then::Simple(SimpleAction::StopProcessing)]
+        );
+    }
+
+    #[test]
+    fn test_parse_condition() {
+        assert_eq!(
+            helpers::parse_condition(r#"AND ("X-Spam",contains,"a \"b\", (c)")"#),
+            Ok(vec![(
+                helpers::Operator::And,
+                helpers::Term::Test {
+                    attribute: "X-Spam".to_owned(),
+                    custom: true,
+                    operator: "contains".to_owned(),
+                    value: r#"a "b", (c)"#.to_owned(),
+                }
+            )])
+        );
+        assert_eq!(
+            helpers::parse_condition(r#"AND (subject,is,"C:\dir\ (x)") AND (body,is,a\b)"#),
+            Ok(vec![
+                (
+                    helpers::Operator::And,
+                    helpers::Term::Test {
+                        attribute: "subject".to_owned(),
+                        custom: false,
+                        operator: "is".to_owned(),
+                        value: r#"C:\dir\ (x)"#.to_owned(),
+                    }
+                ),
+                (
+                    helpers::Operator::And,
+                    helpers::Term::Test {
+                        attribute: "body".to_owned(),
+                        custom: false,
+                        operator: "is".to_owned(),
+                        value: r#"a\b"#.to_owned(),
+                    }
+                ),
+            ])
+        );
+        assert!(helpers::parse_condition("AND (from,contains,a")
+            .unwrap_err()
+            .ends_with("expected `)`"));
+    }
+
+    use super::*;
+    use crate::configuration::{ByteSize, RunOn, SimpleAction, Size, SizeComparison, Then, When};
+}
+
+use std::collections::BTreeMap;
+
+use crate::{
+    configuration::{Account, Configuration},
+    dat,
+    importer::Import,
+};
@@ -0,0 +1,204 @@
+pub mod evolution;
+pub mod thunderbird;
+
+/// A filter file written by `install`, with the copy made of the file it replaced.
+#[derive(Debug)]
+pub struct Installed {
+    pub path: PathBuf,
+    pub backup: Option<PathBuf>,
+}
+
+/// Writes `contents` to `path`, first copying an existing file to
+/// `<path>.<YYYYMMDD-HHMMSS>.bak` with the UTC time `now` in seconds since the epoch. Backups
+/// are never overwritten: a second one in the same second is numbered, e.g. `.<...>.1.bak`.
+///
+/// The contents are written to a temporary file next to `path` and renamed over it, so the
+/// client never reads a partly written file.
+pub fn write_with_backup(path: &Path, contents: &str, now: i64) -> io::Result<Installed> {
+    stream_with_backup(path, now, |file| file.write_all(contents.as_bytes()))
+}
+
+/// Like [`write_with_backup`], but lets `write` stream the contents into the temporary file.
+pub fn stream_with_backup<E, F>(path: &Path, now: i64, write: F) -> Result<Installed, E>
+where
+    E: From<io::Error>,
+    F: FnOnce(&mut BufWriter<File>) -> Result<(), E>,
+{
+    let backup = match path.exists() {
+        true => Some(helpers::backup(path, now)?),
+        false => None,
+    };
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut temporary = path.as_os_str().to_owned();
+    temporary.push(".tmp");
+    let mut file = BufWriter::new(File::create(&temporary)?);
+    let written = write(&mut file).and_then(|()| {
+        file.into_inner()
+            .map(drop)
+            .map_err(|err| err.into_error().into())
+    });
+    if written.is_err() {
+        let _ = fs::remove_file(&temporary);
+    }
+    written?;
+    fs::rename(&temporary, path)?;
+
+    Ok(Installed {
+        path: path.to_owned(),
+        backup,
+    })
+}
+
+/// Reads the sections of an INI or key file, in order, with their keys.
+pub fn parse_ini(source: &str) -> Vec<(String, BTreeMap<String, String>)> {
+    let mut sections: Vec<(String, BTreeMap<String, String>)> = Vec::new();
+    for line in source.lines().map(str::trim) {
+        if line.starts_with(['#', ';']) {
+            continue;
+        } else if let Some(section) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            sections.push((section.to_owned(), BTreeMap::new()));
+        } else if let (Some((key, value)), Some((_, keys))) =
+            (line.split_once('='), sections.last_mut())
+        {
+            keys.insert(key.trim().to_owned(), value.trim().to_owned());
+        }
+    }
+    sections
+}
+
+mod helpers {
+    /// Formats `now` as `YYYYMMDD-HHMMSS`, which sorts backups by age.
+    pub fn format_timestamp(now: i64) -> String {
+        let date = Date::from_days_since_epoch(now.div_euclid(86_400));
+        let seconds = now.rem_euclid(86_400);
+        format!(
+            "{:04}{:02}{:02}-{:02}{:02}{:02}",
+            date.year,
+            date.month,
+            date.day,
+            seconds / 3600,
+            seconds / 60 % 60,
+            seconds % 60
+        )
+    }
+
+    /// Copies `path` to a new backup file and returns its path.
+    pub fn backup(path: &Path, now: i64) -> io::Result<PathBuf> {
+        let timestamp = format_timestamp(now);
+        let mut original = File::open(path)?;
+        for counter in 0.. {
+            let mut name = path.as_os_str().to_owned();
+            match counter {
+                0 => name.push(format!(".{timestamp}.bak")),
+                _ => name.push(format!(".{timestamp}.{counter}.bak")),
+            }
+            let backup = PathBuf::from(name);
+            let mut file = match OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&backup)
+            {
+                Ok(file) => file,
+                Err(err) if err.kind() == io::ErrorKind::AlreadyExists => continue,
+                Err(err) => return Err(err),
+            };
+            io::copy(&mut original, &mut file)?;
+            file.sync_all()?;
+            return Ok(backup);
+        }
+        unreachable!("the counter is unbounded")
+    }
+
+    use std::{
+        fs::{File, OpenOptions},
+        io,
+        path::{Path, PathBuf},
+    };
+
+    use crate::configuration::Date;
+}
+
+#[cfg(test)]
+mod tests {
+    /// An empty directory below the system's temporary directory, removed again on drop so
+    /// that failing tests clean up too.
+    pub struct Scratch(PathBuf);
+
+    impl Scratch {
+        pub fn new(name: &str) -> Self {
+            let root = std::env::temp_dir().join(format!("mmf-{name}-{}", std::process::id()));
+            let _ = fs::remove_dir_all(&root);
+            fs::create_dir_all(&root).unwrap();
+            Scratch(root)
+        }
+    }
+
+    impl std::ops::Deref for Scratch {
+        type Target = Path;
+
+        fn deref(&self) -> &Path {
+            &self.0
+        }
+    }
+
+    impl Drop for Scratch {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn test_format_timestamp() {
+        assert_eq!(helpers::format_timestamp(0), "19700101-000000");
+        assert_eq!(helpers::format_timestamp(1_792_310_645), "20261018-080405");
+    }
+
+    #[test]
+    fn test_write_with_backup() {
+        let root = Scratch::new("install");
+        let path = root.join("dir/filters");
+
+        let installed = write_with_backup(&path, "one", 0).unwrap();
+        assert_eq!(installed.backup, None);
+        let installed = write_with_backup(&path, "two", 60).unwrap();
+        let backup = root.join("dir/filters.19700101-000100.bak");
+        assert_eq!(installed.backup.as_ref(), Some(&backup));
+        assert_eq!(fs::read_to_string(&path).unwrap(), "two");
+        assert_eq!(fs::read_to_string(&backup).unwrap(), "one");
+
+        let installed = write_with_backup(&path, "three", 60).unwrap();
+        let second = root.join("dir/filters.19700101-000100.1.bak");
+        assert_eq!(installed.backup.as_ref(), Some(&second));
+        assert_eq!(fs::read_to_string(&second).unwrap(), "two");
+        assert_eq!(fs::read_to_string(&backup).unwrap(), "one");
+    }
+
+    #[test]
+    fn test_stream_with_backup() {
+        let root = Scratch::new("stream");
+        let path = root.join("filters");
+
+        stream_with_backup(&path, 0, |file| writeln!(file, "one")).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "one\n");
+
+        let failed = stream_with_backup(&path, 60, |file| {
+            writeln!(file, "two")?;
+            Err(io::Error::other("interrupted"))
+        });
+        assert!(failed.is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "one\n");
+        assert!(!root.join("filters.tmp").exists());
+    }
+
+    use super::*;
+}
+
+use std::{
+    collections::BTreeMap,
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+};
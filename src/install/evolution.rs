@@ -0,0 +1,92 @@
+/// Writes the accounts with an `evolution_id` or an `email` into `<dir>/mail/filters.xml`,
+/// where `dir` is Evolution's configuration directory, backing up the file it replaces.
+pub fn install(mut config: Configuration, dir: &Path, now: i64) -> crate::Result<Installed> {
+    resolver::resolve_evolution_ids(&mut config, &dir.join("sources"))?;
+    config
+        .accounts
+        .retain(|account| account.evolution_id.is_some());
+
+    let installed =
+        install::stream_with_backup(&dir.join("mail").join("filters.xml"), now, |file| {
+            printer::evolution::write_config(config, file, true)
+        })?;
+    Ok(installed)
+}
+
+#[cfg(test)]
+mod tests {
+    /// Writes Evolution's sources for a mail account `1a2b` of `JDoe@example.com`, and for
+    /// the local folders.
+    fn write_sources(root: &Path) {
+        fs::create_dir_all(root.join("sources")).unwrap();
+        for (uid, source) in [
+            (
+                "1a2b",
+                "[Data Source]\nDisplayName=Work\n\n[Mail Account]\nIdentityUid=3c4d\n",
+            ),
+            (
+                "3c4d",
+                "[Data Source]\nParent=1a2b\n\n[Mail Identity]\nAddress=JDoe@example.com\n",
+            ),
+            (
+                "local",
+                "[Data Source]\nDisplayName=On This Computer\n\n[Mail Account]\n",
+            ),
+        ] {
+            fs::write(root.join("sources").join(format!("{uid}.source")), source).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_install() {
+        let root = Scratch::new("evolution");
+        write_sources(&root);
+        let config = crate::configuration::parse(
+            "\
+- email: jdoe@example.com
+  message_filters:
+    - title: Github
+      when:
+        - field: from
+          ends_with: ['@github.com']
+      then:
+        - move_to: Github
+- thunderbird_id: elsewhere
+  message_filters: []
+",
+        )
+        .unwrap();
+
+        let path = root.join("mail/filters.xml");
+        fs::create_dir_all(root.join("mail")).unwrap();
+        fs::write(&path, "<filteroptions/>\n").unwrap();
+
+        let installed = install(config.clone(), &root, 0).unwrap();
+        assert_eq!(installed.path, path);
+        let backup = root.join("mail/filters.xml.19700101-000000.bak");
+        assert_eq!(installed.backup.as_ref(), Some(&backup));
+        let written = fs::read_to_string(&path).unwrap();
+        assert!(written.contains("folder://1a2b/Github"));
+        assert_eq!(written.matches("<ruleset>").count(), 1);
+
+        let installed = install(config, &root, 0).unwrap();
+        assert_eq!(
+            installed.backup,
+            Some(root.join("mail/filters.xml.19700101-000000.1.bak"))
+        );
+        assert_eq!(fs::read_to_string(&backup).unwrap(), "<filteroptions/>\n");
+    }
+
+    use std::fs;
+
+    use super::*;
+    use crate::install::tests::Scratch;
+}
+
+use std::path::Path;
+
+use crate::{
+    configuration::Configuration,
+    install::{self, Installed},
+    printer, resolver,
+};
@@ -0,0 +1,419 @@
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("{path}: {source}")]
+    Read {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+    #[error("no profile named `{0}` in profiles.ini")]
+    UnknownProfile(String),
+    #[error("profiles.ini has no default profile, pick one with --profile")]
+    NoDefaultProfile,
+    #[error("account `{0}`: no IMAP server in the profile has this thunderbird_id")]
+    UnknownServer(String),
+    #[error("multiple accounts would be written to {0}")]
+    DuplicatePath(PathBuf),
+}
+
+/// A profile listed in `profiles.ini`.
+#[derive(Debug, PartialEq)]
+pub struct Profile {
+    pub name: String,
+    pub path: PathBuf,
+}
+
+/// An incoming server of a profile, from the `mail.server.<key>.*` preferences.
+#[derive(Debug, PartialEq)]
+pub struct Server {
+    pub key: String,
+    pub kind: String,
+    pub hostname: String,
+    pub username: String,
+    pub directory: PathBuf,
+}
+
+impl Server {
+    /// Returns the id Thunderbird uses in folder URIs, e.g. `jdoe%40example.com@mail.example.com`.
+    pub fn thunderbird_id(&self) -> String {
+        resolver::format_thunderbird_id(&self.username, &self.hostname)
+    }
+}
+
+/// Returns the profile named `name` from `<root>/profiles.ini`, or the default profile: the one
+/// the installation uses, else the one marked as default, else the only one.
+pub fn find_profile(root: &Path, name: Option<&str>) -> Result<Profile, Error> {
+    let sections = install::parse_ini(&read(&root.join("profiles.ini"))?);
+
+    let profiles = sections
+        .iter()
+        .filter(|(section, _)| section.starts_with("Profile"))
+        .filter_map(|(_, keys)| {
+            let path = keys.get("Path")?;
+            let path = match keys.get("IsRelative").map(String::as_str) {
+                Some("0") => PathBuf::from(path),
+                _ => root.join(path),
+            };
+            let default = keys.get("Default").map(String::as_str) == Some("1");
+            Some((keys.get("Name").cloned().unwrap_or_default(), path, default))
+        })
+        .collect::<Vec<_>>();
+
+    let installed = sections
+        .iter()
+        .filter(|(section, _)| section.starts_with("Install"))
+        .find_map(|(_, keys)| keys.get("Default"))
+        .map(|path| root.join(path));
+
+    let found = match name {
+        Some(name) => profiles
+            .iter()
+            .find(|(profile, path, _)| profile == name || path.ends_with(name))
+            .ok_or_else(|| Error::UnknownProfile(name.to_owned()))?,
+        None => profiles
+            .iter()
+            .find(|(_, path, _)| Some(path) == installed.as_ref())
+            .or_else(|| profiles.iter().find(|(_, _, default)| *default))
+            .or(match profiles.as_slice() {
+                [only] => Some(only),
+                _ => None,
+            })
+            .ok_or(Error::NoDefaultProfile)?,
+    };
+
+    Ok(Profile {
+        name: found.0.clone(),
+        path: found.1.clone(),
+    })
+}
+
+/// Reads the incoming servers from the profile's `prefs.js`. Servers without a directory
+/// preference use Thunderbird's default, `ImapMail/<hostname>` for IMAP.
+pub fn read_servers(profile: &Path) -> Result<Vec<Server>, Error> {
+    let prefs = helpers::parse_prefs(&read(&profile.join("prefs.js"))?);
+
+    let mut servers: BTreeMap<&str, BTreeMap<&str, &str>> = BTreeMap::new();
+    for (name, value) in &prefs {
+        if let Some((key, attribute)) = name
+            .strip_prefix("mail.server.")
+            .and_then(|rest| rest.split_once('.'))
+        {
+            servers.entry(key).or_default().insert(attribute, value);
+        }
+    }
+
+    let servers = servers
+        .into_iter()
+        .filter_map(|(key, attributes)| {
+            let hostname = attributes.get("hostname")?.to_string();
+            let kind = attributes.get("type").copied().unwrap_or("imap").to_owned();
+            let relative = attributes
+                .get("directory-rel")
+                .and_then(|relative| relative.strip_prefix("[ProfD]"));
+            let directory = match (relative, attributes.get("directory")) {
+                (Some(relative), _) => profile.join(relative),
+                (None, Some(directory)) => PathBuf::from(directory),
+                (None, None) => profile.join("ImapMail").join(&hostname),
+            };
+            Some(Server {
+                key: key.to_owned(),
+                kind,
+                hostname,
+                username: attributes
+                    .get("userName")
+                    .copied()
+                    .unwrap_or_default()
+                    .to_owned(),
+                directory,
+            })
+        })
+        .collect();
+
+    Ok(servers)
+}
+
+/// Returns the `msgFilterRules.dat` of each account with a `thunderbird_id`, given or derived
+/// from `email` and `imap_host`: the one of the IMAP server with that id, or the account's
+/// `thunderbird_path` relative to the profile.
+pub fn find_files(config: Configuration, profile: &Path) -> Result<Vec<(PathBuf, Account)>, Error> {
+    let servers = read_servers(profile)?;
+
+    let mut files: Vec<(PathBuf, Account)> = Vec::new();
+    for account in config.accounts {
+        let Some(thunderbird_id) = resolver::thunderbird_id(&account) else {
+            continue;
+        };
+        let path = match &account.thunderbird_path {
+            Some(path) => profile.join(path),
+            None => servers
+                .iter()
+                .find(|server| server.kind == "imap" && server.thunderbird_id() == thunderbird_id)
+                .map(|server| server.directory.join("msgFilterRules.dat"))
+                .ok_or(Error::UnknownServer(thunderbird_id))?,
+        };
+        if files.iter().any(|(other, _)| *other == path) {
+            return Err(Error::DuplicatePath(path));
+        }
+        files.push((path, account));
+    }
+
+    Ok(files)
+}
+
+/// Writes each account into its file from [`find_files`]. Filters created in Thunderbird are
+/// kept, as with `print thunderbird --merge-into`, and replaced files are backed up first.
+///
+/// Every file is merged before the first one is written, so an account that fails leaves the
+/// profile as it was.
+pub fn install(config: Configuration, profile: &Path, now: i64) -> crate::Result<Vec<Installed>> {
+    let mut outputs = Vec::new();
+    for (path, account) in find_files(config, profile)? {
+        let existing = match path.exists() {
+            true => read(&path)?,
+            false => String::new(),
+        };
+        let config = Configuration {
+            accounts: vec![account],
+        };
+        let output = printer::thunderbird::merge_config(config, &existing)
+            .map_err(|err| format!("{}: {err}", path.display()))?;
+        outputs.push((path, output));
+    }
+
+    let mut installed = Vec::new();
+    for (path, output) in outputs {
+        installed.push(install::write_with_backup(
+            &path,
+            &format!("{output}\n"),
+            now,
+        )?);
+    }
+
+    Ok(installed)
+}
+
+fn read(path: &Path) -> Result<String, Error> {
+    fs::read_to_string(path).map_err(|source| Error::Read {
+        path: path.to_owned(),
+        source,
+    })
+}
+
+mod helpers {
+    /// Reads the string preferences of a `prefs.js`, which has one
+    /// `user_pref("name", value);` per line.
+    pub fn parse_prefs(source: &str) -> BTreeMap<String, String> {
+        let mut prefs = BTreeMap::new();
+        for line in source.lines() {
+            let Some(rest) = line.trim().strip_prefix("user_pref(") else {
+                continue;
+            };
+            let Some((name, rest)) = parse_string(rest.trim_start()) else {
+                continue;
+            };
+            let Some(rest) = rest.trim_start().strip_prefix(',') else {
+                continue;
+            };
+            if let Some((value, _)) = parse_string(rest.trim_start()) {
+                prefs.insert(name, value);
+            }
+        }
+        prefs
+    }
+
+    /// Reads a JavaScript string literal at the start of `source`, returning it and the rest.
+    fn parse_string(source: &str) -> Option<(String, &str)> {
+        let rest = source.strip_prefix('"')?;
+        let mut value = String::new();
+        let mut chars = rest.char_indices();
+        while let Some((index, c)) = chars.next() {
+            match c {
+                '"' => return Some((value, &rest[index + 1..])),
+                '\\' => match chars.next()?.1 {
+                    'n' => value.push('\n'),
+                    't' => value.push('\t'),
+                    'u' => {
+                        let hex = (0..4)
+                            .map(|_| chars.next().map(|(_, c)| c))
+                            .collect::<Option<String>>()?;
+                        value.push(char::from_u32(u32::from_str_radix(&hex, 16).ok()?)?);
+                    }
+                    c => value.push(c),
+                },
+                c => value.push(c),
+            }
+        }
+        None
+    }
+
+    use std::collections::BTreeMap;
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_find_profile() {
+        let root = Scratch::new("profiles");
+        let ini = "\
+[Profile1]
+Name=work
+IsRelative=1
+Path=abcd.work
+
+[Profile0]
+Name=default
+IsRelative=1
+Path=wxyz.default
+Default=1
+
+[General]
+StartWithLastProfile=1
+";
+        fs::write(root.join("profiles.ini"), ini).unwrap();
+
+        let profile = |name| find_profile(&root, name).map(|profile| profile.name);
+        assert_eq!(profile(None).unwrap(), "default");
+        assert_eq!(profile(Some("work")).unwrap(), "work");
+        assert_eq!(profile(Some("abcd.work")).unwrap(), "work");
+        assert!(matches!(
+            profile(Some("home")),
+            Err(Error::UnknownProfile(_))
+        ));
+
+        let install = "[Install4F96D1932A9F858E]\nDefault=abcd.work\nLocked=1\n";
+        fs::write(root.join("profiles.ini"), format!("{ini}\n{install}")).unwrap();
+        assert_eq!(
+            find_profile(&root, None).unwrap(),
+            Profile {
+                name: "work".to_owned(),
+                path: root.join("abcd.work"),
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_prefs() {
+        let prefs = helpers::parse_prefs(
+            r#"// Mozilla User Preferences
+user_pref("mail.server.server1.hostname", "mail.example.com");
+user_pref("mail.server.server1.check_time", 10);
+user_pref("mail.server.server1.name", "Jane \"JD\" Doe é");
+"#,
+        );
+        assert_eq!(
+            prefs.into_iter().collect::<Vec<_>>(),
+            [
+                (
+                    "mail.server.server1.hostname".to_owned(),
+                    "mail.example.com".to_owned()
+                ),
+                (
+                    "mail.server.server1.name".to_owned(),
+                    "Jane \"JD\" Doe é".to_owned()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_install() {
+        let profile = Scratch::new("thunderbird");
+        fs::write(
+            profile.join("prefs.js"),
+            r#"user_pref("mail.server.server1.directory-rel", "[ProfD]ImapMail/mail.example.com");
+user_pref("mail.server.server1.hostname", "mail.example.com");
+user_pref("mail.server.server1.type", "imap");
+user_pref("mail.server.server1.userName", "jdoe@example.com");
+user_pref("mail.server.server2.hostname", "Local Folders");
+user_pref("mail.server.server2.type", "none");
+user_pref("mail.server.server2.userName", "nobody");
+"#,
+        )
+        .unwrap();
+        let dat = profile.join("ImapMail/mail.example.com/msgFilterRules.dat");
+        fs::create_dir_all(dat.parent().unwrap()).unwrap();
+        fs::write(
+            &dat,
+            "version=\"9\"\nlogging=\"no\"\nname=\"Mine\"\ncondition=\"ALL\"\n",
+        )
+        .unwrap();
+
+        let config = crate::configuration::parse(
+            "\
+- thunderbird_id: 'jdoe%40example.com@mail.example.com'
+  message_filters:
+    - title: Github
+      when:
+        - field: from
+          ends_with: ['@github.com']
+      then:
+        - move_to: Github
+- evolution_id: elsewhere
+  message_filters: []
+",
+        )
+        .unwrap();
+        let installed = install(config, &profile, 0).unwrap();
+
+        assert_eq!(installed.len(), 1);
+        assert_eq!(installed[0].path, dat);
+        assert_eq!(
+            installed[0].backup,
+            Some(profile.join("ImapMail/mail.example.com/msgFilterRules.dat.19700101-000000.bak"))
+        );
+        let written = fs::read_to_string(&dat).unwrap();
+        assert!(written.starts_with("version=\"9\"\nlogging=\"no\"\nname=\"Mine\"\n"));
+        assert!(written.contains("name=\"Github [mail-message-filters]\"\n"));
+        assert!(
+            written.contains("actionValue=\"imap://jdoe%40example.com@mail.example.com/Github\"")
+        );
+
+        let config = crate::configuration::parse(
+            "- thunderbird_id: other@mail.example.com\n  message_filters: []\n",
+        )
+        .unwrap();
+        let err = install(config, &profile, 0).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "account `other@mail.example.com`: no IMAP server in the profile has this thunderbird_id"
+        );
+
+        let broken = profile.join("Broken/msgFilterRules.dat");
+        fs::create_dir_all(broken.parent().unwrap()).unwrap();
+        fs::write(&broken, "name=bare\n").unwrap();
+        let config = crate::configuration::parse(
+            "\
+- thunderbird_id: 'jdoe%40example.com@mail.example.com'
+  message_filters: []
+- thunderbird_id: other
+  thunderbird_path: Broken/msgFilterRules.dat
+  message_filters: []
+",
+        )
+        .unwrap();
+        let err = install(config, &profile, 60).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!("{}: line 1: expected `name=\"value\"`", broken.display())
+        );
+        assert_eq!(fs::read_to_string(&dat).unwrap(), written);
+        assert!(!profile
+            .join("ImapMail/mail.example.com/msgFilterRules.dat.19700101-000100.bak")
+            .exists());
+    }
+
+    use super::*;
+    use crate::install::tests::Scratch;
+}
+
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    configuration::{Account, Configuration},
+    install::{self, Installed},
+    printer, resolver,
+};
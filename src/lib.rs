@@ -0,0 +1,67 @@
+//! Mail filters for Evolution, Thunderbird and Sieve, written once as YAML.
+//!
+//! A [`Configuration`] is read with [`configuration::parse`] and turned into a client's filter
+//! file by one of the [`printer`]s:
+//!
+//! ```
+//! let config = mail_message_filters::configuration::parse(
+//!     "\
+//! - thunderbird_id: 'jdoe%40example.com@mail.example.com'
+//!   message_filters:
+//!     - title: Github
+//!       when:
+//!         - field: from
+//!           ends_with: ['@github.com']
+//!       then:
+//!         - move_to: Github
+//! ",
+//! )?;
+//!
+//! let filters = mail_message_filters::printer::thunderbird::print_config(config)?;
+//! assert!(filters.contains("name=\"Github\""));
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+//!
+//! Each printer fails with its own error type, e.g. for an account without a client id:
+//!
+//! ```
+//! use mail_message_filters::{configuration, printer::thunderbird};
+//!
+//! let config = configuration::parse("- evolution_id: abc\n  message_filters: []\n")?;
+//! assert!(matches!(
+//!     thunderbird::print_config(config),
+//!     Err(thunderbird::Error::MissingThunderbirdId)
+//! ));
+//! # Ok::<(), configuration::Error>(())
+//! ```
+//!
+//! The `mail-message-filters` binary is a thin wrapper around [`run`].
+
+#![allow(clippy::items_after_test_module)]
+
+mod apply;
+mod cli;
+mod condition;
+/// The YAML configuration and its parser.
+pub mod configuration;
+mod dat;
+mod diff;
+mod engine;
+mod importer;
+mod install;
+mod lint;
+mod mailbox;
+mod message;
+mod pattern;
+/// Writing a configuration in each client's filter format.
+pub mod printer;
+mod resolver;
+mod sieve;
+/// Source positions for diagnostics.
+pub mod source;
+mod xml;
+
+pub use cli::run;
+pub use configuration::Configuration;
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
@@ -0,0 +1,441 @@
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("found {0} error(s)")]
+    Failed(usize),
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+impl std::fmt::Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Severity::Warning => f.write_str("warning"),
+            Severity::Error => f.write_str("error"),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Diagnostic {
+    /// Stable identifier of the rule that produced this diagnostic, e.g. `duplicate-title`.
+    pub rule: &'static str,
+    pub severity: Severity,
+    pub message: String,
+    pub position: Option<Position>,
+}
+
+impl std::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(position) = self.position {
+            write!(f, "{position}: ")?;
+        }
+        write!(f, "{}[{}]: {}", self.severity, self.rule, self.message)
+    }
+}
+
+/// Runs every lint rule over `config`, locating diagnostics through `source`.
+pub fn lint_config(config: &Configuration, source: &SourceMap) -> Vec<Diagnostic> {
+    let mut lint = Lint {
+        source,
+        diagnostics: Vec::new(),
+    };
+
+    for (account_index, account) in config.accounts.iter().enumerate() {
+        let account_path = path![account_index];
+
+        if account.evolution_id.is_none()
+            && account.thunderbird_id.is_none()
+            && account.email.is_none()
+        {
+            lint.report(
+                "missing-client-id",
+                Severity::Error,
+                &account_path,
+                "account has no evolution_id, thunderbird_id or email".to_owned(),
+            );
+        } else if account.evolution_id.is_none()
+            && account.thunderbird_id.is_none()
+            && account.imap_host.is_none()
+        {
+            lint.report(
+                "missing-client-id",
+                Severity::Error,
+                &[&account_path[..], &path!["email"]].concat(),
+                "account has `email` but no `imap_host`, which Thunderbird needs for its id"
+                    .to_owned(),
+            );
+        }
+
+        let mut titles: HashSet<&str> = HashSet::new();
+
+        for (filter_index, filter) in account.message_filters.iter().enumerate() {
+            let filter_path = [&account_path[..], &path!["message_filters", filter_index]].concat();
+
+            if !titles.insert(&filter.title) {
+                lint.report(
+                    "duplicate-title",
+                    Severity::Error,
+                    &[&filter_path[..], &path!["title"]].concat(),
+                    format!("filter title '{}' is already used", filter.title),
+                );
+            }
+
+            lint_filter(&mut lint, filter, &filter_path);
+            lint_actions(&mut lint, account, filter, &filter_path);
+        }
+
+        lint_conflicts(&mut lint, &account.message_filters, &account_path);
+    }
+
+    lint.diagnostics
+}
+
+struct Lint<'a> {
+    source: &'a SourceMap,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Lint<'_> {
+    fn report(
+        &mut self,
+        rule: &'static str,
+        severity: Severity,
+        path: &[Segment],
+        message: String,
+    ) {
+        self.diagnostics.push(Diagnostic {
+            rule,
+            severity,
+            message,
+            position: self.source.position(path),
+        });
+    }
+}
+
+fn lint_filter(lint: &mut Lint, filter: &MessageFilter, filter_path: &[Segment]) {
+    if filter.when.is_empty() {
+        lint.report(
+            "empty-when",
+            Severity::Error,
+            &[filter_path, &path!["when"]].concat(),
+            format!("filter '{}' has no conditions", filter.title),
+        );
+    }
+
+    if filter.run_on.is_empty() {
+        lint.report(
+            "empty-run-on",
+            Severity::Error,
+            &[filter_path, &path!["run_on"]].concat(),
+            format!("filter '{}' never runs", filter.title),
+        );
+    }
+
+    if filter.then.is_empty() {
+        lint.report(
+            "empty-then",
+            Severity::Error,
+            &[filter_path, &path!["then"]].concat(),
+            format!("filter '{}' has no actions", filter.title),
+        );
+    }
+
+    for (index, condition) in filter.when.iter().enumerate() {
+        let condition_path = [filter_path, &path!["when", index]].concat();
+        lint_condition(lint, filter, condition, &condition_path);
+    }
+}
+
+fn lint_condition(lint: &mut Lint, filter: &MessageFilter, condition: &When, path: &[Segment]) {
+    let (group, conditions) = match condition {
+        When::All(all) => ("all", &all.conditions),
+        When::Any(any) => ("any", &any.conditions),
+        When::Not(not) => {
+            let not_path = [path, &path!["not"]].concat();
+            return lint_condition(lint, filter, &not.condition, &not_path);
+        }
+        _ => return lint_values(lint, filter, condition, path),
+    };
+
+    let group_path = [path, &path![group]].concat();
+    if conditions.is_empty() {
+        lint.report(
+            "empty-group",
+            Severity::Error,
+            &group_path,
+            format!("`{group}` in filter '{}' has no conditions", filter.title),
+        );
+    }
+
+    for (index, condition) in conditions.iter().enumerate() {
+        let condition_path = [&group_path[..], &path![index]].concat();
+        lint_condition(lint, filter, condition, &condition_path);
+    }
+}
+
+fn lint_values(lint: &mut Lint, filter: &MessageFilter, condition: &When, path: &[Segment]) {
+    let Some(test) = condition.string_test() else {
+        return;
+    };
+    let (matcher, values) = (test.matcher.key(), test.values);
+    let condition_path = [path, &path![matcher]].concat();
+
+    if values.is_empty() {
+        lint.report(
+            "empty-values",
+            Severity::Error,
+            &condition_path,
+            format!("`{matcher}` in filter '{}' has no values", filter.title),
+        );
+    }
+
+    for (value_index, value) in values.iter().enumerate() {
+        if value.trim().is_empty() {
+            lint.report(
+                "blank-value",
+                Severity::Error,
+                &[&condition_path[..], &path![value_index]].concat(),
+                format!(
+                    "`{matcher}` in filter '{}' has a blank value, which matches everything",
+                    filter.title
+                ),
+            );
+        }
+    }
+}
+
+fn lint_actions(
+    lint: &mut Lint,
+    account: &Account,
+    filter: &MessageFilter,
+    filter_path: &[Segment],
+) {
+    for (index, action) in filter.then.iter().enumerate() {
+        let action_path = [filter_path, &path!["then", index, action.key()]].concat();
+
+        match action {
+            Then::Forward(forward) if !is_address(&forward.to) => lint.report(
+                "invalid-address",
+                Severity::Error,
+                &action_path,
+                format!(
+                    "`forward` in filter '{}' has an invalid address '{}'",
+                    filter.title, forward.to
+                ),
+            ),
+            Then::ReplyWith(reply_with) if !account.templates.contains_key(&reply_with.template) => {
+                lint.report(
+                    "unknown-template",
+                    Severity::Error,
+                    &action_path,
+                    format!(
+                        "`reply_with` in filter '{}' names template '{}', which is not in the account's `templates`",
+                        filter.title, reply_with.template
+                    ),
+                )
+            }
+            Then::RunProgram(run_program) if run_program.command.trim().is_empty() => lint.report(
+                "blank-command",
+                Severity::Error,
+                &action_path,
+                format!("`run_program` in filter '{}' has a blank command", filter.title),
+            ),
+            Then::MoveTo(MoveTo { folder }) | Then::CopyTo(CopyTo { folder })
+                if folder.trim().is_empty() =>
+            {
+                lint.report(
+                    "blank-folder",
+                    Severity::Error,
+                    &action_path,
+                    format!("`{}` in filter '{}' has a blank folder", action.key(), filter.title),
+                )
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Returns `true` for a bare `local@domain` address without whitespace or display name.
+fn is_address(address: &str) -> bool {
+    let Some((local, domain)) = address.split_once('@') else {
+        return false;
+    };
+    !local.is_empty()
+        && !domain.is_empty()
+        && !domain.contains('@')
+        && !domain.starts_with('.')
+        && !domain.ends_with('.')
+        && !address.contains(|c: char| c.is_whitespace() || matches!(c, '<' | '>' | ',' | '"'))
+}
+
+/// Reports filters that test the exact same field and value but act differently, since only
+/// one of them can win in most clients. Only top-level conditions are compared.
+fn lint_conflicts(lint: &mut Lint, filters: &[MessageFilter], account_path: &[Segment]) {
+    let mut seen: HashMap<(&'static str, &Field, &str), usize> = HashMap::new();
+
+    for (filter_index, filter) in filters.iter().enumerate() {
+        for test in filter.when.iter().filter_map(When::string_test) {
+            let (matcher, field, values) = (test.matcher.key(), test.field, test.values);
+
+            for value in values {
+                let key = (matcher, field, value.as_str());
+                match seen.get(&key) {
+                    Some(&other) if other != filter_index && filters[other].then != filter.then => {
+                        lint.report(
+                            "conflicting-filters",
+                            Severity::Warning,
+                            &[account_path, &path!["message_filters", filter_index]].concat(),
+                            format!(
+                                "filter '{}' and filter '{}' both match `{matcher}` '{value}' \
+                                 but take different actions",
+                                filters[other].title, filter.title
+                            ),
+                        );
+                    }
+                    Some(_) => {}
+                    None => {
+                        seen.insert(key, filter_index);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    const SOURCE: &str = "\
+- evolution_id: evolution
+  message_filters:
+    - title: Github
+      when:
+        - field: from
+          ends_with: ['@github.com', ' ']
+      then:
+        - move_to: Github
+    - title: Github
+      when:
+        - field: from
+          ends_with: ['@github.com']
+      then:
+        - move_to: Elsewhere
+    - title: Empty
+      run_on: []
+      when: []
+      then: []
+- message_filters: []
+- email: jdoe@example.com
+  message_filters: []
+";
+
+    #[test]
+    fn test_lint_config() {
+        let config = crate::configuration::parse(SOURCE).unwrap();
+        let source = SourceMap::parse(SOURCE);
+
+        let diagnostics: Vec<String> = lint_config(&config, &source)
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+
+        assert_eq!(
+            diagnostics,
+            [
+                "6:38: error[blank-value]: `ends_with` in filter 'Github' has a blank value, which matches everything",
+                "9:7: error[duplicate-title]: filter title 'Github' is already used",
+                "17:7: error[empty-when]: filter 'Empty' has no conditions",
+                "16:7: error[empty-run-on]: filter 'Empty' never runs",
+                "18:7: error[empty-then]: filter 'Empty' has no actions",
+                "9:7: warning[conflicting-filters]: filter 'Github' and filter 'Github' both match `ends_with` '@github.com' but take different actions",
+                "19:3: error[missing-client-id]: account has no evolution_id, thunderbird_id or email",
+                "20:3: error[missing-client-id]: account has `email` but no `imap_host`, which Thunderbird needs for its id",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lint_nested_conditions() {
+        let source = "\
+- thunderbird_id: thunderbird
+  message_filters:
+    - title: Nested
+      when:
+        - not:
+            all:
+              - field: subject
+                contains: ['']
+              - any: []
+      then:
+        - move_to: Nested
+";
+        let config = crate::configuration::parse(source).unwrap();
+
+        let diagnostics: Vec<String> = lint_config(&config, &SourceMap::parse(source))
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+
+        assert_eq!(
+            diagnostics,
+            [
+                "8:28: error[blank-value]: `contains` in filter 'Nested' has a blank value, which matches everything",
+                "9:17: error[empty-group]: `any` in filter 'Nested' has no conditions",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lint_actions() {
+        let source = "\
+- thunderbird_id: thunderbird
+  templates:
+    thanks: mailbox://nobody@Local%20Folders/Templates?messageId=1
+  message_filters:
+    - title: Actions
+      when:
+        - field: subject
+          contains: [invoice]
+      then:
+        - forward: Jane <jane@example.com>
+        - forward: jane@example.com
+        - reply_with: thanks
+        - reply_with: thank
+        - run_program: ' '
+        - move_to: ''
+        - copy_to: ' '
+";
+        let config = crate::configuration::parse(source).unwrap();
+
+        let diagnostics: Vec<String> = lint_config(&config, &SourceMap::parse(source))
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+
+        assert_eq!(
+            diagnostics,
+            [
+                "10:11: error[invalid-address]: `forward` in filter 'Actions' has an invalid address 'Jane <jane@example.com>'",
+                "13:11: error[unknown-template]: `reply_with` in filter 'Actions' names template 'thank', which is not in the account's `templates`",
+                "14:11: error[blank-command]: `run_program` in filter 'Actions' has a blank command",
+                "15:11: error[blank-folder]: `move_to` in filter 'Actions' has a blank folder",
+                "16:11: error[blank-folder]: `copy_to` in filter 'Actions' has a blank folder",
+            ]
+        );
+        assert!(!is_address("jane@"));
+        assert!(is_address("jane+filters@mail.example.com"));
+    }
+
+    use super::*;
+}
+
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    configuration::{Account, Configuration, CopyTo, Field, MessageFilter, MoveTo, Then, When},
+    path,
+    source::{Position, Segment, SourceMap},
+};
@@ -0,0 +1,189 @@
+/// A message read from a file, mbox or Maildir.
+#[derive(Debug)]
+pub struct Entry {
+    /// Where the message was found, e.g. `archive.mbox:120` for the line its `From ` line is on.
+    pub location: String,
+    pub message: Message,
+}
+
+/// Reads a single message from an `.eml` file.
+pub fn read_eml(path: &Path) -> io::Result<Entry> {
+    Ok(Entry {
+        location: path.display().to_string(),
+        message: Message::parse(&fs::read(path)?),
+    })
+}
+
+/// Reads every message in an mbox file.
+pub fn read_mbox(path: &Path) -> io::Result<Vec<Entry>> {
+    let raw = fs::read(path)?;
+    Ok(helpers::split_mbox(&raw)
+        .into_iter()
+        .map(|(line, raw)| Entry {
+            location: format!("{}:{line}", path.display()),
+            message: Message::parse(&raw),
+        })
+        .collect())
+}
+
+/// Reads every message in the `new` and `cur` directories of a Maildir, taking the flags from
+/// the file names.
+pub fn read_maildir(dir: &Path) -> io::Result<Vec<Entry>> {
+    let mut entries = Vec::new();
+
+    for path in maildir_paths(dir)? {
+        entries.push(read_maildir_message(&path)?);
+    }
+
+    Ok(entries)
+}
+
+/// Reads a single message file of a Maildir, taking the flags from its name.
+pub fn read_maildir_message(path: &Path) -> io::Result<Entry> {
+    let mut entry = read_eml(path)?;
+    if let Some(flags) = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .and_then(helpers::maildir_flags)
+    {
+        entry.message.flags = flags;
+    }
+    Ok(entry)
+}
+
+/// Lists the message files in the `new` and `cur` directories of a Maildir, sorted by name
+/// within each directory.
+pub fn maildir_paths(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut all = Vec::new();
+
+    for subdir in ["new", "cur"] {
+        let mut paths = fs::read_dir(dir.join(subdir))?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<io::Result<Vec<_>>>()?;
+        paths.sort();
+        all.extend(paths.into_iter().filter(|path| path.is_file()));
+    }
+
+    Ok(all)
+}
+
+mod helpers {
+    /// Splits an mbox into messages, returning each with the line number of its `From ` line.
+    /// Lines quoted as `>From ` are unquoted, which is right for mboxrd and mboxo alike in
+    /// practice.
+    pub fn split_mbox(raw: &[u8]) -> Vec<(usize, Vec<u8>)> {
+        let mut messages: Vec<(usize, Vec<u8>)> = Vec::new();
+        let mut previous_blank = true;
+
+        for (index, line) in raw.split_inclusive(|&byte| byte == b'\n').enumerate() {
+            if previous_blank && is_from_line(line) {
+                messages.push((index + 1, Vec::new()));
+                previous_blank = false;
+                continue;
+            }
+            previous_blank = matches!(line, b"\n" | b"\r\n");
+
+            let Some((_, message)) = messages.last_mut() else {
+                continue;
+            };
+            let quotes = line.iter().take_while(|&&byte| byte == b'>').count();
+            match quotes > 0 && line[quotes..].starts_with(b"From ") {
+                true => message.extend_from_slice(&line[1..]),
+                false => message.extend_from_slice(line),
+            }
+        }
+
+        // The blank line before the next `From ` line belongs to the mbox, not the message.
+        for (_, message) in &mut messages {
+            if message.ends_with(b"\r\n\r\n") {
+                message.truncate(message.len() - 2);
+            } else if message.ends_with(b"\n\n") {
+                message.truncate(message.len() - 1);
+            }
+        }
+        messages
+    }
+
+    /// Recognizes the `From sender asctime-date` separator line, so unquoted body lines that
+    /// merely start with `From ` are left alone.
+    fn is_from_line(line: &[u8]) -> bool {
+        let Some(rest) = line.strip_prefix(b"From ") else {
+            return false;
+        };
+        let rest = String::from_utf8_lossy(rest);
+        let has_time = rest
+            .split_whitespace()
+            .any(|token| token.matches(':').count() == 2);
+        let has_year = rest
+            .split_whitespace()
+            .any(|token| token.len() == 4 && token.bytes().all(|byte| byte.is_ascii_digit()));
+        has_time && has_year
+    }
+
+    /// Reads the flags of a Maildir file name, e.g. `1700000000.M1P2.host:2,FS`.
+    pub fn maildir_flags(name: &str) -> Option<Flags> {
+        let (_, info) = name.rsplit_once(":2,")?;
+        Some(Flags {
+            seen: info.contains('S'),
+            flagged: info.contains('F'),
+            replied: info.contains('R'),
+        })
+    }
+
+    use crate::message::Flags;
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_split_mbox() {
+        let mbox = b"\
+From alice@example.com Thu Jan  1 00:00:00 2026
+Subject: one
+
+>From the start
+>>From quoted
+
+From bob@example.com Thu Jan  1 00:00:00 2026
+Subject: two
+
+From here on
+";
+        let messages = helpers::split_mbox(mbox);
+
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].0, 1);
+        assert_eq!(
+            String::from_utf8_lossy(&messages[0].1),
+            "Subject: one\n\nFrom the start\n>From quoted\n"
+        );
+        assert_eq!(messages[1].0, 7);
+        assert_eq!(
+            String::from_utf8_lossy(&messages[1].1),
+            "Subject: two\n\nFrom here on\n"
+        );
+    }
+
+    #[test]
+    fn test_maildir_flags() {
+        assert_eq!(
+            helpers::maildir_flags("1700000000.M1P2.host:2,FS"),
+            Some(Flags {
+                seen: true,
+                flagged: true,
+                replied: false,
+            })
+        );
+        assert_eq!(helpers::maildir_flags("1700000000.M1P2.host"), None);
+    }
+
+    use super::*;
+    use crate::message::Flags;
+}
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::message::Message;
@@ -0,0 +1,440 @@
+mod address;
+mod encoding;
+
+pub use address::Address;
+
+/// A parsed RFC 5322 message, with the parts the filter conditions look at.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Message {
+    /// Unfolded headers in order, values still encoded.
+    headers: Vec<(String, String)>,
+    /// Decoded text of the `text/plain` parts, or of the `text/html` parts if there are none.
+    body: String,
+    has_attachment: bool,
+    size: u64,
+    pub flags: Flags,
+}
+
+/// IMAP-style flags, taken from Maildir file names or mbox `Status` headers.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Flags {
+    pub seen: bool,
+    pub flagged: bool,
+    pub replied: bool,
+}
+
+/// A point in time from a `Date` header.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Timestamp {
+    /// Seconds since the epoch.
+    pub seconds: i64,
+    /// The calendar date in the sender's time zone.
+    pub date: Date,
+}
+
+impl Message {
+    /// Parses a raw message. Parsing is lenient: malformed parts are read as well as possible.
+    pub fn parse(raw: &[u8]) -> Message {
+        let (headers, body) = helpers::split_headers(raw);
+        let mut message = Message {
+            size: raw.len() as u64,
+            flags: helpers::status_flags(&headers),
+            ..Message::default()
+        };
+
+        let mut html = String::new();
+        helpers::walk_part(&headers, body, &mut message, &mut html);
+        if message.body.is_empty() {
+            message.body = html;
+        }
+        message.headers = headers;
+        message
+    }
+
+    /// Returns the decoded values of every header called `name`, compared case-insensitively.
+    pub fn header(&self, name: &str) -> impl Iterator<Item = String> + '_ {
+        let name = name.to_owned();
+        self.headers
+            .iter()
+            .filter(move |(header, _)| header.eq_ignore_ascii_case(&name))
+            .map(|(_, value)| encoding::decode_encoded_words(value.trim()))
+    }
+
+    /// Returns the addresses in every header called `name`.
+    pub fn addresses(&self, name: &str) -> Vec<Address> {
+        self.headers
+            .iter()
+            .filter(|(header, _)| header.eq_ignore_ascii_case(name))
+            .flat_map(|(_, value)| address::parse_addresses(value))
+            .collect()
+    }
+
+    pub fn subject(&self) -> String {
+        self.header("Subject").next().unwrap_or_default()
+    }
+
+    pub fn body(&self) -> &str {
+        &self.body
+    }
+
+    /// Returns the size of the raw message in bytes.
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    pub fn has_attachment(&self) -> bool {
+        self.has_attachment
+    }
+
+    /// Returns when the message was sent, according to its `Date` header.
+    pub fn date(&self) -> Option<Timestamp> {
+        self.header("Date")
+            .next()
+            .and_then(|date| helpers::parse_date(&date))
+    }
+
+    /// Returns the priority from `X-Priority` or `Importance`, `Normal` if there is neither.
+    pub fn priority(&self) -> Priority {
+        if let Some(priority) = self.header("X-Priority").next() {
+            return match priority.trim().chars().next() {
+                Some('1') => Priority::Highest,
+                Some('2') => Priority::High,
+                Some('4') => Priority::Low,
+                Some('5') => Priority::Lowest,
+                _ => Priority::Normal,
+            };
+        }
+        match self.header("Importance").next() {
+            Some(importance) if importance.trim().eq_ignore_ascii_case("high") => Priority::High,
+            Some(importance) if importance.trim().eq_ignore_ascii_case("low") => Priority::Low,
+            _ => Priority::Normal,
+        }
+    }
+
+    /// Returns the keywords, i.e. tags and labels, the clients store in the message.
+    pub fn keywords(&self) -> Vec<String> {
+        ["X-Mozilla-Keys", "X-Keywords", "Keywords", "X-Label"]
+            .iter()
+            .flat_map(|name| self.header(name).collect::<Vec<_>>())
+            .flat_map(|value| {
+                value
+                    .split(|c: char| c == ',' || c.is_whitespace())
+                    .filter(|keyword| !keyword.is_empty())
+                    .map(str::to_owned)
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    /// Returns `true` if a spam filter or the client marked the message as junk.
+    pub fn is_junk(&self) -> bool {
+        let spam_flag = self
+            .header("X-Spam-Flag")
+            .any(|value| value.trim().eq_ignore_ascii_case("yes"));
+        let keyword = self.keywords().iter().any(|keyword| {
+            keyword.eq_ignore_ascii_case("$Junk") || keyword.eq_ignore_ascii_case("Junk")
+        });
+        spam_flag || keyword
+    }
+}
+
+mod helpers {
+    /// Splits a message or MIME part into unfolded headers and the body.
+    pub fn split_headers(raw: &[u8]) -> (Vec<(String, String)>, &[u8]) {
+        let mut headers: Vec<(String, String)> = Vec::new();
+        let mut offset = 0;
+
+        while offset < raw.len() {
+            let end = raw[offset..]
+                .iter()
+                .position(|&byte| byte == b'\n')
+                .map_or(raw.len(), |position| offset + position + 1);
+            let line = String::from_utf8_lossy(&raw[offset..end]);
+            let line = line.trim_end_matches(['\r', '\n']);
+            offset = end;
+
+            if line.is_empty() {
+                break;
+            }
+            match (line.starts_with([' ', '\t']), headers.last_mut()) {
+                (true, Some((_, value))) => value.push_str(line),
+                _ => {
+                    if let Some((name, value)) = line.split_once(':') {
+                        headers.push((name.trim().to_owned(), value.to_owned()));
+                    }
+                }
+            }
+        }
+
+        (headers, &raw[offset.min(raw.len())..])
+    }
+
+    /// Collects the text and attachments of a MIME part and its children.
+    pub fn walk_part(
+        headers: &[(String, String)],
+        body: &[u8],
+        message: &mut Message,
+        html: &mut String,
+    ) {
+        let find = |name: &str| {
+            headers
+                .iter()
+                .find(|(header, _)| header.eq_ignore_ascii_case(name))
+                .map(|(_, value)| value.as_str())
+        };
+        let (content_type, params) = parse_content_type(find("Content-Type").unwrap_or(""));
+        let (disposition, disposition_params) =
+            parse_content_type(find("Content-Disposition").unwrap_or(""));
+
+        if content_type.starts_with("multipart/") {
+            if let Some(boundary) = param(&params, "boundary") {
+                for part in split_multipart(body, boundary) {
+                    let (headers, body) = split_headers(part);
+                    walk_part(&headers, body, message, html);
+                }
+            }
+            return;
+        }
+
+        let named =
+            param(&disposition_params, "filename").is_some() || param(&params, "name").is_some();
+        if disposition == "attachment" || (named && disposition != "inline") {
+            message.has_attachment = true;
+            return;
+        }
+
+        let target = match content_type.as_str() {
+            "" | "text/plain" => &mut message.body,
+            "text/html" => html,
+            _ => return,
+        };
+        let encoding = find("Content-Transfer-Encoding")
+            .unwrap_or("")
+            .trim()
+            .to_ascii_lowercase();
+        let bytes = match encoding.as_str() {
+            "base64" => encoding::decode_base64(body),
+            "quoted-printable" => encoding::decode_quoted_printable(body, false),
+            _ => body.to_vec(),
+        };
+        let charset = param(&params, "charset").unwrap_or("utf-8");
+        target.push_str(&encoding::decode_charset(&bytes, charset));
+    }
+
+    /// Splits a `Content-Type` or `Content-Disposition` value into its lowercased value and
+    /// parameters.
+    fn parse_content_type(value: &str) -> (String, Vec<(String, String)>) {
+        let mut parts = value.split(';');
+        let content_type = parts.next().unwrap_or("").trim().to_ascii_lowercase();
+        let params = parts
+            .filter_map(|part| part.split_once('='))
+            .map(|(name, value)| {
+                (
+                    name.trim().to_ascii_lowercase(),
+                    value.trim().trim_matches('"').to_owned(),
+                )
+            })
+            .collect();
+        (content_type, params)
+    }
+
+    fn param<'a>(params: &'a [(String, String)], name: &str) -> Option<&'a str> {
+        params
+            .iter()
+            // RFC 2231 continuations and charsets, e.g. `filename*0*=`.
+            .find(|(param, _)| param == name || param.starts_with(&format!("{name}*")))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Returns the parts between the `--boundary` delimiter lines.
+    fn split_multipart<'a>(body: &'a [u8], boundary: &str) -> Vec<&'a [u8]> {
+        let delimiter = format!("--{boundary}");
+        let mut parts = Vec::new();
+        let mut start = None;
+        let mut offset = 0;
+
+        while offset < body.len() {
+            let end = body[offset..]
+                .iter()
+                .position(|&byte| byte == b'\n')
+                .map_or(body.len(), |position| offset + position + 1);
+            let line = &body[offset..end];
+
+            if line.starts_with(delimiter.as_bytes()) {
+                if let Some(start) = start {
+                    parts.push(&body[start..offset]);
+                }
+                let rest = &line[delimiter.len()..];
+                if rest.starts_with(b"--") {
+                    return parts;
+                }
+                start = Some(end);
+            }
+            offset = end;
+        }
+
+        if let Some(start) = start {
+            parts.push(&body[start..]);
+        }
+        parts
+    }
+
+    /// Reads the mbox `Status` and `X-Status` headers written by mail clients.
+    pub fn status_flags(headers: &[(String, String)]) -> Flags {
+        let find = |name: &str| {
+            headers
+                .iter()
+                .find(|(header, _)| header.eq_ignore_ascii_case(name))
+                .map_or("", |(_, value)| value.trim())
+        };
+        Flags {
+            seen: find("Status").contains('R'),
+            flagged: find("X-Status").contains('F'),
+            replied: find("X-Status").contains('A'),
+        }
+    }
+
+    /// Parses an RFC 5322 date, e.g. `Tue, 1 Jul 2003 10:52:37 +0200`, including the
+    /// obsolete zone names.
+    pub fn parse_date(value: &str) -> Option<Timestamp> {
+        let value = match value.split_once(',') {
+            Some((_, rest)) => rest,
+            None => value,
+        };
+        let mut tokens = value.split_whitespace();
+
+        let day = tokens.next()?.parse().ok()?;
+        let month = tokens.next()?.get(..3)?.to_ascii_lowercase();
+        let month = MONTHS.iter().position(|name| *name == month)? as u32 + 1;
+        let year: i32 = tokens.next()?.parse().ok()?;
+        let year = match year {
+            0..=49 => year + 2000,
+            50..=999 => year + 1900,
+            year => year,
+        };
+
+        let mut time = tokens.next()?.split(':');
+        let mut next = || time.next().and_then(|part| part.parse::<i64>().ok());
+        let (hour, minute, second) = (next()?, next()?, next().unwrap_or(0));
+
+        let offset = match tokens.next().unwrap_or("+0000") {
+            zone if zone.starts_with(['+', '-']) && zone.len() == 5 => {
+                let minutes =
+                    zone[1..3].parse::<i64>().ok()? * 60 + zone[3..].parse::<i64>().ok()?;
+                if zone.starts_with('-') {
+                    -minutes
+                } else {
+                    minutes
+                }
+            }
+            zone => match zone.to_ascii_uppercase().as_str() {
+                "EDT" => -4 * 60,
+                "EST" | "CDT" => -5 * 60,
+                "CST" | "MDT" => -6 * 60,
+                "MST" | "PDT" => -7 * 60,
+                "PST" => -8 * 60,
+                _ => 0,
+            },
+        };
+
+        let date = Date { year, month, day };
+        if date.to_string().parse::<Date>().is_err() {
+            return None;
+        }
+        let seconds =
+            date.days_since_epoch() * 86_400 + hour * 3600 + minute * 60 + second - offset * 60;
+        Some(Timestamp { seconds, date })
+    }
+
+    const MONTHS: [&str; 12] = [
+        "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+    ];
+
+    use super::{encoding, Flags, Message, Timestamp};
+    use crate::configuration::Date;
+}
+
+#[cfg(test)]
+mod tests {
+    const MULTIPART: &str = "\
+From: =?UTF-8?Q?Ren=C3=A9?= <rene@example.com>
+To: a@example.com,
+ \"Doe, Jane\" <jane@example.com>
+Subject: =?UTF-8?B?UmU6IGNhZsOp?=
+Date: Tue, 1 Jul 2003 10:52:37 +0200
+Status: RO
+X-Priority: 2 (High)
+X-Mozilla-Keys: $label1 $Junk
+Content-Type: multipart/mixed; boundary=\"outer\"
+
+preamble
+--outer
+Content-Type: text/plain; charset=iso-8859-1
+Content-Transfer-Encoding: quoted-printable
+
+Caf=E9 au lait
+--outer
+Content-Type: application/pdf; name=\"invoice.pdf\"
+Content-Transfer-Encoding: base64
+
+JVBERi0=
+--outer--
+";
+
+    #[test]
+    fn test_parse_headers() {
+        let message = Message::parse(MULTIPART.as_bytes());
+
+        assert_eq!(message.subject(), "Re: café");
+        assert_eq!(
+            message.addresses("from"),
+            [Address {
+                name: Some("René".to_owned()),
+                email: "rene@example.com".to_owned(),
+            }]
+        );
+        assert_eq!(message.addresses("To").len(), 2);
+        assert_eq!(message.priority(), Priority::High);
+        assert_eq!(message.keywords(), ["$label1", "$Junk"]);
+        assert!(message.is_junk());
+        assert_eq!(
+            message.flags,
+            Flags {
+                seen: true,
+                flagged: false,
+                replied: false,
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_body_and_attachments() {
+        let message = Message::parse(MULTIPART.as_bytes());
+        assert_eq!(message.body(), "Café au lait\n");
+        assert!(message.has_attachment());
+        assert_eq!(message.size(), MULTIPART.len() as u64);
+
+        let message = Message::parse(b"Subject: plain\r\n\r\nhello\r\n");
+        assert_eq!(message.subject(), "plain");
+        assert_eq!(message.body(), "hello\r\n");
+        assert!(!message.has_attachment());
+    }
+
+    #[test]
+    fn test_parse_date() {
+        let message = Message::parse(MULTIPART.as_bytes());
+        let date = message.date().unwrap();
+        assert_eq!(date.seconds, 1_057_049_557);
+        assert_eq!(date.date.to_string(), "2003-07-01");
+
+        assert_eq!(
+            helpers::parse_date("2 Jan 26 00:30 PST").map(|date| date.seconds),
+            Some(1_767_342_600)
+        );
+        assert_eq!(helpers::parse_date("31 Feb 2026 00:00 +0000"), None);
+    }
+
+    use super::*;
+}
+
+use crate::configuration::{Date, Priority};
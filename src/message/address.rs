@@ -0,0 +1,178 @@
+/// A mailbox from an address header, e.g. `Jane Doe <jane@example.com>`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Address {
+    pub name: Option<String>,
+    pub email: String,
+}
+
+/// Parses an RFC 5322 address list, including groups, quoted display names, comments and
+/// encoded words. Entries without an address are skipped.
+pub fn parse_addresses(value: &str) -> Vec<Address> {
+    helpers::split_list(value)
+        .iter()
+        .filter_map(|entry| helpers::parse_mailbox(entry))
+        .collect()
+}
+
+mod helpers {
+    /// Splits an address list at top-level commas and group delimiters.
+    pub fn split_list(value: &str) -> Vec<String> {
+        let mut entries = Vec::new();
+        let mut current = String::new();
+        let mut quoted = false;
+        let mut escaped = false;
+        let mut comment = 0;
+        let mut angle = false;
+
+        for c in value.chars() {
+            if escaped {
+                escaped = false;
+                current.push(c);
+                continue;
+            }
+            match c {
+                '\\' if quoted || comment > 0 => escaped = true,
+                '"' if comment == 0 => quoted = !quoted,
+                '(' if !quoted => comment += 1,
+                ')' if !quoted && comment > 0 => comment -= 1,
+                '<' if !quoted && comment == 0 => angle = true,
+                '>' if !quoted && comment == 0 => angle = false,
+                // The display name of a group, `Team: a@example.com, b@example.com;`.
+                ':' if !quoted && comment == 0 && !angle => {
+                    current.clear();
+                    continue;
+                }
+                ',' | ';' if !quoted && comment == 0 && !angle => {
+                    entries.push(std::mem::take(&mut current));
+                    continue;
+                }
+                _ => {}
+            }
+            current.push(c);
+        }
+        entries.push(current);
+        entries
+    }
+
+    /// Parses `Name <email>`, `email (Name)` or a bare `email`.
+    pub fn parse_mailbox(entry: &str) -> Option<Address> {
+        let (phrase, comments) = strip_comments(entry);
+
+        let (name, email) = match (phrase.rfind('<'), phrase.rfind('>')) {
+            (Some(start), Some(end)) if start < end => {
+                (phrase[..start].trim(), phrase[start + 1..end].trim())
+            }
+            _ => ("", phrase.trim()),
+        };
+        if email.is_empty() {
+            return None;
+        }
+
+        let name = match name.is_empty() {
+            true => comments.trim(),
+            false => name,
+        };
+        let name = unquote(name);
+        Some(Address {
+            name: (!name.is_empty()).then(|| decode_encoded_words(&name)),
+            email: unquote(email),
+        })
+    }
+
+    /// Removes comments from `entry`, returning the rest and the comments' text.
+    fn strip_comments(entry: &str) -> (String, String) {
+        let mut rest = String::new();
+        let mut comments = String::new();
+        let mut quoted = false;
+        let mut escaped = false;
+        let mut depth = 0;
+
+        for c in entry.chars() {
+            let target = if depth > 0 { &mut comments } else { &mut rest };
+            if escaped {
+                escaped = false;
+                target.push(c);
+                continue;
+            }
+            match c {
+                '"' if depth == 0 => quoted = !quoted,
+                '(' if !quoted => {
+                    depth += 1;
+                    if depth == 1 {
+                        continue;
+                    }
+                }
+                ')' if !quoted && depth > 0 => {
+                    depth -= 1;
+                    if depth == 0 {
+                        continue;
+                    }
+                }
+                '\\' if quoted || depth > 0 => escaped = true,
+                _ => {}
+            }
+            target.push(c);
+        }
+        (rest, comments)
+    }
+
+    /// Removes the quotes and escapes of a quoted string, leaving other text as is.
+    fn unquote(value: &str) -> String {
+        let mut unquoted = String::with_capacity(value.len());
+        let mut escaped = false;
+        for c in value.trim().chars() {
+            match c {
+                _ if escaped => {
+                    escaped = false;
+                    unquoted.push(c);
+                }
+                '\\' => escaped = true,
+                '"' => {}
+                c => unquoted.push(c),
+            }
+        }
+        unquoted.trim().to_owned()
+    }
+
+    use super::Address;
+    use crate::message::encoding::decode_encoded_words;
+}
+
+#[cfg(test)]
+mod tests {
+    fn address(name: Option<&str>, email: &str) -> Address {
+        Address {
+            name: name.map(str::to_owned),
+            email: email.to_owned(),
+        }
+    }
+
+    #[test]
+    fn test_parse_addresses() {
+        assert_eq!(
+            parse_addresses(
+                r#""Doe, Jane" <jane@example.com>, bob@example.com (Bob), <ci@example.com>"#
+            ),
+            [
+                address(Some("Doe, Jane"), "jane@example.com"),
+                address(Some("Bob"), "bob@example.com"),
+                address(None, "ci@example.com"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_groups_and_encoded_names() {
+        assert_eq!(
+            parse_addresses("Team: a@example.com, =?UTF-8?Q?Ren=C3=A9?= <b@example.com>;, c@x.org"),
+            [
+                address(None, "a@example.com"),
+                address(Some("René"), "b@example.com"),
+                address(None, "c@x.org"),
+            ]
+        );
+        assert_eq!(parse_addresses("undisclosed-recipients:;"), []);
+    }
+
+    use super::*;
+}
@@ -0,0 +1,177 @@
+/// Decodes RFC 2047 encoded words, e.g. `=?UTF-8?Q?Caf=C3=A9?=`, in an unstructured header
+/// value. Whitespace between two adjacent encoded words is dropped, anything that fails to
+/// decode is kept as written.
+pub fn decode_encoded_words(value: &str) -> String {
+    let mut decoded = String::with_capacity(value.len());
+    let mut rest = value;
+    let mut pending_space = "";
+    let mut after_word = false;
+
+    while !rest.is_empty() {
+        let start = rest.find("=?").unwrap_or(rest.len());
+        let (text, tail) = rest.split_at(start);
+
+        if !text.is_empty() {
+            match after_word && text.trim().is_empty() {
+                true => pending_space = text,
+                false => {
+                    decoded.push_str(pending_space);
+                    decoded.push_str(text);
+                    pending_space = "";
+                }
+            }
+        }
+        if tail.is_empty() {
+            break;
+        }
+
+        match helpers::decode_word(tail) {
+            Some((word, len)) => {
+                decoded.push_str(&word);
+                pending_space = "";
+                after_word = true;
+                rest = &tail[len..];
+            }
+            None => {
+                decoded.push_str(pending_space);
+                decoded.push_str("=?");
+                pending_space = "";
+                after_word = false;
+                rest = &tail[2..];
+            }
+        }
+    }
+
+    decoded.push_str(pending_space);
+    decoded
+}
+
+/// Decodes base64, skipping line breaks and anything else outside the alphabet.
+pub fn decode_base64(input: &[u8]) -> Vec<u8> {
+    let mut decoded = Vec::with_capacity(input.len() / 4 * 3);
+    let mut buffer = 0u32;
+    let mut bits = 0;
+
+    for &byte in input {
+        let value = match byte {
+            b'A'..=b'Z' => byte - b'A',
+            b'a'..=b'z' => byte - b'a' + 26,
+            b'0'..=b'9' => byte - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            b'=' => break,
+            _ => continue,
+        };
+        buffer = (buffer << 6) | u32::from(value);
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            decoded.push((buffer >> bits) as u8);
+        }
+    }
+
+    decoded
+}
+
+/// Decodes quoted-printable, or the `Q` encoding of RFC 2047 if `header` is set, where `_`
+/// stands for a space.
+pub fn decode_quoted_printable(input: &[u8], header: bool) -> Vec<u8> {
+    let mut decoded = Vec::with_capacity(input.len());
+    let mut i = 0;
+
+    while i < input.len() {
+        match input[i] {
+            b'=' => {
+                let hex = input
+                    .get(i + 1..i + 3)
+                    .and_then(|hex| std::str::from_utf8(hex).ok())
+                    .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+                match (hex, input.get(i + 1)) {
+                    (Some(byte), _) => {
+                        decoded.push(byte);
+                        i += 3;
+                        continue;
+                    }
+                    // Soft line break.
+                    (None, Some(b'\r')) if input.get(i + 2) == Some(&b'\n') => i += 2,
+                    (None, Some(b'\n')) => i += 1,
+                    (None, _) => decoded.push(b'='),
+                }
+            }
+            b'_' if header => decoded.push(b' '),
+            byte => decoded.push(byte),
+        }
+        i += 1;
+    }
+
+    decoded
+}
+
+/// Converts text in `charset` to a string. Unknown charsets are read as UTF-8, replacing
+/// invalid sequences.
+pub fn decode_charset(bytes: &[u8], charset: &str) -> String {
+    match charset.to_ascii_lowercase().as_str() {
+        "iso-8859-1" | "iso8859-1" | "latin1" | "latin-1" | "windows-1252" | "cp1252" => {
+            bytes.iter().map(|&byte| char::from(byte)).collect()
+        }
+        _ => String::from_utf8_lossy(bytes).into_owned(),
+    }
+}
+
+mod helpers {
+    /// Decodes the encoded word at the start of `input`, returning it and its length.
+    pub fn decode_word(input: &str) -> Option<(String, usize)> {
+        let inner = input.strip_prefix("=?")?;
+        let (charset, inner) = inner.split_once('?')?;
+        let (encoding, inner) = inner.split_once('?')?;
+        let end = inner.find("?=")?;
+        let text = &inner[..end];
+        if text.contains(char::is_whitespace) {
+            return None;
+        }
+        let len = 2 + charset.len() + 1 + encoding.len() + 1 + end + 2;
+
+        // RFC 2231 allows a language suffix, e.g. `UTF-8*en`.
+        let charset = charset.split('*').next().unwrap_or(charset);
+        let bytes = match encoding {
+            "B" | "b" => decode_base64(text.as_bytes()),
+            "Q" | "q" => decode_quoted_printable(text.as_bytes(), true),
+            _ => return None,
+        };
+
+        Some((decode_charset(&bytes, charset), len))
+    }
+
+    use super::{decode_base64, decode_charset, decode_quoted_printable};
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_decode_encoded_words() {
+        assert_eq!(
+            decode_encoded_words("=?UTF-8?Q?Caf=C3=A9_au_lait?="),
+            "Café au lait"
+        );
+        assert_eq!(
+            decode_encoded_words("=?utf-8?B?w6lw?= =?utf-8?B?w6ll?="),
+            "épée"
+        );
+        assert_eq!(
+            decode_encoded_words("Re: =?ISO-8859-1?Q?d=E9j=E0?= vu"),
+            "Re: déjà vu"
+        );
+        assert_eq!(decode_encoded_words("50% =?off"), "50% =?off");
+    }
+
+    #[test]
+    fn test_decode_transfer_encodings() {
+        assert_eq!(decode_base64(b"aGVs\r\nbG8="), b"hello");
+        assert_eq!(
+            decode_quoted_printable(b"caf=C3=A9 =\r\nau lait=3D", false),
+            "café au lait=".as_bytes()
+        );
+    }
+
+    use super::*;
+}
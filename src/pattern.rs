@@ -0,0 +1,30 @@
+/// Translates a glob, where `*` matches any run of characters, `?` a single character and
+/// everything else is literal, into an anchored regular expression.
+pub fn glob_to_regex(glob: &str) -> String {
+    let mut regex = String::with_capacity(glob.len() + 2);
+    regex.push('^');
+    for c in glob.chars() {
+        match c {
+            '*' => regex.push_str(".*"),
+            '?' => regex.push('.'),
+            '\\' | '.' | '+' | '(' | ')' | '|' | '[' | ']' | '{' | '}' | '^' | '$' => {
+                regex.push('\\');
+                regex.push(c);
+            }
+            c => regex.push(c),
+        }
+    }
+    regex.push('$');
+    regex
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_glob_to_regex() {
+        assert_eq!(glob_to_regex("*@github.com"), r"^.*@github\.com$");
+        assert_eq!(glob_to_regex("[ci] build ?"), r"^\[ci\] build .$");
+    }
+
+    use super::*;
+}
@@ -1,2 +1,7 @@
 pub mod evolution;
+pub mod sieve;
 pub mod thunderbird;
+
+/// Raised when a filter's conditions mix `all` and `any` in a way a client's flat condition
+/// list cannot express.
+pub use crate::condition::Error as ConditionError;
@@ -2,9 +2,72 @@
 pub enum Error {
     #[error("evolution_id is missing")]
     MissingEvolutionId,
+    #[error("filter '{filter}': {source}")]
+    Condition {
+        filter: String,
+        #[source]
+        source: ConditionError,
+    },
+    #[error("filter '{filter}': Evolution rules cannot represent `{condition}`")]
+    UnsupportedCondition { filter: String, condition: String },
+    #[error("filter '{filter}': `run_on` is empty, so the rule would never run")]
+    EmptyRunOn { filter: String },
+    #[error("filter '{filter}': Evolution rules cannot run on `{run_on}`")]
+    UnsupportedRunOn {
+        filter: String,
+        run_on: &'static str,
+    },
+    #[error("filter '{filter}': Evolution rules cannot represent `{action}`")]
+    UnsupportedAction {
+        filter: String,
+        action: &'static str,
+    },
+    #[error(transparent)]
+    Io(#[from] io::Error),
 }
 
-pub fn print_config(config: Configuration) -> Result<String> {
+/// Prints the configuration as an Evolution `filters.xml`, indented if `pretty` is set.
+pub fn print_config(config: Configuration, pretty: bool) -> Result<String, Error> {
+    let mut buff = Vec::new();
+    write_config(config, &mut buff, pretty)?;
+    let output =
+        String::from_utf8(buff).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+    Ok(output.trim_end().to_owned())
+}
+
+/// Streams the configuration as an Evolution `filters.xml` into `writer`, indented if `pretty`
+/// is set and ending with a newline either way.
+pub fn write_config<W: io::Write>(
+    config: Configuration,
+    mut writer: W,
+    pretty: bool,
+) -> Result<(), Error> {
+    print_document(config)?.write(&mut writer, pretty)?;
+    if !pretty {
+        writeln!(writer)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Lists what [`print_config`] leaves out because Evolution rules cannot store it, e.g.
+/// `filter 'Github': Evolution rules cannot store \`description\`, it is left out`.
+pub fn dropped_fields(config: &Configuration) -> Vec<String> {
+    config
+        .accounts
+        .iter()
+        .flat_map(|account| &account.message_filters)
+        .filter(|message_filter| message_filter.description.is_some())
+        .map(|message_filter| {
+            format!(
+                "filter '{}': Evolution rules cannot store `description`, it is left out",
+                message_filter.title
+            )
+        })
+        .collect()
+}
+
+pub(crate) fn print_document(config: Configuration) -> Result<XmlDocument, Error> {
     let mut document = XmlDocument::new();
 
     let mut filter_options = XmlElementBuilder::new("filteroptions");
@@ -13,147 +76,488 @@ pub fn print_config(config: Configuration) -> Result<String> {
         let evolution_id = account.evolution_id.ok_or(Error::MissingEvolutionId)?;
 
         let mut rule_set = XmlElementBuilder::new("ruleset");
+        let mut outgoing_rules = Vec::new();
 
         for message_filter in account.message_filters {
-            let mut rule = XmlElementBuilder::new("rule");
-            rule.append_attr("enabled", "true")
-                .append_attr("grouping", "any")
-                .append_attr("source", "incoming");
+            let flat = condition::flatten(message_filter.grouping, &message_filter.when).map_err(
+                |source| Error::Condition {
+                    filter: message_filter.title.clone(),
+                    source,
+                },
+            )?;
+
+            if message_filter.run_on.is_empty() {
+                return Err(Error::EmptyRunOn {
+                    filter: message_filter.title,
+                });
+            }
+            let sources = helpers::format_sources(&message_filter.run_on).map_err(|run_on| {
+                Error::UnsupportedRunOn {
+                    filter: message_filter.title.clone(),
+                    run_on: run_on.key(),
+                }
+            })?;
 
-            rule.append_child({
+            let title = {
                 let mut title = XmlTextElementBuilder::new("title");
-                title.append_text(message_filter.title);
+                title.append_text(&message_filter.title);
                 title.build()
-            });
+            };
 
             let mut part_set = XmlElementBuilder::new("partset");
 
-            for condition in message_filter.when {
-                match condition {
-                    When::Contains(contains) => {
-                        let field = helpers::format_field(&contains.field);
-
-                        for needle in contains.values {
-                            let mut xml_part = XmlElementBuilder::new("part");
-                            xml_part.append_attr("name", &field);
-
-                            xml_part.append_child({
-                                let mut value = XmlElementBuilder::new("value");
-                                value
-                                    .append_attr("name", format!("{field}-type"))
-                                    .append_attr("type", "option")
-                                    .append_attr("value", "contains");
-                                value.build()
-                            });
-
-                            xml_part.append_child({
-                                let mut value = XmlElementBuilder::new("value");
-                                value
-                                    .append_attr("name", &field)
-                                    .append_attr("type", "string")
-                                    .append_attr("allow-empty", "false");
-                                value.append_child({
-                                    let mut string = XmlTextElementBuilder::new("string");
-                                    string.append_text(needle);
-                                    string.build()
-                                });
-                                value.build()
-                            });
-
-                            part_set.append_child(xml_part.build());
-                        }
-                    }
-                    When::EndsWith(ends_with) => {
-                        let field = helpers::format_field(&ends_with.field);
-
-                        for suffix in ends_with.values {
-                            let mut xml_part = XmlElementBuilder::new("part");
-                            xml_part.append_attr("name", &field);
-
-                            xml_part.append_child({
-                                let mut value = XmlElementBuilder::new("value");
-                                value
-                                    .append_attr("name", format!("{field}-type"))
-                                    .append_attr("type", "option")
-                                    .append_attr("value", "ends with");
-                                value.build()
-                            });
-
-                            xml_part.append_child({
-                                let mut value = XmlElementBuilder::new("value");
-                                value
-                                    .append_attr("name", &field)
-                                    .append_attr("type", "string")
-                                    .append_attr("allow-empty", "false");
-                                value.append_child({
-                                    let mut string = XmlTextElementBuilder::new("string");
-                                    string.append_text(suffix);
-                                    string.build()
-                                });
-                                value.build()
-                            });
-
-                            part_set.append_child(xml_part.build());
-                        }
+            for test in &flat.tests {
+                let part =
+                    helpers::format_test(test).ok_or_else(|| Error::UnsupportedCondition {
+                        filter: message_filter.title.clone(),
+                        condition: test.key(),
+                    })?;
+                part_set.append_child(part);
+            }
+
+            let mut action_set = XmlElementBuilder::new("actionset");
+
+            for action in &message_filter.then {
+                let part = helpers::format_action(&evolution_id, action).ok_or_else(|| {
+                    Error::UnsupportedAction {
+                        filter: message_filter.title.clone(),
+                        action: action.key(),
                     }
-                }
+                })?;
+                action_set.append_child(part);
             }
 
-            rule.append_child(part_set.build());
+            let (part_set, action_set) = (part_set.build(), action_set.build());
 
-            let mut action_set = XmlElementBuilder::new("actionset");
+            for source in sources {
+                let mut rule = XmlElementBuilder::new("rule");
+                rule.append_attr("enabled", message_filter.enabled)
+                    .append_attr("grouping", helpers::format_grouping(flat.grouping))
+                    .append_attr("source", source);
+                rule.append_child(title.clone())
+                    .append_child(part_set.clone())
+                    .append_child(action_set.clone());
 
-            for action in message_filter.then {
-                match action {
-                    Then::MoveTo(move_to) => {
-                        let mut part = XmlElementBuilder::new("part");
-                        part.append_attr("name", "move-to-folder");
-
-                        part.append_child({
-                            let mut value = XmlElementBuilder::new("value");
-                            value
-                                .append_attr("name", "folder")
-                                .append_attr("type", "folder");
-                            value.append_child({
-                                let mut folder = XmlElementBuilder::new("folder");
-                                folder.append_attr(
-                                    "uri",
-                                    helpers::format_folder(&evolution_id, &move_to.folder),
-                                );
-                                folder.build()
-                            });
-                            value.build()
-                        });
-                        action_set.append_child(part.build());
+                match source {
+                    "outgoing" => outgoing_rules.push(rule.build()),
+                    _ => {
+                        rule_set.append_child(rule.build());
                     }
                 }
             }
-
-            rule.append_child(action_set.build());
-            rule_set.append_child(rule.build());
         }
 
         filter_options.append_child(rule_set.build());
+
+        // Rules run after sending go into a rule set of their own.
+        if !outgoing_rules.is_empty() {
+            let mut outgoing = XmlElementBuilder::new("ruleset");
+            for rule in outgoing_rules {
+                outgoing.append_child(rule);
+            }
+            filter_options.append_child(outgoing.build());
+        }
     }
 
     document.append_element(filter_options.build());
 
-    Ok(document.to_string())
+    Ok(document)
 }
 
 mod helpers {
 
-    pub fn format_field(field: &Field) -> String {
-        match field {
-            Field::From => "sender".to_owned(),
+    /// How a [`Field`] is addressed in Evolution's `filtertypes.xml`.
+    pub struct EvolutionField {
+        /// The rule part, e.g. `sender` or `header`.
+        pub part: &'static str,
+        /// The name of the option value selecting the match type.
+        pub option: &'static str,
+        /// The name of the string value holding the needle.
+        pub value: &'static str,
+        /// The header name, for the generic `header` part.
+        pub header: Option<String>,
+    }
+
+    pub fn format_field(field: &Field) -> EvolutionField {
+        let (part, option, value) = match field {
+            Field::From => ("sender", "sender-type", "sender"),
+            Field::To => ("to", "recipient-type", "recipient"),
+            Field::Cc => ("cc", "recipient-type", "recipient"),
+            Field::Bcc => ("bcc", "recipient-type", "recipient"),
+            Field::ToOrCc => ("recipients", "recipient-type", "recipient"),
+            Field::Subject => ("subject", "subject-type", "subject"),
+            Field::Body => ("body", "body-type", "word"),
+            Field::ListId => ("mlist", "mlist-type", "mlist"),
+            Field::ReplyTo | Field::Sender | Field::Header(_) => {
+                return EvolutionField {
+                    part: "header",
+                    option: "header-type",
+                    value: "word",
+                    header: field.header_name().map(str::to_owned),
+                }
+            }
+        };
+
+        EvolutionField {
+            part,
+            option,
+            value,
+            header: None,
+        }
+    }
+
+    /// Builds the `<part>` for `test`, `None` if Evolution cannot express it.
+    pub fn format_test(test: &Test) -> Option<XmlElement> {
+        match test.predicate {
+            Predicate::String {
+                field,
+                matcher,
+                value,
+            } => {
+                let option = format_matcher(matcher, test.negated)?;
+                let needle = match matcher {
+                    Matcher::MatchesGlob => glob_to_regex(value),
+                    _ => value.to_owned(),
+                };
+                Some(format_part(field, option, &needle))
+            }
+            Predicate::State(state) => format_state(state, test.negated),
+        }
+    }
+
+    /// Lowers a state test to the `size`, `sent-date`, `status`, `attachments` or `label` part.
+    /// Sizes are compared in kilobytes, ages as a time span before now.
+    fn format_state(state: StateTest, negated: bool) -> Option<XmlElement> {
+        const DAY: i64 = 24 * 60 * 60;
+        let is = if negated { "is not" } else { "is" };
+
+        let (name, values) = match (state, negated) {
+            (StateTest::Size(size), false) => {
+                let (option, size) = match size {
+                    SizeComparison::GreaterThan(size) => ("greater-than", size),
+                    SizeComparison::LessThan(size) => ("less-than", size),
+                };
+                (
+                    "size",
+                    vec![
+                        format_option_value("size-type", option),
+                        format_integer_value("versus", size.kilobytes()),
+                    ],
+                )
+            }
+            (StateTest::AgeDays(age), false) => {
+                let (option, days) = match age {
+                    AgeComparison::MoreThan(days) => ("before", days),
+                    AgeComparison::LessThan(days) => ("after", days),
+                };
+                (
+                    "sent-date",
+                    vec![
+                        format_option_value("date-spec-type", option),
+                        format_datespec_value("versus", 2, i64::from(days) * DAY),
+                    ],
+                )
+            }
+            (StateTest::Date(date), negated) => {
+                let (option, date) = match (date, negated) {
+                    (DateComparison::Before(date), false) => ("before", date),
+                    (DateComparison::After(date), false) => ("after", date),
+                    (DateComparison::On(date), false) => ("is", date),
+                    (DateComparison::On(date), true) => ("is-not", date),
+                    _ => return None,
+                };
+                (
+                    "sent-date",
+                    vec![
+                        format_option_value("date-spec-type", option),
+                        format_datespec_value("versus", 1, date.days_since_epoch() * DAY),
+                    ],
+                )
+            }
+            (StateTest::Status(status), _) => {
+                let flag = match status {
+                    Status::Read => "Seen",
+                    Status::Flagged => "Flagged",
+                    Status::Replied => "Answered",
+                };
+                (
+                    "status",
+                    vec![
+                        format_option_value("match-type", is),
+                        format_option_value("flag", flag),
+                    ],
+                )
+            }
+            (StateTest::Junk, _) => (
+                "status",
+                vec![
+                    format_option_value("match-type", is),
+                    format_option_value("flag", "Junk"),
+                ],
+            ),
+            (StateTest::HasAttachment, _) => (
+                "attachments",
+                vec![format_option_value(
+                    "match-type",
+                    if negated { "not exist" } else { "exist" },
+                )],
+            ),
+            (StateTest::Tag(tag), _) => (
+                "label",
+                vec![
+                    format_option_value("label-type", is),
+                    format_option_value("versus", tag),
+                ],
+            ),
+            (StateTest::Size(_) | StateTest::AgeDays(_) | StateTest::Priority(_), _) => {
+                return None
+            }
+        };
+        Some(format_state_part(name, values))
+    }
+
+    /// Returns the match type option for a positive `matcher`, negated if `negated` is set.
+    /// Globs are matched as regular expressions, which cannot be negated.
+    pub fn format_matcher(matcher: Matcher, negated: bool) -> Option<&'static str> {
+        let option = match (matcher, negated) {
+            (Matcher::Contains, false) => "contains",
+            (Matcher::Contains, true) => "not contains",
+            (Matcher::Is, false) => "is",
+            (Matcher::Is, true) => "is not",
+            (Matcher::StartsWith, false) => "starts with",
+            (Matcher::StartsWith, true) => "not starts with",
+            (Matcher::EndsWith, false) => "ends with",
+            (Matcher::EndsWith, true) => "not ends with",
+            (Matcher::MatchesRegex | Matcher::MatchesGlob, false) => "regex",
+            _ => return None,
+        };
+        Some(option)
+    }
+
+    /// Returns the rule sources for `run_on`: `incoming` (which Evolution also runs on demand),
+    /// `demand` for manual-only filters and `outgoing`. Fails with the first trigger Evolution
+    /// has no source for.
+    pub fn format_sources(run_on: &[RunOn]) -> Result<Vec<&'static str>, RunOn> {
+        if let Some(unsupported) = run_on
+            .iter()
+            .find(|run_on| matches!(run_on, RunOn::Periodic | RunOn::Archiving))
+        {
+            return Err(*unsupported);
+        }
+
+        let mut sources = Vec::new();
+        if run_on.contains(&RunOn::Incoming) {
+            sources.push("incoming");
+        } else if run_on.contains(&RunOn::Manual) {
+            sources.push("demand");
+        }
+        if run_on.contains(&RunOn::AfterSending) {
+            sources.push("outgoing");
+        }
+        Ok(sources)
+    }
+
+    pub fn format_grouping(grouping: Grouping) -> &'static str {
+        match grouping {
+            Grouping::All => "all",
+            Grouping::Any => "any",
+        }
+    }
+
+    /// Builds a `<part>` testing `field` against `needle` with the given match type.
+    pub fn format_part(field: &Field, option: &str, needle: &str) -> XmlElement {
+        let field = format_field(field);
+
+        let mut xml_part = XmlElementBuilder::new("part");
+        xml_part.append_attr("name", field.part);
+
+        if let Some(header) = field.header {
+            xml_part.append_child(format_string_value("header-field", &header));
         }
+
+        xml_part.append_child({
+            let mut value = XmlElementBuilder::new("value");
+            value
+                .append_attr("name", field.option)
+                .append_attr("type", "option")
+                .append_attr("value", option);
+            value.build()
+        });
+
+        xml_part.append_child(format_string_value(field.value, needle));
+        xml_part.build()
+    }
+
+    fn format_string_value(name: &str, text: &str) -> XmlElement {
+        let mut value = XmlElementBuilder::new("value");
+        value
+            .append_attr("name", name)
+            .append_attr("type", "string")
+            .append_attr("allow-empty", "false");
+        value.append_child({
+            let mut string = XmlTextElementBuilder::new("string");
+            string.append_text(text);
+            string.build()
+        });
+        value.build()
     }
 
     pub fn format_folder(account: &str, folder: &str) -> String {
         format!("folder://{account}/{folder}")
     }
 
-    use crate::configuration::Field;
+    /// Builds the `actionset` part for `action`, `None` if Evolution has no equivalent.
+    pub fn format_action(account: &str, action: &Then) -> Option<XmlElement> {
+        let part = match action {
+            Then::MoveTo(move_to) => {
+                format_folder_action("move-to-folder", account, &move_to.folder)
+            }
+            Then::CopyTo(copy_to) => {
+                format_folder_action("copy-to-folder", account, &copy_to.folder)
+            }
+            Then::Tag(tag) => format_option_action("set-label", "label", &tag.tag),
+            Then::Forward(forward) => format_input_action("forward", "address", &forward.to),
+            Then::RunProgram(run_program) => format_input_action(
+                "shell-command",
+                "command",
+                &format_command(&run_program.command, &run_program.args),
+            ),
+            Then::SetPriority(_) | Then::ReplyWith(_) => return None,
+            Then::Simple(action) => match action {
+                SimpleAction::Delete => format_plain_action("delete"),
+                SimpleAction::MarkRead => format_option_action("set-status", "flag", "Seen"),
+                SimpleAction::MarkUnread => format_option_action("unset-status", "flag", "Seen"),
+                SimpleAction::Flag => format_option_action("set-status", "flag", "Flagged"),
+                SimpleAction::MarkJunk => format_option_action("set-status", "flag", "Junk"),
+                SimpleAction::MarkNotJunk => format_option_action("unset-status", "flag", "Junk"),
+                SimpleAction::StopProcessing => format_plain_action("stop"),
+            },
+        };
+        Some(part)
+    }
+
+    fn format_plain_action(name: &str) -> XmlElement {
+        let mut part = XmlElementBuilder::new("part");
+        part.append_attr("name", name);
+        part.build()
+    }
+
+    fn format_folder_action(name: &str, account: &str, folder: &str) -> XmlElement {
+        let mut part = XmlElementBuilder::new("part");
+        part.append_attr("name", name);
+
+        part.append_child({
+            let mut value = XmlElementBuilder::new("value");
+            value
+                .append_attr("name", "folder")
+                .append_attr("type", "folder");
+            value.append_child({
+                let mut uri = XmlElementBuilder::new("folder");
+                uri.append_attr("uri", format_folder(account, folder));
+                uri.build()
+            });
+            value.build()
+        });
+        part.build()
+    }
+
+    /// Builds a part with a single input, encoded as a child element named after its type.
+    fn format_input_action(name: &str, input: &str, text: &str) -> XmlElement {
+        let mut part = XmlElementBuilder::new("part");
+        part.append_attr("name", name);
+
+        part.append_child({
+            let mut value = XmlElementBuilder::new("value");
+            value.append_attr("name", input).append_attr("type", input);
+            value.append_child({
+                let mut content = XmlTextElementBuilder::new(input);
+                content.append_text(text);
+                content.build()
+            });
+            value.build()
+        });
+        part.build()
+    }
+
+    /// Joins `command` and `args` into a shell command line, single-quoting arguments that
+    /// contain anything but plain word characters.
+    pub fn format_command(command: &str, args: &[String]) -> String {
+        let mut line = command.to_owned();
+        for arg in args {
+            line.push(' ');
+            let plain = !arg.is_empty()
+                && arg.chars().all(|c| {
+                    c.is_ascii_alphanumeric()
+                        || matches!(c, '-' | '_' | '.' | '/' | '@' | '=' | ':' | ',')
+                });
+            if plain {
+                line.push_str(arg);
+            } else {
+                line.push('\'');
+                line.push_str(&arg.replace('\'', r"'\''"));
+                line.push('\'');
+            }
+        }
+        line
+    }
+
+    fn format_option_action(name: &str, option: &str, value: &str) -> XmlElement {
+        format_state_part(name, vec![format_option_value(option, value)])
+    }
+
+    fn format_state_part(name: &str, values: Vec<XmlElement>) -> XmlElement {
+        let mut part = XmlElementBuilder::new("part");
+        part.append_attr("name", name);
+        for value in values {
+            part.append_child(value);
+        }
+        part.build()
+    }
+
+    fn format_option_value(name: &str, option: &str) -> XmlElement {
+        let mut value = XmlElementBuilder::new("value");
+        value
+            .append_attr("name", name)
+            .append_attr("type", "option")
+            .append_attr("value", option);
+        value.build()
+    }
+
+    fn format_integer_value(name: &str, integer: u64) -> XmlElement {
+        let mut value = XmlElementBuilder::new("value");
+        value
+            .append_attr("name", name)
+            .append_attr("type", "integer")
+            .append_attr("integer", integer);
+        value.build()
+    }
+
+    /// Builds a date value, either a point in time (`type="1"`, seconds since the epoch) or a
+    /// time span before now (`type="2"`, in seconds).
+    fn format_datespec_value(name: &str, kind: u8, seconds: i64) -> XmlElement {
+        let mut value = XmlElementBuilder::new("value");
+        value
+            .append_attr("name", name)
+            .append_attr("type", "datespec");
+        value.append_child({
+            let mut datespec = XmlElementBuilder::new("datespec");
+            datespec
+                .append_attr("type", kind)
+                .append_attr("value", seconds);
+            datespec.build()
+        });
+        value.build()
+    }
+
+    use crate::{
+        condition::{Predicate, Test},
+        configuration::{
+            AgeComparison, DateComparison, Field, Grouping, Matcher, RunOn, SimpleAction,
+            SizeComparison, StateTest, Status, Then,
+        },
+        pattern::glob_to_regex,
+        xml::{XmlElement, XmlElementBuilder, XmlTextElementBuilder},
+    };
 }
 
 #[cfg(test)]
@@ -162,11 +566,21 @@ mod tests {
     fn test_print_config() {
         let config = Configuration {
             accounts: vec![Account {
+                account: None,
+                email: None,
+                imap_host: None,
                 evolution_id: Some("evolution".to_owned()),
                 thunderbird_id: Some("thunderbird".to_owned()),
+                thunderbird_path: None,
+                sieve_separator: None,
+                templates: BTreeMap::new(),
                 message_filters: vec![
                     MessageFilter {
                         title: "DigitalOcean".to_owned(),
+                        grouping: Grouping::Any,
+                        description: None,
+                        enabled: true,
+                        run_on: vec![RunOn::Incoming, RunOn::Manual],
                         when: vec![When::EndsWith(EndsWith {
                             field: Field::From,
                             values: vec!["@digitalocean.com".to_owned()],
@@ -177,6 +591,10 @@ mod tests {
                     },
                     MessageFilter {
                         title: "Amazon".to_owned(),
+                        grouping: Grouping::Any,
+                        description: None,
+                        enabled: true,
+                        run_on: vec![RunOn::Incoming, RunOn::Manual],
                         when: vec![When::Contains(Contains {
                             field: Field::From,
                             values: vec!["@amazon.".to_owned()],
@@ -190,7 +608,7 @@ mod tests {
         };
 
         assert_eq!(
-            print_config(config).unwrap(),
+            print_document(config).unwrap().to_string(),
             vec![
                 "<?xml version=\"1.0\"?>",
                 "<filteroptions>",
@@ -199,7 +617,7 @@ mod tests {
                 "<title>DigitalOcean</title>",
                 "<partset>",
                 "<part name=\"sender\">",
-                "<value name=\"sender-type\" type=\"option\" value=\"ends with\"></value>",
+                "<value name=\"sender-type\" type=\"option\" value=\"ends with\"/>",
                 "<value name=\"sender\" type=\"string\" allow-empty=\"false\">",
                 "<string>@digitalocean.com</string>",
                 "</value>",
@@ -208,8 +626,7 @@ mod tests {
                 "<actionset>",
                 "<part name=\"move-to-folder\">",
                 "<value name=\"folder\" type=\"folder\">",
-                "<folder uri=\"folder://evolution/do\">",
-                "</folder>",
+                "<folder uri=\"folder://evolution/do\"/>",
                 "</value>",
                 "</part>",
                 "</actionset>",
@@ -218,7 +635,7 @@ mod tests {
                 "<title>Amazon</title>",
                 "<partset>",
                 "<part name=\"sender\">",
-                "<value name=\"sender-type\" type=\"option\" value=\"contains\"></value>",
+                "<value name=\"sender-type\" type=\"option\" value=\"contains\"/>",
                 "<value name=\"sender\" type=\"string\" allow-empty=\"false\">",
                 "<string>@amazon.</string>",
                 "</value>",
@@ -227,8 +644,7 @@ mod tests {
                 "<actionset>",
                 "<part name=\"move-to-folder\">",
                 "<value name=\"folder\" type=\"folder\">",
-                "<folder uri=\"folder://evolution/amzn\">",
-                "</folder>",
+                "<folder uri=\"folder://evolution/amzn\"/>",
                 "</value>",
                 "</part>",
                 "</actionset>",
@@ -240,12 +656,391 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_write_config() {
+        let config = crate::configuration::parse(
+            "\
+- evolution_id: evolution
+  message_filters:
+    - title: Github
+      when:
+        - field: from
+          ends_with: ['@github.com']
+      then:
+        - move_to: Github
+",
+        )
+        .unwrap();
+        for pretty in [false, true] {
+            let mut output = Vec::new();
+            write_config(config.clone(), &mut output, pretty).unwrap();
+            assert_eq!(
+                String::from_utf8(output).unwrap(),
+                format!("{}\n", print_config(config.clone(), pretty).unwrap())
+            );
+        }
+    }
+
+    #[test]
+    fn test_print_config_with_matchers() {
+        let config = Configuration {
+            accounts: vec![Account {
+                account: None,
+                email: None,
+                imap_host: None,
+                evolution_id: Some("evolution".to_owned()),
+                thunderbird_id: None,
+                thunderbird_path: None,
+                sieve_separator: None,
+                templates: BTreeMap::new(),
+                message_filters: vec![MessageFilter {
+                    title: "CI".to_owned(),
+                    grouping: Grouping::Any,
+                    description: None,
+                    enabled: true,
+                    run_on: vec![RunOn::Incoming, RunOn::Manual],
+                    when: vec![
+                        When::StartsWith(StartsWith {
+                            field: Field::Subject,
+                            values: vec!["[ci]".to_owned()],
+                        }),
+                        When::MatchesGlob(MatchesGlob {
+                            field: Field::From,
+                            values: vec!["build-*@example.com".to_owned()],
+                        }),
+                    ],
+                    then: vec![Then::MoveTo(MoveTo {
+                        folder: "ci".to_owned(),
+                    })],
+                }],
+            }],
+        };
+
+        let output = print_document(config).unwrap().to_string();
+        assert!(
+            output.contains("<value name=\"subject-type\" type=\"option\" value=\"starts with\"/>")
+        );
+        assert!(output.contains("<value name=\"sender-type\" type=\"option\" value=\"regex\"/>"));
+        assert!(output.contains("<string>^build-.*@example\\.com$</string>"));
+    }
+
+    #[test]
+    fn test_print_config_groups_negated_values() {
+        let filter = |when: Vec<When>| Configuration {
+            accounts: vec![Account {
+                account: None,
+                email: None,
+                imap_host: None,
+                evolution_id: Some("evolution".to_owned()),
+                thunderbird_id: None,
+                thunderbird_path: None,
+                sieve_separator: None,
+                templates: BTreeMap::new(),
+                message_filters: vec![MessageFilter {
+                    title: "Not work".to_owned(),
+                    grouping: Grouping::Any,
+                    description: None,
+                    enabled: true,
+                    run_on: vec![RunOn::Incoming, RunOn::Manual],
+                    when,
+                    then: vec![Then::MoveTo(MoveTo {
+                        folder: "personal".to_owned(),
+                    })],
+                }],
+            }],
+        };
+        let not_work = When::DoesNotContain(DoesNotContain {
+            field: Field::From,
+            values: vec!["@work.com".to_owned(), "@corp.com".to_owned()],
+        });
+
+        let output = print_document(filter(vec![not_work.clone()]))
+            .unwrap()
+            .to_string();
+        assert!(output.contains("<rule enabled=\"true\" grouping=\"all\" source=\"incoming\">"));
+        assert_eq!(output.matches("value=\"not contains\"").count(), 2);
+
+        let mixed = filter(vec![
+            not_work,
+            When::StartsWith(StartsWith {
+                field: Field::Subject,
+                values: vec!["[personal]".to_owned()],
+            }),
+        ]);
+        assert_eq!(
+            print_document(mixed).err().unwrap().to_string(),
+            "filter 'Not work': conditions mix `all` and `any`, which cannot be expressed as a single list"
+        );
+    }
+
+    #[test]
+    fn test_print_config_with_all_actions() {
+        let source = "\
+- evolution_id: evolution
+  thunderbird_id: thunderbird
+  message_filters:
+    - title: Newsletters
+      when:
+        - field: list_id
+          contains: [news]
+      then:
+        - copy_to: Archive/News
+        - mark_read
+        - flag
+        - tag: $label1
+        - mark_junk
+        - mark_not_junk
+        - mark_unread
+        - delete
+        - stop_processing
+";
+        let config = crate::configuration::parse(source).unwrap();
+        let output = print_document(config).unwrap().to_string();
+        let actions = &output[output.find("<actionset>").unwrap()..];
+
+        assert_eq!(
+            actions,
+            [
+                "<actionset>",
+                "<part name=\"copy-to-folder\">",
+                "<value name=\"folder\" type=\"folder\">",
+                "<folder uri=\"folder://evolution/Archive/News\"/>",
+                "</value>",
+                "</part>",
+                "<part name=\"set-status\"><value name=\"flag\" type=\"option\" value=\"Seen\"/></part>",
+                "<part name=\"set-status\"><value name=\"flag\" type=\"option\" value=\"Flagged\"/></part>",
+                "<part name=\"set-label\"><value name=\"label\" type=\"option\" value=\"$label1\"/></part>",
+                "<part name=\"set-status\"><value name=\"flag\" type=\"option\" value=\"Junk\"/></part>",
+                "<part name=\"unset-status\"><value name=\"flag\" type=\"option\" value=\"Junk\"/></part>",
+                "<part name=\"unset-status\"><value name=\"flag\" type=\"option\" value=\"Seen\"/></part>",
+                "<part name=\"delete\"/>",
+                "<part name=\"stop\"/>",
+                "</actionset>",
+                "</rule>",
+                "</ruleset>",
+                "</filteroptions>",
+            ]
+            .join("")
+        );
+
+        let source = source.replace("- delete", "- set_priority: high");
+        let config = crate::configuration::parse(&source).unwrap();
+        assert_eq!(
+            print_document(config).err().unwrap().to_string(),
+            "filter 'Newsletters': Evolution rules cannot represent `set_priority`"
+        );
+    }
+
+    #[test]
+    fn test_print_config_with_forward_and_program() {
+        let source = "\
+- evolution_id: evolution
+  thunderbird_id: thunderbird
+  templates:
+    thanks: mailbox://nobody@Local%20Folders/Templates?messageId=1
+  message_filters:
+    - title: Invoices
+      when:
+        - field: subject
+          contains: [invoice]
+      then:
+        - forward: accounting@example.com
+        - run_program: notify-send
+          args: [Invoice]
+";
+        let config = crate::configuration::parse(source).unwrap();
+        let output = print_document(config).unwrap().to_string();
+
+        assert!(output.contains(
+            "<part name=\"forward\"><value name=\"address\" type=\"address\">\
+             <address>accounting@example.com</address></value></part>"
+        ));
+        assert!(output.contains(
+            "<part name=\"shell-command\"><value name=\"command\" type=\"command\">\
+             <command>notify-send Invoice</command></value></part>"
+        ));
+    }
+
+    #[test]
+    fn test_print_config_with_run_on() {
+        let source = "\
+- evolution_id: evolution
+  thunderbird_id: thunderbird
+  message_filters:
+    - title: Cleanup
+      description: Old newsletters
+      enabled: false
+      run_on: [manual, after_sending]
+      when:
+        - field: list_id
+          contains: [news]
+      then:
+        - delete
+";
+        let config = crate::configuration::parse(source).unwrap();
+        let output = print_document(config).unwrap().to_string();
+
+        assert!(output.starts_with(
+            "<?xml version=\"1.0\"?><filteroptions><ruleset>\
+             <rule enabled=\"false\" grouping=\"any\" source=\"demand\"><title>Cleanup</title>"
+        ));
+        assert!(output.contains(
+            "</ruleset><ruleset>\
+             <rule enabled=\"false\" grouping=\"any\" source=\"outgoing\"><title>Cleanup</title>"
+        ));
+
+        let config = crate::configuration::parse(source).unwrap();
+        assert_eq!(
+            dropped_fields(&config),
+            ["filter 'Cleanup': Evolution rules cannot store `description`, it is left out"]
+        );
+
+        let periodic = source.replace("after_sending", "periodic");
+        let config = crate::configuration::parse(&periodic).unwrap();
+        assert_eq!(
+            print_document(config).err().unwrap().to_string(),
+            "filter 'Cleanup': Evolution rules cannot run on `periodic`"
+        );
+
+        let never = source.replace("[manual, after_sending]", "[]");
+        let config = crate::configuration::parse(&never).unwrap();
+        assert_eq!(
+            print_document(config).err().unwrap().to_string(),
+            "filter 'Cleanup': `run_on` is empty, so the rule would never run"
+        );
+    }
+
+    #[test]
+    fn test_print_config_with_state_conditions() {
+        let source = "\
+- evolution_id: evolution
+  thunderbird_id: thunderbird
+  message_filters:
+    - title: Old
+      match: all
+      when:
+        - size: { greater_than: 5MB }
+        - date: { on: 2026-01-31 }
+        - not:
+            status: read
+        - has_attachment: true
+        - junk: false
+        - tag: $label1
+      then:
+        - delete
+";
+        let config = crate::configuration::parse(source).unwrap();
+        let output = print_document(config).unwrap().to_string();
+
+        assert!(output.contains(
+            "<part name=\"size\">\
+             <value name=\"size-type\" type=\"option\" value=\"greater-than\"/>\
+             <value name=\"versus\" type=\"integer\" integer=\"5120\"/></part>\
+             <part name=\"sent-date\">\
+             <value name=\"date-spec-type\" type=\"option\" value=\"is\"/>\
+             <value name=\"versus\" type=\"datespec\"><datespec type=\"1\" value=\"1769817600\"/>\
+             </value></part>\
+             <part name=\"status\">\
+             <value name=\"match-type\" type=\"option\" value=\"is not\"/>\
+             <value name=\"flag\" type=\"option\" value=\"Seen\"/></part>\
+             <part name=\"attachments\">\
+             <value name=\"match-type\" type=\"option\" value=\"exist\"/></part>\
+             <part name=\"status\">\
+             <value name=\"match-type\" type=\"option\" value=\"is not\"/>\
+             <value name=\"flag\" type=\"option\" value=\"Junk\"/></part>\
+             <part name=\"label\">\
+             <value name=\"label-type\" type=\"option\" value=\"is\"/>\
+             <value name=\"versus\" type=\"option\" value=\"$label1\"/></part>"
+        ));
+
+        let source = source.replace("- junk: false", "- priority: high");
+        let config = crate::configuration::parse(&source).unwrap();
+        assert_eq!(
+            print_document(config).err().unwrap().to_string(),
+            "filter 'Old': Evolution rules cannot represent `priority`"
+        );
+    }
+
+    #[test]
+    fn test_format_command() {
+        assert_eq!(
+            helpers::format_command(
+                "notify-send",
+                &["-a".to_owned(), "it's mail".to_owned(), String::new()]
+            ),
+            r"notify-send -a 'it'\''s mail' ''"
+        );
+    }
+
+    #[test]
+    fn test_format_header_part() {
+        assert_eq!(
+            helpers::format_part(&Field::Header("X-Mailer".to_owned()), "contains", "Mutt")
+                .to_string(),
+            [
+                "<part name=\"header\">",
+                "<value name=\"header-field\" type=\"string\" allow-empty=\"false\">",
+                "<string>X-Mailer</string>",
+                "</value>",
+                "<value name=\"header-type\" type=\"option\" value=\"contains\"/>",
+                "<value name=\"word\" type=\"string\" allow-empty=\"false\">",
+                "<string>Mutt</string>",
+                "</value>",
+                "</part>",
+            ]
+            .join("")
+        );
+    }
+
+    #[test]
+    fn test_print_config_escapes_values() {
+        let config = Configuration {
+            accounts: vec![Account {
+                account: None,
+                email: None,
+                imap_host: None,
+                evolution_id: Some("evolution".to_owned()),
+                thunderbird_id: None,
+                thunderbird_path: None,
+                sieve_separator: None,
+                templates: BTreeMap::new(),
+                message_filters: vec![MessageFilter {
+                    title: "R&D <alerts>".to_owned(),
+                    grouping: Grouping::Any,
+                    description: None,
+                    enabled: true,
+                    run_on: vec![RunOn::Incoming, RunOn::Manual],
+                    when: vec![When::Contains(Contains {
+                        field: Field::From,
+                        values: vec!["\"R&D\"".to_owned()],
+                    })],
+                    then: vec![Then::MoveTo(MoveTo {
+                        folder: "Say \"hi\"".to_owned(),
+                    })],
+                }],
+            }],
+        };
+
+        let output = print_document(config).unwrap().to_string();
+        assert!(output.contains("<title>R&amp;D &lt;alerts&gt;</title>"));
+        assert!(output.contains("<string>\"R&amp;D\"</string>"));
+        assert!(output.contains("<folder uri=\"folder://evolution/Say &quot;hi&quot;\"/>"));
+    }
+
+    use std::collections::BTreeMap;
+
     use super::*;
-    use crate::configuration::{Account, Contains, EndsWith, Field, MessageFilter, MoveTo, When};
+    use crate::configuration::{
+        Account, Contains, DoesNotContain, EndsWith, Field, Grouping, MatchesGlob, MessageFilter,
+        MoveTo, RunOn, StartsWith, Then, When,
+    };
 }
 
+use std::io;
+
 use crate::{
-    configuration::{Configuration, Then, When},
+    condition,
+    configuration::Configuration,
+    printer::ConditionError,
     xml::{XmlDocument, XmlElementBuilder, XmlTextElementBuilder},
-    Result,
 };
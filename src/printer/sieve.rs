@@ -0,0 +1,765 @@
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("filter '{filter}': Sieve scripts only run on incoming mail")]
+    NotIncoming { filter: String },
+    #[error("filter '{filter}': Sieve scripts cannot represent `{condition}`")]
+    UnsupportedCondition {
+        filter: String,
+        condition: &'static str,
+    },
+    #[error("filter '{filter}': Sieve scripts cannot represent `{action}`")]
+    UnsupportedAction {
+        filter: String,
+        action: &'static str,
+    },
+    #[error("filter {filter:?}: titles cannot contain line breaks or `]` in Sieve scripts")]
+    InvalidTitle { filter: String },
+}
+
+pub fn print_config(config: Configuration) -> Result<String, Error> {
+    let mut extensions = BTreeSet::new();
+    let mut rules = Vec::new();
+
+    for account in config.accounts {
+        let separator = account.sieve_separator.as_deref().unwrap_or("/");
+
+        for message_filter in account.message_filters {
+            // The title goes into a `# rule:[...]` comment, which a line break would end.
+            if message_filter.title.contains(['\r', '\n', ']']) {
+                return Err(Error::InvalidTitle {
+                    filter: message_filter.title,
+                });
+            }
+
+            let tests = message_filter
+                .when
+                .iter()
+                .map(|when| helpers::format_test(when, &mut extensions))
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|condition| Error::UnsupportedCondition {
+                    filter: message_filter.title.clone(),
+                    condition,
+                })?;
+
+            let test = helpers::format_group(message_filter.grouping, tests);
+
+            if !message_filter.run_on.contains(&RunOn::Incoming) {
+                return Err(Error::NotIncoming {
+                    filter: message_filter.title,
+                });
+            }
+
+            let mut rule = vec![format!("# rule:[{}]", message_filter.title)];
+            if let Some(description) = &message_filter.description {
+                rule.extend(description.lines().map(|line| format!("# {line}")));
+            }
+            // Disabled rules keep their test in a comment, like Roundcube's managesieve plugin.
+            match message_filter.enabled {
+                true => rule.push(format!("if {test} {{")),
+                false => rule.extend([format!("if false # {test}"), "{".to_owned()]),
+            }
+
+            for action in &message_filter.then {
+                let command = helpers::format_action(separator, action, &mut extensions)
+                    .ok_or_else(|| Error::UnsupportedAction {
+                        filter: message_filter.title.clone(),
+                        action: action.key(),
+                    })?;
+                rule.push(format!("    {command};"));
+            }
+
+            rule.push("}".to_owned());
+            rules.push(rule.join("\n"));
+        }
+    }
+
+    let mut script = Vec::new();
+    if !extensions.is_empty() {
+        script.push(format!(
+            "require {};",
+            helpers::format_string_list(extensions)
+        ));
+    }
+    script.extend(rules);
+
+    Ok(script.join("\n\n"))
+}
+
+mod helpers {
+
+    /// Formats the test for `when`, failing with the key of the first condition Sieve cannot
+    /// express.
+    pub fn format_test(
+        when: &When,
+        extensions: &mut BTreeSet<&'static str>,
+    ) -> Result<String, &'static str> {
+        let (grouping, conditions) = match when {
+            When::All(all) => (Grouping::All, &all.conditions),
+            When::Any(any) => (Grouping::Any, &any.conditions),
+            When::Not(not) => {
+                return Ok(format!("not {}", format_test(&not.condition, extensions)?))
+            }
+            _ => match when.state_test() {
+                Some((state, true)) => return format_state_test(state, extensions),
+                Some((state, false)) => {
+                    return Ok(format!("not {}", format_state_test(state, extensions)?))
+                }
+                None => return Ok(format_matcher_test(when, extensions)),
+            },
+        };
+
+        let tests = conditions
+            .iter()
+            .map(|condition| format_test(condition, extensions))
+            .collect::<Result<_, _>>()?;
+        Ok(format_group(grouping, tests))
+    }
+
+    /// Formats a test on the message state. Flags are tested with `imap4flags`, which mostly
+    /// makes sense in scripts run on demand, and the age of a message cannot be expressed.
+    fn format_state_test(
+        state: StateTest,
+        extensions: &mut BTreeSet<&'static str>,
+    ) -> Result<String, &'static str> {
+        let (extension, test) = match state {
+            StateTest::Size(SizeComparison::GreaterThan(size)) => {
+                return Ok(format!("size :over {}", format_size(size)))
+            }
+            StateTest::Size(SizeComparison::LessThan(size)) => {
+                return Ok(format!("size :under {}", format_size(size)))
+            }
+            StateTest::AgeDays(_) => return Err(state.key()),
+            StateTest::Date(date) => {
+                let test = match date {
+                    DateComparison::Before(date) => {
+                        extensions.insert("relational");
+                        format!(r#"date :value "lt" "date" "date" "{date}""#)
+                    }
+                    DateComparison::After(date) => {
+                        extensions.insert("relational");
+                        format!(r#"date :value "gt" "date" "date" "{date}""#)
+                    }
+                    DateComparison::On(date) => format!(r#"date :is "date" "date" "{date}""#),
+                };
+                ("date", test)
+            }
+            StateTest::Status(status) => {
+                let flag = match status {
+                    Status::Read => "\\Seen",
+                    Status::Flagged => "\\Flagged",
+                    Status::Replied => "\\Answered",
+                };
+                ("imap4flags", format!("hasflag {}", quote(flag)))
+            }
+            StateTest::HasAttachment => (
+                "mime",
+                r#"header :mime :anychild :contains "Content-Disposition" "attachment""#.to_owned(),
+            ),
+            StateTest::Priority(priority) => {
+                let level = match priority {
+                    Priority::Highest => "1*",
+                    Priority::High => "2*",
+                    Priority::Normal => "3*",
+                    Priority::Low => "4*",
+                    Priority::Lowest => "5*",
+                };
+                return Ok(format!(r#"header :matches "X-Priority" "{level}""#));
+            }
+            StateTest::Junk => ("imap4flags", r#"hasflag "$Junk""#.to_owned()),
+            StateTest::Tag(tag) => ("imap4flags", format!("hasflag {}", quote(tag))),
+        };
+        extensions.insert(extension);
+        Ok(test)
+    }
+
+    /// Formats `size` as a Sieve number, using the `K`, `M` and `G` quantifiers when exact.
+    fn format_size(size: ByteSize) -> String {
+        let units = [(1 << 30, "G"), (1 << 20, "M"), (1 << 10, "K")];
+        match units
+            .iter()
+            .find(|(scale, _)| size.0 != 0 && size.0.is_multiple_of(*scale))
+        {
+            Some((scale, unit)) => format!("{}{unit}", size.0 / scale),
+            None => size.0.to_string(),
+        }
+    }
+
+    /// Combines `tests` with `allof` or `anyof`, unless there is only one. Sieve requires at
+    /// least one test in a list, so an empty group is `true` or `false` as the engine has it.
+    pub fn format_group(grouping: Grouping, tests: Vec<String>) -> String {
+        match (grouping, tests.len()) {
+            (Grouping::All, 0) => "true".to_owned(),
+            (Grouping::Any, 0) => "false".to_owned(),
+            (_, 1) => tests.into_iter().next().unwrap_or_default(),
+            (Grouping::All, _) => format!("allof ({})", tests.join(", ")),
+            (Grouping::Any, _) => format!("anyof ({})", tests.join(", ")),
+        }
+    }
+
+    fn format_matcher_test(when: &When, extensions: &mut BTreeSet<&'static str>) -> String {
+        let Some(test) = when.string_test() else {
+            unreachable!("groups are handled by format_test");
+        };
+        let values = test.values.iter();
+
+        let (match_type, keys): (&str, Vec<String>) = match test.matcher {
+            Matcher::Contains | Matcher::DoesNotContain => (":contains", values.cloned().collect()),
+            Matcher::Is | Matcher::IsNot => (":is", values.cloned().collect()),
+            Matcher::StartsWith => (
+                ":matches",
+                values
+                    .map(|prefix| format!("{}*", escape_wildcards(prefix)))
+                    .collect(),
+            ),
+            Matcher::EndsWith | Matcher::DoesNotEndWith => (
+                ":matches",
+                values
+                    .map(|suffix| format!("*{}", escape_wildcards(suffix)))
+                    .collect(),
+            ),
+            Matcher::MatchesRegex => {
+                extensions.insert("regex");
+                (":regex", values.cloned().collect())
+            }
+            // A glob's backslash is literal, but escapes the next character in `:matches`.
+            Matcher::MatchesGlob => (
+                ":matches",
+                values.map(|glob| glob.replace('\\', "\\\\")).collect(),
+            ),
+        };
+
+        let formatted = format_string_test(test.field, match_type, &keys, extensions);
+        match test.matcher.is_negated() {
+            true => format!("not {formatted}"),
+            false => formatted,
+        }
+    }
+
+    /// Formats a `header`, `address` or `body` test depending on the kind of `field`.
+    /// `:contains` and `:regex` tests always use `header` so display names are searched too.
+    fn format_string_test(
+        field: &Field,
+        match_type: &str,
+        keys: &[String],
+        extensions: &mut BTreeSet<&'static str>,
+    ) -> String {
+        let keys = format_string_list(keys.iter().map(String::as_str));
+
+        if *field == Field::Body {
+            extensions.insert("body");
+            return format!("body {match_type} {keys}");
+        }
+
+        let test = if matches!(match_type, ":is" | ":matches") && is_address(field) {
+            "address"
+        } else {
+            "header"
+        };
+        let headers = format_headers(field);
+        let headers = format_string_list(headers.iter().map(String::as_str));
+        format!("{test} {match_type} {headers} {keys}")
+    }
+
+    pub fn format_headers(field: &Field) -> Vec<String> {
+        match field {
+            Field::ToOrCc => vec!["to".to_owned(), "cc".to_owned()],
+            Field::Header(name) => vec![name.clone()],
+            field => field
+                .header_name()
+                .map(str::to_lowercase)
+                .into_iter()
+                .collect(),
+        }
+    }
+
+    fn is_address(field: &Field) -> bool {
+        matches!(
+            field,
+            Field::From
+                | Field::To
+                | Field::Cc
+                | Field::ToOrCc
+                | Field::Bcc
+                | Field::ReplyTo
+                | Field::Sender
+        )
+    }
+
+    /// Formats the command for `action`, `None` if Sieve has no equivalent. Flags use the
+    /// `imap4flags` extension, with the `$Junk`/`$NotJunk` keywords for junk.
+    pub fn format_action(
+        separator: &str,
+        action: &Then,
+        extensions: &mut BTreeSet<&'static str>,
+    ) -> Option<String> {
+        let (extension, command) = match action {
+            Then::MoveTo(move_to) => {
+                let folder = format_folder(separator, &move_to.folder);
+                ("fileinto", format!("fileinto {}", quote(&folder)))
+            }
+            Then::CopyTo(copy_to) => {
+                extensions.insert("copy");
+                let folder = format_folder(separator, &copy_to.folder);
+                ("fileinto", format!("fileinto :copy {}", quote(&folder)))
+            }
+            Then::Tag(tag) => ("imap4flags", format!("addflag {}", quote(&tag.tag))),
+            Then::Forward(forward) => return Some(format!("redirect {}", quote(&forward.to))),
+            Then::SetPriority(_) | Then::ReplyWith(_) | Then::RunProgram(_) => return None,
+            Then::Simple(action) => match action {
+                SimpleAction::Delete => return Some("discard".to_owned()),
+                SimpleAction::StopProcessing => return Some("stop".to_owned()),
+                SimpleAction::MarkRead => ("imap4flags", r#"addflag "\\Seen""#.to_owned()),
+                SimpleAction::MarkUnread => ("imap4flags", r#"removeflag "\\Seen""#.to_owned()),
+                SimpleAction::Flag => ("imap4flags", r#"addflag "\\Flagged""#.to_owned()),
+                SimpleAction::MarkJunk => ("imap4flags", r#"addflag "$Junk""#.to_owned()),
+                SimpleAction::MarkNotJunk => ("imap4flags", r#"addflag "$NotJunk""#.to_owned()),
+            },
+        };
+        extensions.insert(extension);
+        Some(command)
+    }
+
+    /// Converts a `/`-separated folder path into a mailbox name using `separator`.
+    pub fn format_folder(separator: &str, folder: &str) -> String {
+        folder.split('/').collect::<Vec<_>>().join(separator)
+    }
+
+    pub fn format_string_list<'a, I>(values: I) -> String
+    where
+        I: IntoIterator<Item = &'a str>,
+    {
+        let values: Vec<String> = values.into_iter().map(quote).collect();
+        match values.len() {
+            1 => values.into_iter().next().unwrap_or_default(),
+            _ => format!("[{}]", values.join(", ")),
+        }
+    }
+
+    /// Quotes `value` as a Sieve quoted-string (RFC 5228, section 2.4.2).
+    pub fn quote(value: &str) -> String {
+        let mut quoted = String::with_capacity(value.len() + 2);
+        quoted.push('"');
+        for c in value.chars() {
+            if c == '"' || c == '\\' {
+                quoted.push('\\');
+            }
+            quoted.push(c);
+        }
+        quoted.push('"');
+        quoted
+    }
+
+    /// Escapes the `:matches` wildcards `*` and `?` (and backslashes) so they match literally.
+    pub fn escape_wildcards(value: &str) -> String {
+        let mut escaped = String::with_capacity(value.len());
+        for c in value.chars() {
+            if c == '*' || c == '?' || c == '\\' {
+                escaped.push('\\');
+            }
+            escaped.push(c);
+        }
+        escaped
+    }
+
+    #[cfg(test)]
+    mod tests {
+        #[test]
+        fn test_quote_escapes_quotes_and_backslashes() {
+            assert_eq!(quote(r#"a "b" \c"#), r#""a \"b\" \\c""#);
+        }
+
+        #[test]
+        fn test_format_ends_with_escapes_wildcards() {
+            assert_eq!(
+                format_test(
+                    &When::EndsWith(EndsWith {
+                        field: Field::From,
+                        values: vec!["*@example.com".to_owned()]
+                    }),
+                    &mut BTreeSet::new()
+                )
+                .unwrap(),
+                r#"address :matches "from" "*\\*@example.com""#
+            );
+        }
+
+        #[test]
+        fn test_format_fields() {
+            let mut extensions = BTreeSet::new();
+            let mut test = |field: Field| {
+                format_test(
+                    &When::EndsWith(EndsWith {
+                        field,
+                        values: vec!["x".to_owned()],
+                    }),
+                    &mut extensions,
+                )
+                .unwrap()
+            };
+
+            assert_eq!(test(Field::ToOrCc), r#"address :matches ["to", "cc"] "*x""#);
+            assert_eq!(test(Field::ListId), r#"header :matches "list-id" "*x""#);
+            assert_eq!(
+                test(Field::Header("X-Spam-Flag".to_owned())),
+                r#"header :matches "X-Spam-Flag" "*x""#
+            );
+            assert_eq!(test(Field::Body), r#"body :matches "*x""#);
+            assert!(extensions.contains("body"));
+        }
+
+        #[test]
+        fn test_format_matchers() {
+            let mut extensions = BTreeSet::new();
+            let mut test = |when: When| format_test(&when, &mut extensions).unwrap();
+
+            assert_eq!(
+                test(When::DoesNotContain(DoesNotContain {
+                    field: Field::From,
+                    values: vec!["bot".to_owned(), "noreply".to_owned()],
+                })),
+                r#"not header :contains "from" ["bot", "noreply"]"#
+            );
+            assert_eq!(
+                test(When::StartsWith(StartsWith {
+                    field: Field::Subject,
+                    values: vec!["[ci]?".to_owned()],
+                })),
+                r#"header :matches "subject" "[ci]\\?*""#
+            );
+            assert_eq!(
+                test(When::MatchesGlob(MatchesGlob {
+                    field: Field::From,
+                    values: vec!["build-*@example.com".to_owned()],
+                })),
+                r#"address :matches "from" "build-*@example.com""#
+            );
+            assert_eq!(
+                test(When::MatchesGlob(MatchesGlob {
+                    field: Field::Subject,
+                    values: vec![r"a\b*".to_owned()],
+                })),
+                r#"header :matches "subject" "a\\\\b*""#
+            );
+            assert_eq!(
+                test(When::MatchesRegex(MatchesRegex {
+                    field: Field::Subject,
+                    values: vec!["^v[0-9]+$".to_owned()],
+                })),
+                r#"header :regex "subject" "^v[0-9]+$""#
+            );
+            assert!(extensions.contains("regex"));
+        }
+
+        #[test]
+        fn test_format_nested_groups() {
+            let when = When::Not(Not {
+                condition: Box::new(When::Any(Any {
+                    conditions: vec![
+                        When::Contains(Contains {
+                            field: Field::From,
+                            values: vec!["bot".to_owned()],
+                        }),
+                        When::All(All {
+                            conditions: vec![When::StartsWith(StartsWith {
+                                field: Field::Subject,
+                                values: vec!["[ci]".to_owned()],
+                            })],
+                        }),
+                    ],
+                })),
+            });
+
+            assert_eq!(
+                format_test(&when, &mut BTreeSet::new()).unwrap(),
+                r#"not anyof (header :contains "from" "bot", header :matches "subject" "[ci]*")"#
+            );
+            assert_eq!(
+                format_group(Grouping::All, vec!["true".to_owned(), "false".to_owned()]),
+                "allof (true, false)"
+            );
+            assert_eq!(format_group(Grouping::All, Vec::new()), "true");
+            assert_eq!(
+                format_test(
+                    &When::Any(Any {
+                        conditions: Vec::new()
+                    }),
+                    &mut BTreeSet::new()
+                )
+                .unwrap(),
+                "false"
+            );
+        }
+
+        #[test]
+        fn test_format_forward() {
+            let forward = Then::Forward(Forward {
+                to: "jane@example.com".to_owned(),
+            });
+            assert_eq!(
+                format_action("/", &forward, &mut BTreeSet::new()).as_deref(),
+                Some(r#"redirect "jane@example.com""#)
+            );
+        }
+
+        #[test]
+        fn test_format_folder_with_separator() {
+            assert_eq!(format_folder(".", "Lists/Rust"), "Lists.Rust");
+        }
+
+        use super::*;
+        use crate::configuration::{
+            All, Any, Contains, DoesNotContain, EndsWith, Forward, MatchesGlob, MatchesRegex, Not,
+            StartsWith,
+        };
+    }
+
+    use std::collections::BTreeSet;
+
+    use crate::configuration::{
+        ByteSize, DateComparison, Field, Grouping, Matcher, Priority, SimpleAction, SizeComparison,
+        StateTest, Status, Then, When,
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_print_config() {
+        let config = Configuration {
+            accounts: vec![Account {
+                account: None,
+                email: None,
+                imap_host: None,
+                evolution_id: Some("evolution".to_owned()),
+                thunderbird_id: Some("thunderbird".to_owned()),
+                thunderbird_path: None,
+                sieve_separator: Some(".".to_owned()),
+                templates: BTreeMap::new(),
+                message_filters: vec![
+                    MessageFilter {
+                        title: "Github".to_owned(),
+                        grouping: Grouping::Any,
+                        description: None,
+                        enabled: true,
+                        run_on: vec![RunOn::Incoming, RunOn::Manual],
+                        when: vec![When::EndsWith(EndsWith {
+                            field: Field::From,
+                            values: vec![
+                                "@github.com".to_owned(),
+                                "@noreply.github.com".to_owned(),
+                            ],
+                        })],
+                        then: vec![Then::MoveTo(MoveTo {
+                            folder: "Dev/Github".to_owned(),
+                        })],
+                    },
+                    MessageFilter {
+                        title: "Amazon".to_owned(),
+                        grouping: Grouping::Any,
+                        description: None,
+                        enabled: true,
+                        run_on: vec![RunOn::Incoming, RunOn::Manual],
+                        when: vec![
+                            When::Contains(Contains {
+                                field: Field::From,
+                                values: vec!["@amazon.".to_owned()],
+                            }),
+                            When::EndsWith(EndsWith {
+                                field: Field::From,
+                                values: vec!["@aws.com".to_owned()],
+                            }),
+                        ],
+                        then: vec![Then::MoveTo(MoveTo {
+                            folder: "amzn".to_owned(),
+                        })],
+                    },
+                ],
+            }],
+        };
+
+        assert_eq!(
+            print_config(config).unwrap(),
+            [
+                "require \"fileinto\";",
+                "",
+                "# rule:[Github]",
+                "if address :matches \"from\" [\"*@github.com\", \"*@noreply.github.com\"] {",
+                "    fileinto \"Dev.Github\";",
+                "}",
+                "",
+                "# rule:[Amazon]",
+                "if anyof (header :contains \"from\" \"@amazon.\", address :matches \"from\" \"*@aws.com\") {",
+                "    fileinto \"amzn\";",
+                "}",
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test]
+    fn test_print_config_with_all_actions() {
+        let source = "\
+- evolution_id: evolution
+  thunderbird_id: thunderbird
+  message_filters:
+    - title: Newsletters
+      when:
+        - field: list_id
+          contains: [news]
+      then:
+        - copy_to: Archive/News
+        - mark_read
+        - flag
+        - tag: $label1
+        - mark_junk
+        - mark_not_junk
+        - mark_unread
+        - delete
+        - stop_processing
+";
+        let config = crate::configuration::parse(source).unwrap();
+
+        assert_eq!(
+            print_config(config).unwrap(),
+            [
+                "require [\"copy\", \"fileinto\", \"imap4flags\"];",
+                "",
+                "# rule:[Newsletters]",
+                "if header :contains \"list-id\" \"news\" {",
+                "    fileinto :copy \"Archive/News\";",
+                "    addflag \"\\\\Seen\";",
+                "    addflag \"\\\\Flagged\";",
+                "    addflag \"$label1\";",
+                "    addflag \"$Junk\";",
+                "    addflag \"$NotJunk\";",
+                "    removeflag \"\\\\Seen\";",
+                "    discard;",
+                "    stop;",
+                "}",
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test]
+    fn test_print_config_disabled_with_description() {
+        let source = "\
+- thunderbird_id: thunderbird
+  message_filters:
+    - title: Drafts
+      description: |-
+        Not ready yet.
+        Needs review.
+      enabled: false
+      when:
+        - field: subject
+          contains: [draft]
+      then:
+        - delete
+";
+        let config = crate::configuration::parse(source).unwrap();
+
+        assert_eq!(
+            print_config(config).unwrap(),
+            [
+                "# rule:[Drafts]",
+                "# Not ready yet.",
+                "# Needs review.",
+                "if false # header :contains \"subject\" \"draft\"",
+                "{",
+                "    discard;",
+                "}",
+            ]
+            .join("\n")
+        );
+
+        let source = source.replace("enabled: false", "run_on: [manual]");
+        let config = crate::configuration::parse(&source).unwrap();
+        assert_eq!(
+            print_config(config).unwrap_err().to_string(),
+            "filter 'Drafts': Sieve scripts only run on incoming mail"
+        );
+    }
+
+    #[test]
+    fn test_print_config_with_state_conditions() {
+        let source = "\
+- evolution_id: evolution
+  thunderbird_id: thunderbird
+  message_filters:
+    - title: Old
+      match: all
+      when:
+        - size: { greater_than: 5MB }
+        - date: { on: 2026-01-31 }
+        - not:
+            status: read
+        - has_attachment: true
+        - junk: false
+        - tag: $label1
+      then:
+        - delete
+";
+        let config = crate::configuration::parse(source).unwrap();
+
+        assert_eq!(
+            print_config(config).unwrap(),
+            [
+                "require [\"date\", \"imap4flags\", \"mime\"];",
+                "",
+                "# rule:[Old]",
+                "if allof (size :over 5M, date :is \"date\" \"date\" \"2026-01-31\", \
+                 not hasflag \"\\\\Seen\", \
+                 header :mime :anychild :contains \"Content-Disposition\" \"attachment\", \
+                 not hasflag \"$Junk\", hasflag \"$label1\") {",
+                "    discard;",
+                "}",
+            ]
+            .join("\n")
+        );
+
+        let source = source.replace("- junk: false", "- age_days: { more_than: 30 }");
+        let config = crate::configuration::parse(&source).unwrap();
+        assert_eq!(
+            print_config(config).unwrap_err().to_string(),
+            "filter 'Old': Sieve scripts cannot represent `age_days`"
+        );
+    }
+
+    #[test]
+    fn test_print_config_rejects_titles_ending_the_comment() {
+        // Written as YAML double-quoted strings.
+        for title in [r"x\ny", r"x\ndiscard;", r"x\ry", "[x]"] {
+            let source = format!(
+                "\
+- thunderbird_id: thunderbird
+  message_filters:
+    - title: \"{title}\"
+      when:
+        - field: subject
+          contains: [draft]
+      then:
+        - delete
+"
+            );
+            let config = crate::configuration::parse(&source).unwrap();
+            let err = print_config(config).unwrap_err();
+            assert!(
+                err.to_string()
+                    .ends_with("titles cannot contain line breaks or `]` in Sieve scripts"),
+                "printing {title}: {err}"
+            );
+        }
+    }
+
+    use std::collections::BTreeMap;
+
+    use super::*;
+    use crate::configuration::{
+        Account, Contains, EndsWith, Field, Grouping, MessageFilter, MoveTo, RunOn, Then, When,
+    };
+}
+
+use std::collections::BTreeSet;
+
+use crate::configuration::{Configuration, RunOn};
@@ -1,87 +1,423 @@
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
-    #[error("thunderbird_id is missing")]
+    #[error("thunderbird_id is missing, set it or `email` and `imap_host`")]
     MissingThunderbirdId,
+    #[error("thunderbird_id is empty")]
+    EmptyThunderbirdId,
+    #[error("the folder name is empty")]
+    EmptyFolder,
+    #[error("multiple accounts would be written to {0}")]
+    DuplicatePath(PathBuf),
+    #[error("{0} is not a relative path inside the output directory")]
+    OutsidePath(PathBuf),
+    #[error("Thunderbird filters cannot represent `{0}`")]
+    UnsupportedCondition(String),
+    #[error("Thunderbird filters cannot represent `{0}`")]
+    UnsupportedAction(&'static str),
+    #[error("the conditions match no message, which Thunderbird filters cannot represent")]
+    MatchesNothing,
+    #[error("unknown template `{0}`, add it to the account's `templates`")]
+    UnknownTemplate(String),
+    #[error(transparent)]
+    Condition(#[from] ConditionError),
+    #[error(transparent)]
+    Parse(#[from] ParseError),
+    #[error("filter '{filter}': {source}")]
+    Filter {
+        filter: String,
+        #[source]
+        source: Box<Error>,
+    },
 }
 
-pub fn print_config(config: Configuration) -> Result<String> {
+pub fn print_config(config: Configuration) -> Result<String, Error> {
     let mut document = DatDocument::new(9, false);
 
     for account in config.accounts {
-        let thunderbird_id = account.thunderbird_id.ok_or(Error::MissingThunderbirdId)?;
-
-        for message_filter in account.message_filters {
-            for action in message_filter.then {
-                match action {
-                    Then::MoveTo(move_to) => {
-                        let move_to =
-                            helpers::format_folder("imap", &thunderbird_id, &move_to.folder);
-
-                        helpers::append_filter(
-                            &mut document,
-                            &message_filter.title,
-                            &move_to,
-                            &message_filter.when,
-                        )
-                    }
-                }
+        append_account(&mut document, account)?;
+    }
+
+    Ok(document.to_string())
+}
+
+/// Prints one `msgFilterRules.dat` per account, paired with the path it should
+/// be written to relative to the output directory.
+pub fn print_accounts(config: Configuration) -> Result<Vec<(PathBuf, String)>, Error> {
+    let mut files: Vec<(PathBuf, String)> = Vec::new();
+
+    for account in config.accounts {
+        let path = helpers::format_path(&account)?;
+        if files.iter().any(|(other, _)| *other == path) {
+            return Err(Error::DuplicatePath(path));
+        }
+
+        let mut document = DatDocument::new(9, false);
+        append_account(&mut document, account)?;
+        files.push((path, document.to_string()));
+    }
+
+    Ok(files)
+}
+
+/// Appended to the names of the filters written by [`merge_config`], which replaces exactly
+/// these filters on the next merge.
+pub const MANAGED_MARKER: &str = " [mail-message-filters]";
+
+/// Prints the configuration into the existing filter list `existing`, keeping the filters
+/// created in Thunderbird and their order.
+///
+/// Filters named with [`MANAGED_MARKER`] are replaced by the configured filters, at the
+/// position of the first one. If there is none, the configured filters are added at the end.
+/// Other filters are kept even if they have the title of a configured filter.
+pub fn merge_config(config: Configuration, existing: &str) -> Result<String, Error> {
+    let (header, filters) = dat::split_filters(dat::parse(existing)?);
+
+    let mut managed = helpers::new_document(&header);
+    for mut account in config.accounts {
+        for message_filter in &mut account.message_filters {
+            message_filter.title.push_str(MANAGED_MARKER);
+        }
+        append_account(&mut managed, account)?;
+    }
+
+    let mut document = helpers::new_document(&header);
+    let mut managed = Some(managed);
+    for entries in filters {
+        if entries[0].value.ends_with(MANAGED_MARKER) {
+            if let Some(managed) = managed.take() {
+                document.extend(managed);
             }
+            continue;
+        }
+        for entry in entries {
+            document.append(&entry.name, entry.value);
         }
     }
+    if let Some(managed) = managed {
+        document.extend(managed);
+    }
 
     Ok(document.to_string())
 }
 
+/// Returns the filters of `existing` that [`merge_config`] would replace, as a filter list.
+pub(crate) fn managed_filters(existing: &str) -> Result<String, Error> {
+    let (header, filters) = dat::split_filters(dat::parse(existing)?);
+
+    let mut document = helpers::new_document(&header);
+    for entries in filters {
+        if entries[0].value.ends_with(MANAGED_MARKER) {
+            for entry in entries {
+                document.append(&entry.name, entry.value);
+            }
+        }
+    }
+
+    Ok(document.to_string())
+}
+
+/// Returns the names of the filters of `existing` that [`merge_config`] keeps as they are.
+pub(crate) fn unmanaged_filters(existing: &str) -> Result<Vec<String>, Error> {
+    let (_, filters) = dat::split_filters(dat::parse(existing)?);
+
+    Ok(filters
+        .into_iter()
+        .map(|mut entries| entries.swap_remove(0).value)
+        .filter(|name| !name.ends_with(MANAGED_MARKER))
+        .collect())
+}
+
+fn append_account(document: &mut DatDocument, account: Account) -> Result<(), Error> {
+    let thunderbird_id = resolver::thunderbird_id(&account).ok_or(Error::MissingThunderbirdId)?;
+
+    for message_filter in account.message_filters {
+        let actions = message_filter
+            .then
+            .iter()
+            .map(|action| helpers::format_action(&thunderbird_id, &account.templates, action))
+            .collect::<Result<Vec<_>, Error>>();
+
+        actions
+            .and_then(|actions| helpers::append_filter(document, &message_filter, &actions))
+            .map_err(|err| Error::Filter {
+                filter: message_filter.title.clone(),
+                source: Box::new(err),
+            })?;
+    }
+
+    Ok(())
+}
+
 mod helpers {
+    /// Starts a filter list with the `version` and `logging` of `header`.
+    pub fn new_document(header: &[DatEntry]) -> DatDocument {
+        let value = |name: &str| {
+            header
+                .iter()
+                .find(|entry| entry.name == name)
+                .map(|entry| entry.value.as_str())
+        };
+        let version = value("version")
+            .and_then(|version| version.parse().ok())
+            .unwrap_or(9);
+        DatDocument::new(version, value("logging") == Some("yes"))
+    }
+
+    /// A filter action, written as `action=` followed by an optional `actionValue=`.
+    pub struct Action {
+        pub name: &'static str,
+        pub value: Option<String>,
+    }
+
+    /// Returns the action for `action`, looking up `reply_with` templates in `templates`.
+    pub fn format_action(
+        account: &str,
+        templates: &BTreeMap<String, String>,
+        action: &Then,
+    ) -> Result<Action, Error> {
+        let (name, value) = match action {
+            Then::MoveTo(move_to) => (
+                "Move to folder",
+                Some(format_folder("imap", account, &move_to.folder)?),
+            ),
+            Then::CopyTo(copy_to) => (
+                "Copy to folder",
+                Some(format_folder("imap", account, &copy_to.folder)?),
+            ),
+            Then::Tag(tag) => ("AddTag", Some(tag.tag.clone())),
+            Then::SetPriority(set_priority) => (
+                "Change priority",
+                Some(format_priority(set_priority.priority).to_owned()),
+            ),
+            Then::Forward(forward) => ("Forward", Some(forward.to.clone())),
+            Then::ReplyWith(reply_with) => {
+                let uri = templates
+                    .get(&reply_with.template)
+                    .ok_or_else(|| Error::UnknownTemplate(reply_with.template.clone()))?;
+                ("Reply", Some(uri.clone()))
+            }
+            Then::RunProgram(_) => return Err(Error::UnsupportedAction(action.key())),
+            Then::Simple(action) => match action {
+                SimpleAction::Delete => ("Delete", None),
+                SimpleAction::MarkRead => ("Mark read", None),
+                SimpleAction::MarkUnread => ("Mark unread", None),
+                SimpleAction::Flag => ("Mark flagged", None),
+                SimpleAction::MarkJunk => ("JunkScore", Some("100".to_owned())),
+                SimpleAction::MarkNotJunk => ("JunkScore", Some("0".to_owned())),
+                SimpleAction::StopProcessing => ("Stop execution", None),
+            },
+        };
+        Ok(Action { name, value })
+    }
+
+    fn format_priority(priority: Priority) -> &'static str {
+        match priority {
+            Priority::Lowest => "Lowest",
+            Priority::Low => "Low",
+            Priority::Normal => "Normal",
+            Priority::High => "High",
+            Priority::Highest => "Highest",
+        }
+    }
 
-    pub fn append_filter(doc: &mut DatDocument, name: &str, move_to: &str, when: &[When]) {
-        doc.append("name", name);
-        doc.append("enabled", "yes");
-        doc.append("type", "17");
-        doc.append("action", "Move to folder");
-        doc.append("actionValue", move_to);
-        doc.append("condition", format_condition(when));
+    pub fn append_filter(
+        doc: &mut DatDocument,
+        filter: &MessageFilter,
+        actions: &[Action],
+    ) -> Result<(), Error> {
+        let condition = format_condition(filter.grouping, &filter.when)?;
+
+        doc.append("name", &filter.title);
+        doc.append("enabled", if filter.enabled { "yes" } else { "no" });
+        if let Some(description) = &filter.description {
+            doc.append("description", description);
+        }
+        doc.append("type", format_type(&filter.run_on));
+        for action in actions {
+            doc.append("action", action.name);
+            if let Some(value) = &action.value {
+                doc.append("actionValue", value);
+            }
+        }
+        doc.append("condition", condition);
+        Ok(())
     }
 
-    fn format_condition(when: &[When]) -> String {
-        let inner: Vec<String> = when
+    /// Returns the `nsMsgFilterType` bitmask for `run_on`.
+    pub fn format_type(run_on: &[RunOn]) -> u32 {
+        run_on
             .iter()
-            .map(|cond| match cond {
-                When::Contains(cond) => {
-                    let prefix: &'static str = if cond.values.len() == 1 { "AND" } else { "OR" };
-                    let field = format_field(&cond.field);
-                    cond.values
-                        .iter()
-                        .map(move |value| format!("{} ({},contains,{})", prefix, field, value))
-                        .collect::<Vec<_>>()
-                        .join(" ")
+            .map(|run_on| match run_on {
+                RunOn::Incoming => 0x1,
+                RunOn::Manual => 0x10,
+                RunOn::AfterSending => 0x40,
+                RunOn::Archiving => 0x80,
+                RunOn::Periodic => 0x100,
+            })
+            .fold(0, |mask, flag| mask | flag)
+    }
+
+    /// Formats the conditions as a search term, which combines its terms either all with `AND`
+    /// or all with `OR`. An empty `all` matches every message and is written as `ALL`, while
+    /// an empty `any` matches none, which Thunderbird cannot express.
+    fn format_condition(grouping: Grouping, when: &[When]) -> Result<String, Error> {
+        let flat = condition::flatten(grouping, when)?;
+        match (flat.grouping, flat.tests.is_empty()) {
+            (Grouping::All, true) => return Ok("ALL".to_owned()),
+            (Grouping::Any, true) => return Err(Error::MatchesNothing),
+            (_, false) => {}
+        }
+        let prefix = match (flat.grouping, flat.tests.len()) {
+            (Grouping::All, _) | (Grouping::Any, 1) => "AND",
+            (Grouping::Any, _) => "OR",
+        };
+
+        let mut inner = Vec::new();
+        for test in &flat.tests {
+            let (attribute, operator, value) =
+                format_term(test).ok_or_else(|| Error::UnsupportedCondition(test.key()))?;
+            inner.push(format!(
+                "{prefix} ({attribute},{operator},{})",
+                format_value(&value)
+            ));
+        }
+
+        Ok(inner.join(" "))
+    }
+
+    /// Returns the search attribute, operator and value for `test`, `None` if Thunderbird
+    /// cannot express it. Sizes are compared in kilobytes.
+    fn format_term(test: &Test) -> Option<(String, &'static str, String)> {
+        let (is, negated) = (if test.negated { "isn't" } else { "is" }, test.negated);
+
+        let (attribute, operator, value) = match test.predicate {
+            Predicate::String {
+                field,
+                matcher,
+                value,
+            } => {
+                let operator = format_operator(matcher, negated)?;
+                return Some((format_field(field), operator, value.to_owned()));
+            }
+            Predicate::State(state) => match (state, negated) {
+                (StateTest::Size(SizeComparison::GreaterThan(size)), false) => {
+                    ("size", "is greater than", size.kilobytes().to_string())
                 }
-                When::EndsWith(cond) => {
-                    let prefix: &'static str = if cond.values.len() == 1 { "AND" } else { "OR" };
-                    let field = format_field(&cond.field);
-                    cond.values
-                        .iter()
-                        .map(move |value| format!("{} ({},ends with,{})", prefix, field, value))
-                        .collect::<Vec<_>>()
-                        .join(" ")
+                (StateTest::Size(SizeComparison::LessThan(size)), false) => {
+                    ("size", "is less than", size.kilobytes().to_string())
                 }
-            })
-            .collect();
+                (StateTest::AgeDays(AgeComparison::MoreThan(days)), false) => {
+                    ("age in days", "is greater than", days.to_string())
+                }
+                (StateTest::AgeDays(AgeComparison::LessThan(days)), false) => {
+                    ("age in days", "is less than", days.to_string())
+                }
+                (StateTest::Date(DateComparison::Before(date)), false) => {
+                    ("date", "is before", format_date(date))
+                }
+                (StateTest::Date(DateComparison::After(date)), false) => {
+                    ("date", "is after", format_date(date))
+                }
+                (StateTest::Date(DateComparison::On(date)), _) => ("date", is, format_date(date)),
+                (StateTest::Status(status), _) => {
+                    let status = match status {
+                        Status::Read => "read",
+                        Status::Flagged => "flagged",
+                        Status::Replied => "replied",
+                    };
+                    ("status", is, status.to_owned())
+                }
+                (StateTest::HasAttachment, _) => ("has attachment status", is, "true".to_owned()),
+                (StateTest::Priority(priority), _) => {
+                    ("priority", is, format_priority(priority).to_owned())
+                }
+                (StateTest::Junk, _) => ("junk status", is, "2".to_owned()),
+                (StateTest::Tag(tag), false) => ("tag", "contains", tag.to_owned()),
+                (StateTest::Tag(tag), true) => ("tag", "doesn't contain", tag.to_owned()),
+                (StateTest::Size(_) | StateTest::AgeDays(_) | StateTest::Date(_), true) => {
+                    return None
+                }
+            },
+        };
+        Some((attribute.to_owned(), operator, value))
+    }
 
-        inner.join(" ")
+    /// Formats `date` like Thunderbird's search terms, e.g. `01-Jan-2026`.
+    fn format_date(date: Date) -> String {
+        const MONTHS: [&str; 12] = [
+            "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+        ];
+        let month = MONTHS[(date.month as usize).saturating_sub(1) % 12];
+        format!("{:02}-{month}-{}", date.day, date.year)
     }
 
-    pub fn format_folder(scheme: &str, account: &str, folder: &str) -> String {
-        assert!(!scheme.is_empty());
-        assert!(!account.is_empty());
-        assert!(!folder.is_empty());
+    /// Returns the operator for a positive `matcher`, negated if `negated` is set.
+    pub fn format_operator(matcher: Matcher, negated: bool) -> Option<&'static str> {
+        let operator = match (matcher, negated) {
+            (Matcher::Contains, false) => "contains",
+            (Matcher::Contains, true) => "doesn't contain",
+            (Matcher::Is, false) => "is",
+            (Matcher::Is, true) => "isn't",
+            (Matcher::StartsWith, false) => "begins with",
+            (Matcher::EndsWith, false) => "ends with",
+            _ => return None,
+        };
+        Some(operator)
+    }
+
+    /// Wraps `value` in quotes where Thunderbird's `nsMsgSearchTerm::OutputValue` does: when it
+    /// has a `)` or starts with a space or a quote. Only quotes are escaped, with a backslash.
+    pub fn format_value(value: &str) -> String {
+        match value.contains(')') || value.starts_with([' ', '"']) {
+            true => format!("\"{}\"", value.replace('"', "\\\"")),
+            false => value.to_owned(),
+        }
+    }
 
-        format!("{scheme}://{account}/{folder}")
+    /// Returns the URI of `folder` in the account `account`, e.g. `imap://home/Archive`.
+    pub fn format_folder(scheme: &str, account: &str, folder: &str) -> Result<String, Error> {
+        match (account.is_empty(), folder.is_empty()) {
+            (true, _) => Err(Error::EmptyThunderbirdId),
+            (_, true) => Err(Error::EmptyFolder),
+            _ => Ok(format!("{scheme}://{account}/{folder}")),
+        }
     }
 
+    /// Returns the search attribute for `field`. Headers without a built-in attribute are
+    /// written as quoted custom headers, which must also be listed in Thunderbird's
+    /// `mailnews.customHeaders` preference.
     pub fn format_field(field: &Field) -> String {
         match field {
             Field::From => "from".to_owned(),
+            Field::To => "to".to_owned(),
+            Field::Cc => "cc".to_owned(),
+            Field::ToOrCc => "to or cc".to_owned(),
+            Field::Subject => "subject".to_owned(),
+            Field::Body => "body".to_owned(),
+            Field::Bcc | Field::ReplyTo | Field::ListId | Field::Sender | Field::Header(_) => {
+                format!("\"{}\"", field.header_name().unwrap_or_default())
+            }
+        }
+    }
+
+    /// Returns `thunderbird_path` if set, `<thunderbird_id>/msgFilterRules.dat` otherwise,
+    /// failing unless it is a relative path without `..`.
+    pub fn format_path(account: &Account) -> Result<PathBuf, Error> {
+        let path = match &account.thunderbird_path {
+            Some(path) => PathBuf::from(path),
+            None => {
+                let thunderbird_id =
+                    resolver::thunderbird_id(account).ok_or(Error::MissingThunderbirdId)?;
+                Path::new(&thunderbird_id).join("msgFilterRules.dat")
+            }
+        };
+
+        match path
+            .components()
+            .all(|component| matches!(component, Component::Normal(_) | Component::CurDir))
+        {
+            true => Ok(path),
+            false => Err(Error::OutsidePath(path)),
         }
     }
 
@@ -90,10 +426,14 @@ mod helpers {
         #[test]
         fn test_format_one_condition() {
             assert_eq!(
-                format_condition(&[When::EndsWith(EndsWith {
-                    field: Field::From,
-                    values: vec!["@example.com".to_owned()]
-                })]),
+                format_condition(
+                    Grouping::Any,
+                    &[When::EndsWith(EndsWith {
+                        field: Field::From,
+                        values: vec!["@example.com".to_owned()]
+                    })]
+                )
+                .unwrap(),
                 "AND (from,ends with,@example.com)"
             );
         }
@@ -101,21 +441,163 @@ mod helpers {
         #[test]
         fn test_format_multiple_conditions() {
             assert_eq!(
-                format_condition(&[When::EndsWith(EndsWith {
+                format_condition(
+                    Grouping::Any,
+                    &[When::EndsWith(EndsWith {
+                        field: Field::From,
+                        values: vec!["@example.com".to_owned(), "@test.com".to_owned()]
+                    })]
+                )
+                .unwrap(),
+                "OR (from,ends with,@example.com) OR (from,ends with,@test.com)"
+            );
+        }
+
+        #[test]
+        fn test_format_no_conditions() {
+            let nested = |when| {
+                When::All(All {
+                    conditions: vec![when],
+                })
+            };
+            assert_eq!(format_condition(Grouping::All, &[]).unwrap(), "ALL");
+            assert_eq!(
+                format_condition(
+                    Grouping::All,
+                    &[nested(When::All(All {
+                        conditions: Vec::new()
+                    }))]
+                )
+                .unwrap(),
+                "ALL"
+            );
+            assert!(matches!(
+                format_condition(
+                    Grouping::All,
+                    &[nested(When::Any(Any {
+                        conditions: Vec::new()
+                    }))]
+                ),
+                Err(Error::MatchesNothing)
+            ));
+            assert!(matches!(
+                format_condition(Grouping::Any, &[]),
+                Err(Error::MatchesNothing)
+            ));
+        }
+
+        #[test]
+        fn test_format_matchers() {
+            let when = [
+                When::StartsWith(StartsWith {
+                    field: Field::Subject,
+                    values: vec!["[ci]".to_owned()],
+                }),
+                When::DoesNotContain(DoesNotContain {
                     field: Field::From,
-                    values: vec!["@example.com".to_owned(), "@test.com".to_owned()]
+                    values: vec!["bot".to_owned(), "noreply".to_owned()],
+                }),
+            ];
+            assert_eq!(
+                format_condition(Grouping::All, &when).unwrap(),
+                "AND (subject,begins with,[ci]) AND (from,doesn't contain,bot) AND (from,doesn't contain,noreply)"
+            );
+            assert!(matches!(
+                format_condition(Grouping::Any, &when),
+                Err(Error::Condition(condition::Error::MixedGrouping))
+            ));
+            assert!(matches!(
+                format_condition(Grouping::Any, &[When::MatchesRegex(MatchesRegex {
+                    field: Field::Subject,
+                    values: vec!["^v[0-9]+".to_owned()]
                 })]),
-                "OR (from,ends with,@example.com) OR (from,ends with,@test.com)"
+                Err(Error::UnsupportedCondition(condition)) if condition == "matches_regex"
+            ));
+        }
+
+        #[test]
+        fn test_format_custom_header() {
+            assert_eq!(
+                format_condition(
+                    Grouping::Any,
+                    &[When::Contains(Contains {
+                        field: Field::Header("X-Mailer".to_owned()),
+                        values: vec!["Mutt".to_owned()]
+                    })]
+                )
+                .unwrap(),
+                "AND (\"X-Mailer\",contains,Mutt)"
+            );
+            assert_eq!(format_field(&Field::ListId), "\"List-Id\"");
+            assert_eq!(format_field(&Field::ToOrCc), "to or cc");
+        }
+
+        #[test]
+        fn test_format_type() {
+            assert_eq!(format_type(&[RunOn::Incoming, RunOn::Manual]), 17);
+            assert_eq!(format_type(&[RunOn::Manual]), 16);
+            assert_eq!(
+                format_type(&[RunOn::AfterSending, RunOn::Periodic, RunOn::Archiving]),
+                448
+            );
+        }
+
+        #[test]
+        fn test_format_awkward_values() {
+            let corpus = [
+                ("plain@example.com", "plain@example.com"),
+                ("Doe, John", "Doe, John"),
+                ("John Doe <jdoe@example.com>", "John Doe <jdoe@example.com>"),
+                ("(bounce)", "\"(bounce)\""),
+                ("\"Quoted\" Name", "\"\\\"Quoted\\\" Name\""),
+                ("Name \"Quoted\"", "Name \"Quoted\""),
+                ("back\\slash", "back\\slash"),
+                ("back\\slash)", "\"back\\slash)\""),
+                (" leading", "\" leading\""),
+                ("", ""),
+            ];
+
+            for (value, expected) in corpus {
+                assert_eq!(format_value(value), expected, "formatting {value:?}");
+            }
+        }
+
+        #[test]
+        fn test_format_condition_quotes_values() {
+            assert_eq!(
+                format_condition(
+                    Grouping::Any,
+                    &[When::Contains(Contains {
+                        field: Field::From,
+                        values: vec!["Doe, John (work)".to_owned()]
+                    })]
+                )
+                .unwrap(),
+                "AND (from,contains,\"Doe, John (work)\")"
             );
         }
 
         use super::*;
-        use crate::configuration::{EndsWith, Field};
+        use crate::configuration::{
+            All, Any, Contains, DoesNotContain, EndsWith, Field, MatchesRegex, StartsWith,
+        };
     }
 
+    use std::{
+        collections::BTreeMap,
+        path::{Component, Path, PathBuf},
+    };
+
+    use super::Error;
     use crate::{
-        configuration::{Field, When},
-        dat::DatDocument,
+        condition,
+        condition::{Predicate, Test},
+        configuration::{
+            Account, AgeComparison, Date, DateComparison, Field, Grouping, Matcher, MessageFilter,
+            Priority, RunOn, SimpleAction, SizeComparison, StateTest, Status, Then, When,
+        },
+        dat::{DatDocument, DatEntry},
+        resolver,
     };
 }
 
@@ -125,11 +607,21 @@ mod tests {
     fn test_print_config() {
         let config = Configuration {
             accounts: vec![Account {
+                account: None,
+                email: None,
+                imap_host: None,
                 evolution_id: Some("evolution".to_owned()),
                 thunderbird_id: Some("thunderbird".to_owned()),
+                thunderbird_path: None,
+                sieve_separator: None,
+                templates: BTreeMap::new(),
                 message_filters: vec![
                     MessageFilter {
                         title: "DigitalOcean".to_owned(),
+                        grouping: Grouping::Any,
+                        description: None,
+                        enabled: true,
+                        run_on: vec![RunOn::Incoming, RunOn::Manual],
                         when: vec![When::EndsWith(EndsWith {
                             field: Field::From,
                             values: vec!["@digitalocean.com".to_owned()],
@@ -140,6 +632,10 @@ mod tests {
                     },
                     MessageFilter {
                         title: "Amazon".to_owned(),
+                        grouping: Grouping::Any,
+                        description: None,
+                        enabled: true,
+                        run_on: vec![RunOn::Incoming, RunOn::Manual],
                         when: vec![When::Contains(Contains {
                             field: Field::From,
                             values: vec!["@amazon.".to_owned()],
@@ -174,14 +670,471 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_print_config_with_multiple_actions() {
+        let config = Configuration {
+            accounts: vec![Account {
+                account: None,
+                email: None,
+                imap_host: None,
+                evolution_id: None,
+                thunderbird_id: Some("thunderbird".to_owned()),
+                thunderbird_path: None,
+                sieve_separator: None,
+                templates: BTreeMap::new(),
+                message_filters: vec![MessageFilter {
+                    title: "Amazon".to_owned(),
+                    grouping: Grouping::Any,
+                    description: None,
+                    enabled: true,
+                    run_on: vec![RunOn::Incoming, RunOn::Manual],
+                    when: vec![When::Contains(Contains {
+                        field: Field::From,
+                        values: vec!["@amazon.".to_owned()],
+                    })],
+                    then: vec![
+                        Then::MoveTo(MoveTo {
+                            folder: "amzn".to_owned(),
+                        }),
+                        Then::MoveTo(MoveTo {
+                            folder: "shopping".to_owned(),
+                        }),
+                    ],
+                }],
+            }],
+        };
+
+        assert_eq!(
+            print_config(config).unwrap(),
+            [
+                "version=\"9\"",
+                "logging=\"no\"",
+                "name=\"Amazon\"",
+                "enabled=\"yes\"",
+                "type=\"17\"",
+                "action=\"Move to folder\"",
+                "actionValue=\"imap://thunderbird/amzn\"",
+                "action=\"Move to folder\"",
+                "actionValue=\"imap://thunderbird/shopping\"",
+                "condition=\"AND (from,contains,@amazon.)\""
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test]
+    fn test_print_config_with_all_actions() {
+        let source = "\
+- evolution_id: evolution
+  thunderbird_id: thunderbird
+  message_filters:
+    - title: Newsletters
+      when:
+        - field: list_id
+          contains: [news]
+      then:
+        - copy_to: Archive/News
+        - mark_read
+        - flag
+        - tag: $label1
+        - mark_junk
+        - mark_not_junk
+        - mark_unread
+        - delete
+        - set_priority: highest
+        - stop_processing
+";
+        let config = crate::configuration::parse(source).unwrap();
+
+        assert_eq!(
+            print_config(config).unwrap(),
+            [
+                "version=\"9\"",
+                "logging=\"no\"",
+                "name=\"Newsletters\"",
+                "enabled=\"yes\"",
+                "type=\"17\"",
+                "action=\"Copy to folder\"",
+                "actionValue=\"imap://thunderbird/Archive/News\"",
+                "action=\"Mark read\"",
+                "action=\"Mark flagged\"",
+                "action=\"AddTag\"",
+                "actionValue=\"$label1\"",
+                "action=\"JunkScore\"",
+                "actionValue=\"100\"",
+                "action=\"JunkScore\"",
+                "actionValue=\"0\"",
+                "action=\"Mark unread\"",
+                "action=\"Delete\"",
+                "action=\"Change priority\"",
+                "actionValue=\"Highest\"",
+                "action=\"Stop execution\"",
+                "condition=\"AND (\\\"List-Id\\\",contains,news)\"",
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test]
+    fn test_print_config_with_forward_and_reply() {
+        let source = "\
+- evolution_id: evolution
+  thunderbird_id: thunderbird
+  templates:
+    thanks: mailbox://nobody@Local%20Folders/Templates?messageId=1
+  message_filters:
+    - title: Invoices
+      when:
+        - field: subject
+          contains: [invoice]
+      then:
+        - forward: accounting@example.com
+        - reply_with: thanks
+";
+        let config = crate::configuration::parse(source).unwrap();
+        let output = print_config(config).unwrap();
+        assert!(output.contains(
+            "action=\"Forward\"\nactionValue=\"accounting@example.com\"\n\
+             action=\"Reply\"\nactionValue=\"mailbox://nobody@Local%20Folders/Templates?messageId=1\"\n"
+        ));
+
+        for (action, message) in [
+            (
+                "- reply_with: thank",
+                "filter 'Invoices': unknown template `thank`, add it to the account's `templates`",
+            ),
+            (
+                "- run_program: notify-send",
+                "filter 'Invoices': Thunderbird filters cannot represent `run_program`",
+            ),
+        ] {
+            let source = format!("{source}        {action}\n");
+            let config = crate::configuration::parse(&source).unwrap();
+            assert_eq!(print_config(config).unwrap_err().to_string(), message);
+        }
+    }
+
+    #[test]
+    fn test_print_config_with_run_on() {
+        let source = "\
+- evolution_id: evolution
+  thunderbird_id: thunderbird
+  message_filters:
+    - title: Cleanup
+      description: Old newsletters
+      enabled: false
+      run_on: [manual, after_sending]
+      when:
+        - field: list_id
+          contains: [news]
+      then:
+        - delete
+";
+        let config = crate::configuration::parse(source).unwrap();
+
+        assert_eq!(
+            print_config(config).unwrap(),
+            [
+                "version=\"9\"",
+                "logging=\"no\"",
+                "name=\"Cleanup\"",
+                "enabled=\"no\"",
+                "description=\"Old newsletters\"",
+                "type=\"80\"",
+                "action=\"Delete\"",
+                "condition=\"AND (\\\"List-Id\\\",contains,news)\"",
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test]
+    fn test_print_config_with_state_conditions() {
+        let source = "\
+- evolution_id: evolution
+  thunderbird_id: thunderbird
+  message_filters:
+    - title: Old
+      match: all
+      when:
+        - size: { greater_than: 5MB }
+        - date: { on: 2026-01-31 }
+        - not:
+            status: read
+        - has_attachment: true
+        - junk: false
+        - tag: $label1
+      then:
+        - delete
+";
+        let config = crate::configuration::parse(source).unwrap();
+        let output = print_config(config).unwrap();
+
+        assert!(output.contains(
+            "condition=\"AND (size,is greater than,5120) AND (date,is,31-Jan-2026) \
+             AND (status,isn't,read) AND (has attachment status,is,true) \
+             AND (junk status,isn't,2) AND (tag,contains,$label1)\""
+        ));
+    }
+
+    #[test]
+    fn test_print_accounts() {
+        let account = |thunderbird_id: &str, thunderbird_path: Option<&str>| Account {
+            account: None,
+            email: None,
+            imap_host: None,
+            evolution_id: None,
+            thunderbird_id: Some(thunderbird_id.to_owned()),
+            thunderbird_path: thunderbird_path.map(str::to_owned),
+            sieve_separator: None,
+            templates: BTreeMap::new(),
+            message_filters: vec![MessageFilter {
+                title: "Amazon".to_owned(),
+                grouping: Grouping::Any,
+                description: None,
+                enabled: true,
+                run_on: vec![RunOn::Incoming, RunOn::Manual],
+                when: vec![When::Contains(Contains {
+                    field: Field::From,
+                    values: vec!["@amazon.".to_owned()],
+                })],
+                then: vec![Then::MoveTo(MoveTo {
+                    folder: "amzn".to_owned(),
+                })],
+            }],
+        };
+        let config = Configuration {
+            accounts: vec![
+                account("work", None),
+                account("home", Some("ImapMail/home/msgFilterRules.dat")),
+            ],
+        };
+
+        let files = print_accounts(config).unwrap();
+        assert_eq!(
+            files
+                .iter()
+                .map(|(path, _)| path.clone())
+                .collect::<Vec<_>>(),
+            vec![
+                PathBuf::from("work/msgFilterRules.dat"),
+                PathBuf::from("ImapMail/home/msgFilterRules.dat")
+            ]
+        );
+        assert!(files[0]
+            .1
+            .starts_with("version=\"9\"\nlogging=\"no\"\nname=\"Amazon\""));
+        assert!(files[1].1.contains("actionValue=\"imap://home/amzn\""));
+    }
+
+    #[test]
+    fn test_print_accounts_rejects_duplicate_paths() {
+        let config = Configuration {
+            accounts: vec![
+                Account {
+                    account: None,
+                    email: None,
+                    imap_host: None,
+                    evolution_id: None,
+                    thunderbird_id: Some("same".to_owned()),
+                    thunderbird_path: None,
+                    sieve_separator: None,
+                    templates: BTreeMap::new(),
+                    message_filters: Vec::new(),
+                },
+                Account {
+                    account: None,
+                    email: None,
+                    imap_host: None,
+                    evolution_id: None,
+                    thunderbird_id: Some("other".to_owned()),
+                    thunderbird_path: Some("same/msgFilterRules.dat".to_owned()),
+                    sieve_separator: None,
+                    templates: BTreeMap::new(),
+                    message_filters: Vec::new(),
+                },
+            ],
+        };
+
+        assert!(print_accounts(config).is_err());
+    }
+
+    #[test]
+    fn test_print_accounts_rejects_paths_outside_the_directory() {
+        let config = |account: &str| {
+            crate::configuration::parse(&format!("- {account}\n  message_filters: []\n")).unwrap()
+        };
+
+        assert!(matches!(
+            print_accounts(config("thunderbird_id: ../../evil")),
+            Err(err) if err.to_string() == "../../evil/msgFilterRules.dat is not a relative path inside the output directory"
+        ));
+        assert!(print_accounts(config("thunderbird_id: /evil")).is_err());
+        assert!(
+            print_accounts(config("email: jdoe@example.com\n  imap_host: x/../../evil")).is_err()
+        );
+    }
+
+    #[test]
+    fn test_print_config_rejects_empty_folders() {
+        let print = |account: &str, action: &str| {
+            let source = format!(
+                "- {account}\n  message_filters:\n    - title: Archive\n      when: []\n      then: [{action}]\n"
+            );
+            print_config(crate::configuration::parse(&source).unwrap())
+        };
+
+        assert!(matches!(
+            print("thunderbird_id: home", "move_to: ''"),
+            Err(Error::Filter { source, .. }) if matches!(*source, Error::EmptyFolder)
+        ));
+        assert!(matches!(
+            print("thunderbird_id: home", "copy_to: ''"),
+            Err(Error::Filter { source, .. }) if matches!(*source, Error::EmptyFolder)
+        ));
+        assert!(matches!(
+            print("thunderbird_id: ''", "move_to: Archive"),
+            Err(Error::Filter { source, .. }) if matches!(*source, Error::EmptyThunderbirdId)
+        ));
+    }
+
+    #[test]
+    fn test_merge_config() {
+        let source = r#"
+- thunderbird_id: thunderbird
+  message_filters:
+    - title: Amazon
+      when:
+        - field: from
+          contains: ["@amazon."]
+      then:
+        - move_to: amzn
+    - title: Renamed
+      when:
+        - field: from
+          contains: ["@renamed."]
+      then:
+        - delete
+"#;
+        let existing = [
+            "version=\"9\"",
+            "logging=\"yes\"",
+            "name=\"Mine\"",
+            "enabled=\"yes\"",
+            "type=\"1\"",
+            "action=\"Delete\"",
+            "condition=\"AND (subject,contains,\\\"spam\\\")\"",
+            "name=\"Old [mail-message-filters]\"",
+            "enabled=\"yes\"",
+            "type=\"17\"",
+            "action=\"Delete\"",
+            "condition=\"AND (from,contains,old)\"",
+            "name=\"Colleague\"",
+            "enabled=\"no\"",
+            "description=\"one\ntwo\"",
+            "type=\"16\"",
+            "action=\"Mark read\"",
+            "condition=\"ALL\"",
+            "name=\"Amazon\"",
+            "enabled=\"yes\"",
+            "type=\"17\"",
+            "action=\"Delete\"",
+            "condition=\"AND (from,contains,@amazon.)\"",
+        ]
+        .join("\n");
+
+        let config = crate::configuration::parse(source).unwrap();
+        assert_eq!(
+            merge_config(config, &existing).unwrap(),
+            [
+                "version=\"9\"",
+                "logging=\"yes\"",
+                "name=\"Mine\"",
+                "enabled=\"yes\"",
+                "type=\"1\"",
+                "action=\"Delete\"",
+                "condition=\"AND (subject,contains,\\\"spam\\\")\"",
+                "name=\"Amazon [mail-message-filters]\"",
+                "enabled=\"yes\"",
+                "type=\"17\"",
+                "action=\"Move to folder\"",
+                "actionValue=\"imap://thunderbird/amzn\"",
+                "condition=\"AND (from,contains,@amazon.)\"",
+                "name=\"Renamed [mail-message-filters]\"",
+                "enabled=\"yes\"",
+                "type=\"17\"",
+                "action=\"Delete\"",
+                "condition=\"AND (from,contains,@renamed.)\"",
+                "name=\"Colleague\"",
+                "enabled=\"no\"",
+                "description=\"one\ntwo\"",
+                "type=\"16\"",
+                "action=\"Mark read\"",
+                "condition=\"ALL\"",
+                "name=\"Amazon\"",
+                "enabled=\"yes\"",
+                "type=\"17\"",
+                "action=\"Delete\"",
+                "condition=\"AND (from,contains,@amazon.)\"",
+            ]
+            .join("\n")
+        );
+
+        let config = crate::configuration::parse(source).unwrap();
+        let merged = merge_config(config, "version=\"9\"\nlogging=\"no\"").unwrap();
+        assert!(merged.starts_with("version=\"9\"\nlogging=\"no\"\nname=\"Amazon [mail"));
+    }
+
+    #[test]
+    fn test_merge_config_keeps_filters_with_configured_titles() {
+        let config = crate::configuration::parse(
+            "\
+- thunderbird_id: thunderbird
+  message_filters:
+    - title: Github
+      when:
+        - field: from
+          ends_with: ['@github.com']
+      then:
+        - move_to: Github
+",
+        )
+        .unwrap();
+        let existing = [
+            "version=\"9\"",
+            "logging=\"no\"",
+            "name=\"Github\"",
+            "enabled=\"yes\"",
+            "type=\"17\"",
+            "action=\"Delete\"",
+            "condition=\"AND (subject,contains,github)\"",
+        ]
+        .join("\n");
+
+        let merged = merge_config(config, &existing).unwrap();
+        assert!(merged.starts_with(&existing));
+        assert!(merged.contains("name=\"Github [mail-message-filters]\""));
+        assert_eq!(
+            managed_filters(&existing).unwrap(),
+            "version=\"9\"\nlogging=\"no\""
+        );
+        assert_eq!(unmanaged_filters(&merged).unwrap(), ["Github"]);
+    }
+
+    use std::collections::BTreeMap;
+
     use super::*;
     use crate::configuration::{
-        Account, Contains, EndsWith, Field, MessageFilter, MoveTo, Then, When,
+        Account, Contains, EndsWith, Field, Grouping, MessageFilter, MoveTo, RunOn, Then, When,
     };
 }
 
+use std::path::PathBuf;
+
+pub use crate::dat::Error as ParseError;
 use crate::{
-    configuration::{Configuration, Then},
-    dat::DatDocument,
-    Result,
+    configuration::{Account, Configuration},
+    dat::{self, DatDocument},
+    printer::ConditionError,
+    resolver,
 };
@@ -0,0 +1,259 @@
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("{path}: {source}")]
+    Read {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+    #[error("account `{0}`: no Evolution mail account has this address")]
+    UnknownEmail(String),
+    #[error("account `{email}`: several Evolution mail accounts have this address: {}", .uids.join(", "))]
+    AmbiguousEmail { email: String, uids: Vec<String> },
+}
+
+/// Returns the account's `thunderbird_id`, or else the id Thunderbird gives the IMAP server of
+/// `email` on `imap_host`, assuming the address is the user name.
+pub fn thunderbird_id(account: &Account) -> Option<String> {
+    if let Some(thunderbird_id) = &account.thunderbird_id {
+        return Some(thunderbird_id.clone());
+    }
+    match (&account.email, &account.imap_host) {
+        (Some(email), Some(imap_host)) => Some(format_thunderbird_id(email, imap_host)),
+        _ => None,
+    }
+}
+
+/// Returns the id Thunderbird uses in folder URIs for `username` on `hostname`, with the user
+/// name percent-encoded, e.g. `jdoe%40example.com@mail.example.com`.
+pub fn format_thunderbird_id(username: &str, hostname: &str) -> String {
+    let mut id = String::with_capacity(username.len() + hostname.len() + 3);
+    for byte in username.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                id.push(byte as char)
+            }
+            _ => id.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    id.push('@');
+    id.push_str(hostname);
+    id
+}
+
+/// A mail account from Evolution's `sources` directory, with the address of its identity.
+#[derive(Debug, PartialEq)]
+pub struct MailAccount {
+    /// The source UID, which Evolution uses as the account in folder URIs.
+    pub uid: String,
+    pub address: String,
+}
+
+/// Reads the mail accounts from the `*.source` key files in `sources`. Each `[Mail Account]`
+/// refers to a `[Mail Identity]` source, which holds the address.
+pub fn read_mail_accounts(sources: &Path) -> Result<Vec<MailAccount>, Error> {
+    let read_error = |source| Error::Read {
+        path: sources.to_owned(),
+        source,
+    };
+
+    let mut files = BTreeMap::new();
+    for entry in fs::read_dir(sources).map_err(read_error)? {
+        let path = entry.map_err(read_error)?.path();
+        if path.extension() != Some(OsStr::new("source")) {
+            continue;
+        }
+        let Some(uid) = path.file_stem().and_then(OsStr::to_str) else {
+            continue;
+        };
+        let source = fs::read_to_string(&path).map_err(|source| Error::Read {
+            path: path.clone(),
+            source,
+        })?;
+        files.insert(uid.to_owned(), install::parse_ini(&source));
+    }
+
+    let key = |uid: &str, section: &str, key: &str| {
+        files
+            .get(uid)?
+            .iter()
+            .find(|(name, _)| name == section)?
+            .1
+            .get(key)
+            .cloned()
+    };
+
+    let accounts = files
+        .keys()
+        .filter_map(|uid| {
+            let identity = key(uid, "Mail Account", "IdentityUid")?;
+            let address = key(&identity, "Mail Identity", "Address")?;
+            Some(MailAccount {
+                uid: uid.clone(),
+                address,
+            })
+        })
+        .collect();
+
+    Ok(accounts)
+}
+
+/// Returns whether an account has an `email` but no `evolution_id`, so that
+/// [`resolve_evolution_ids`] needs Evolution's `sources` directory.
+pub fn needs_evolution_ids(config: &Configuration) -> bool {
+    config
+        .accounts
+        .iter()
+        .any(|account| account.evolution_id.is_none() && account.email.is_some())
+}
+
+/// Sets the `evolution_id` of accounts that only have an `email`, from the mail account with
+/// that address in `sources`. The directory is only read if an account needs it.
+pub fn resolve_evolution_ids(config: &mut Configuration, sources: &Path) -> Result<(), Error> {
+    if !needs_evolution_ids(config) {
+        return Ok(());
+    }
+
+    let mail_accounts = read_mail_accounts(sources)?;
+    for account in &mut config.accounts {
+        let (None, Some(email)) = (&account.evolution_id, &account.email) else {
+            continue;
+        };
+        let uids = mail_accounts
+            .iter()
+            .filter(|mail_account| mail_account.address.eq_ignore_ascii_case(email))
+            .map(|mail_account| mail_account.uid.clone())
+            .collect::<Vec<_>>();
+        account.evolution_id = match <[String; 1]>::try_from(uids) {
+            Ok([uid]) => Some(uid),
+            Err(uids) if uids.is_empty() => return Err(Error::UnknownEmail(email.clone())),
+            Err(uids) => {
+                return Err(Error::AmbiguousEmail {
+                    email: email.clone(),
+                    uids,
+                })
+            }
+        };
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_thunderbird_id() {
+        let config = crate::configuration::parse(
+            "\
+- email: j.doe+lists@example.com
+  imap_host: mail.example.com
+  message_filters: []
+- email: jdoe@example.com
+  imap_host: mail.example.com
+  thunderbird_id: explicit
+  message_filters: []
+- email: jdoe@example.com
+  message_filters: []
+",
+        )
+        .unwrap();
+
+        assert_eq!(
+            thunderbird_id(&config.accounts[0]).as_deref(),
+            Some("j.doe%2Blists%40example.com@mail.example.com")
+        );
+        assert_eq!(
+            thunderbird_id(&config.accounts[1]).as_deref(),
+            Some("explicit")
+        );
+        assert_eq!(thunderbird_id(&config.accounts[2]), None);
+    }
+
+    #[test]
+    fn test_resolve_evolution_ids() {
+        let sources = std::env::temp_dir().join(format!("mmf-sources-{}", std::process::id()));
+        fs::create_dir_all(&sources).unwrap();
+        for (uid, source) in [
+            (
+                "1a2b",
+                "[Data Source]\nDisplayName=Work\n\n[Mail Account]\nIdentityUid=3c4d\n",
+            ),
+            (
+                "3c4d",
+                "[Data Source]\nParent=1a2b\n\n[Mail Identity]\nAddress=JDoe@example.com\n",
+            ),
+            (
+                "local",
+                "[Data Source]\nDisplayName=On This Computer\n\n[Mail Account]\n",
+            ),
+        ] {
+            fs::write(sources.join(format!("{uid}.source")), source).unwrap();
+        }
+
+        assert_eq!(
+            read_mail_accounts(&sources).unwrap(),
+            [MailAccount {
+                uid: "1a2b".to_owned(),
+                address: "JDoe@example.com".to_owned(),
+            }]
+        );
+
+        let mut config = crate::configuration::parse(
+            "\
+- email: jdoe@example.com
+  message_filters: []
+- email: other@example.com
+  evolution_id: explicit
+  message_filters: []
+",
+        )
+        .unwrap();
+        resolve_evolution_ids(&mut config, &sources).unwrap();
+        assert_eq!(config.accounts[0].evolution_id.as_deref(), Some("1a2b"));
+        assert_eq!(config.accounts[1].evolution_id.as_deref(), Some("explicit"));
+
+        config.accounts[0].evolution_id = None;
+        config.accounts[0].email = Some("nobody@example.com".to_owned());
+        assert!(matches!(
+            resolve_evolution_ids(&mut config, &sources),
+            Err(Error::UnknownEmail(email)) if email == "nobody@example.com"
+        ));
+
+        fs::remove_dir_all(sources).unwrap();
+    }
+
+    #[test]
+    fn test_resolve_evolution_ids_without_sources() {
+        let mut config = crate::configuration::parse(
+            "\
+- evolution_id: abc
+  email: jdoe@example.com
+  message_filters: []
+- thunderbird_id: elsewhere
+  message_filters: []
+",
+        )
+        .unwrap();
+
+        assert!(!needs_evolution_ids(&config));
+        resolve_evolution_ids(&mut config, Path::new("/nonexistent/sources")).unwrap();
+        assert_eq!(config.accounts[0].evolution_id.as_deref(), Some("abc"));
+
+        config.accounts[0].evolution_id = None;
+        assert!(needs_evolution_ids(&config));
+    }
+
+    use super::*;
+}
+
+use std::{
+    collections::BTreeMap,
+    ffi::OsStr,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    configuration::{Account, Configuration},
+    install,
+};
@@ -0,0 +1,519 @@
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum Error {
+    #[error("line {line}: unexpected {found}, expected {expected}")]
+    Unexpected {
+        line: usize,
+        found: String,
+        expected: &'static str,
+    },
+    #[error("line {line}: unexpected character `{found}`")]
+    InvalidCharacter { line: usize, found: char },
+    #[error("line {0}: unterminated string")]
+    UnterminatedString(usize),
+    #[error("line {0}: unterminated comment")]
+    UnterminatedComment(usize),
+}
+
+/// A command such as `fileinto "INBOX";` or `if ... { ... }`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Command {
+    pub line: usize,
+    pub name: String,
+    pub arguments: Vec<Argument>,
+    pub tests: Vec<Test>,
+    pub block: Option<Vec<Command>>,
+    /// Comments right before the command, without the leading `#`.
+    pub comments: Vec<String>,
+    /// Comments between the command name and its block or `;`, e.g. the test of a rule
+    /// disabled with `if false # ...`.
+    pub inline_comments: Vec<String>,
+}
+
+/// A test such as `header :contains "subject" "x"` or `anyof (...)`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Test {
+    pub line: usize,
+    pub name: String,
+    pub arguments: Vec<Argument>,
+    pub tests: Vec<Test>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Argument {
+    /// A string or string list, e.g. `"a"` or `["a", "b"]`.
+    Strings(Vec<String>),
+    /// A number with any `K`, `M` or `G` quantifier applied.
+    Number(u64),
+    /// A tag without the leading `:`, e.g. `contains`.
+    Tag(String),
+}
+
+/// Parses a Sieve script (RFC 5228) into its commands.
+pub fn parse(source: &str) -> Result<Vec<Command>, Error> {
+    let mut parser = helpers::Parser::new(source)?;
+    let commands = parser.commands()?;
+    parser.expect_end()?;
+    Ok(commands)
+}
+
+/// Parses a single test, e.g. from the comment of a disabled rule.
+pub fn parse_test(source: &str) -> Result<Test, Error> {
+    let mut parser = helpers::Parser::new(source)?;
+    let test = parser.test()?;
+    parser.expect_end()?;
+    Ok(test)
+}
+
+mod helpers {
+    #[derive(Clone, Debug, PartialEq)]
+    enum Token {
+        Identifier(String),
+        Tag(String),
+        Number(u64),
+        String(String),
+        Comment(String),
+        Punctuation(char),
+    }
+
+    impl std::fmt::Display for Token {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            match self {
+                Token::Identifier(name) => write!(f, "`{name}`"),
+                Token::Tag(tag) => write!(f, "`:{tag}`"),
+                Token::Number(number) => write!(f, "number {number}"),
+                Token::String(_) => f.write_str("string"),
+                Token::Comment(_) => f.write_str("comment"),
+                Token::Punctuation(c) => write!(f, "`{c}`"),
+            }
+        }
+    }
+
+    /// Splits a script into tokens, each with the line it starts on.
+    fn tokenize(source: &str) -> Result<Vec<(usize, Token)>, Error> {
+        let chars: Vec<char> = source.chars().collect();
+        let mut tokens = Vec::new();
+        let mut line = 1;
+        let mut i = 0;
+
+        while let Some(&c) = chars.get(i) {
+            let start = line;
+            let token = match c {
+                '\n' => {
+                    line += 1;
+                    i += 1;
+                    continue;
+                }
+                c if c.is_whitespace() => {
+                    i += 1;
+                    continue;
+                }
+                '#' => {
+                    let end = chars[i..]
+                        .iter()
+                        .position(|&c| c == '\n')
+                        .map_or(chars.len(), |end| i + end);
+                    let comment = chars[i + 1..end].iter().collect::<String>();
+                    i = end;
+                    Token::Comment(comment.trim_end_matches('\r').to_owned())
+                }
+                '/' if chars.get(i + 1) == Some(&'*') => {
+                    let rest = chars[i + 2..].iter().collect::<String>();
+                    let Some(end) = rest.find("*/") else {
+                        return Err(Error::UnterminatedComment(start));
+                    };
+                    let comment = rest[..end].to_owned();
+                    line += comment.matches('\n').count();
+                    i += 2 + comment.chars().count() + 2;
+                    Token::Comment(comment)
+                }
+                '"' => {
+                    let mut string = String::new();
+                    i += 1;
+                    loop {
+                        match chars.get(i) {
+                            Some('"') => break,
+                            Some('\\') => {
+                                string.extend(chars.get(i + 1));
+                                i += 1;
+                            }
+                            Some(&c) => {
+                                line += usize::from(c == '\n');
+                                string.push(c);
+                            }
+                            None => return Err(Error::UnterminatedString(start)),
+                        }
+                        i += 1;
+                    }
+                    i += 1;
+                    Token::String(string)
+                }
+                '[' | ']' | '(' | ')' | '{' | '}' | ',' | ';' => {
+                    i += 1;
+                    Token::Punctuation(c)
+                }
+                ':' => {
+                    let length = identifier_length(&chars[i + 1..]);
+                    if length == 0 {
+                        return Err(Error::InvalidCharacter { line, found: c });
+                    }
+                    let tag = chars[i + 1..i + 1 + length].iter().collect::<String>();
+                    i += 1 + length;
+                    Token::Tag(tag.to_ascii_lowercase())
+                }
+                c if c.is_ascii_digit() => {
+                    let length = chars[i..].iter().take_while(|c| c.is_ascii_digit()).count();
+                    let digits = chars[i..i + length].iter().collect::<String>();
+                    i += length;
+                    let scale = match chars.get(i).map(char::to_ascii_uppercase) {
+                        Some('K') => 1 << 10,
+                        Some('M') => 1 << 20,
+                        Some('G') => 1 << 30,
+                        _ => 1,
+                    };
+                    if scale != 1 {
+                        i += 1;
+                    }
+                    let number = digits
+                        .parse::<u64>()
+                        .ok()
+                        .and_then(|number| number.checked_mul(scale))
+                        .ok_or(Error::InvalidCharacter { line, found: c })?;
+                    Token::Number(number)
+                }
+                c if c.is_ascii_alphabetic() || c == '_' => {
+                    let length = identifier_length(&chars[i..]);
+                    let name = chars[i..i + length].iter().collect::<String>();
+                    i += length;
+
+                    // A multi-line string, `text:` up to a line holding a single `.`.
+                    if name.eq_ignore_ascii_case("text") && chars.get(i) == Some(&':') {
+                        let rest = chars[i..].iter().collect::<String>();
+                        let Some(newline) = rest.find('\n') else {
+                            return Err(Error::UnterminatedString(start));
+                        };
+                        let mut text = Vec::new();
+                        let mut consumed = newline + 1;
+                        let mut terminated = false;
+                        for text_line in rest[newline + 1..].split_inclusive('\n') {
+                            consumed += text_line.len();
+                            line += 1;
+                            let content = text_line.trim_end_matches(['\r', '\n']);
+                            if content == "." {
+                                terminated = true;
+                                break;
+                            }
+                            text.push(content.strip_prefix('.').unwrap_or(content).to_owned());
+                        }
+                        if !terminated {
+                            return Err(Error::UnterminatedString(start));
+                        }
+                        i += rest[..consumed].chars().count();
+                        let mut string = text.join("\n");
+                        if !text.is_empty() {
+                            string.push('\n');
+                        }
+                        Token::String(string)
+                    } else {
+                        Token::Identifier(name.to_ascii_lowercase())
+                    }
+                }
+                c => return Err(Error::InvalidCharacter { line, found: c }),
+            };
+            tokens.push((start, token));
+        }
+
+        Ok(tokens)
+    }
+
+    fn identifier_length(chars: &[char]) -> usize {
+        match chars.first() {
+            Some(c) if c.is_ascii_alphabetic() || *c == '_' => chars
+                .iter()
+                .take_while(|c| c.is_ascii_alphanumeric() || **c == '_')
+                .count(),
+            _ => 0,
+        }
+    }
+
+    pub struct Parser {
+        tokens: Vec<(usize, Token)>,
+        position: usize,
+        /// Comments skipped since the last command started.
+        comments: Vec<String>,
+    }
+
+    impl Parser {
+        pub fn new(source: &str) -> Result<Self, Error> {
+            Ok(Parser {
+                tokens: tokenize(source)?,
+                position: 0,
+                comments: Vec::new(),
+            })
+        }
+
+        /// Parses commands up to the end of the script or a closing `}`.
+        pub fn commands(&mut self) -> Result<Vec<Command>, Error> {
+            let mut commands = Vec::new();
+            while let Some(Token::Identifier(_)) = self.peek() {
+                commands.push(self.command()?);
+            }
+            self.comments.clear();
+            Ok(commands)
+        }
+
+        fn command(&mut self) -> Result<Command, Error> {
+            let comments = std::mem::take(&mut self.comments);
+            let (line, name) = self.identifier()?;
+            let (arguments, tests) = self.arguments()?;
+
+            let block = match self.next() {
+                Some((_, Token::Punctuation(';'))) => None,
+                Some((_, Token::Punctuation('{'))) => {
+                    let inline_comments = std::mem::take(&mut self.comments);
+                    let block = self.commands()?;
+                    match self.next() {
+                        Some((_, Token::Punctuation('}'))) => {}
+                        token => return Err(self.unexpected(token, "a command or `}`")),
+                    }
+                    return Ok(Command {
+                        line,
+                        name,
+                        arguments,
+                        tests,
+                        block: Some(block),
+                        comments,
+                        inline_comments,
+                    });
+                }
+                token => return Err(self.unexpected(token, "`;` or `{`")),
+            };
+
+            Ok(Command {
+                line,
+                name,
+                arguments,
+                tests,
+                block,
+                comments,
+                inline_comments: std::mem::take(&mut self.comments),
+            })
+        }
+
+        pub fn test(&mut self) -> Result<Test, Error> {
+            let (line, name) = self.identifier()?;
+            let (arguments, tests) = self.arguments()?;
+            Ok(Test {
+                line,
+                name,
+                arguments,
+                tests,
+            })
+        }
+
+        /// Parses arguments followed by an optional test or test list.
+        fn arguments(&mut self) -> Result<(Vec<Argument>, Vec<Test>), Error> {
+            let mut arguments = Vec::new();
+            loop {
+                let argument = match self.peek() {
+                    Some(Token::Tag(tag)) => Argument::Tag(tag.clone()),
+                    Some(Token::Number(number)) => Argument::Number(*number),
+                    Some(Token::String(string)) => Argument::Strings(vec![string.clone()]),
+                    Some(Token::Punctuation('[')) => {
+                        self.next();
+                        arguments.push(Argument::Strings(self.string_list()?));
+                        continue;
+                    }
+                    _ => break,
+                };
+                self.next();
+                arguments.push(argument);
+            }
+
+            let tests = match self.peek() {
+                Some(Token::Identifier(_)) => vec![self.test()?],
+                Some(Token::Punctuation('(')) => {
+                    self.next();
+                    let mut tests = vec![self.test()?];
+                    loop {
+                        match self.next() {
+                            Some((_, Token::Punctuation(','))) => tests.push(self.test()?),
+                            Some((_, Token::Punctuation(')'))) => break,
+                            token => return Err(self.unexpected(token, "`,` or `)`")),
+                        }
+                    }
+                    tests
+                }
+                _ => Vec::new(),
+            };
+
+            Ok((arguments, tests))
+        }
+
+        /// Parses the rest of a string list after `[`.
+        fn string_list(&mut self) -> Result<Vec<String>, Error> {
+            let mut strings = Vec::new();
+            loop {
+                match self.next() {
+                    Some((_, Token::String(string))) => strings.push(string),
+                    token => return Err(self.unexpected(token, "a string")),
+                }
+                match self.next() {
+                    Some((_, Token::Punctuation(','))) => {}
+                    Some((_, Token::Punctuation(']'))) => return Ok(strings),
+                    token => return Err(self.unexpected(token, "`,` or `]`")),
+                }
+            }
+        }
+
+        fn identifier(&mut self) -> Result<(usize, String), Error> {
+            match self.next() {
+                Some((line, Token::Identifier(name))) => Ok((line, name)),
+                token => Err(self.unexpected(token, "an identifier")),
+            }
+        }
+
+        pub fn expect_end(&mut self) -> Result<(), Error> {
+            match self.next() {
+                None => Ok(()),
+                token => Err(self.unexpected(token, "a command")),
+            }
+        }
+
+        /// Returns the next token that is not a comment, collecting the comments.
+        fn peek(&mut self) -> Option<&Token> {
+            while let Some((_, Token::Comment(comment))) = self.tokens.get(self.position) {
+                self.comments.push(comment.clone());
+                self.position += 1;
+            }
+            self.tokens.get(self.position).map(|(_, token)| token)
+        }
+
+        fn next(&mut self) -> Option<(usize, Token)> {
+            self.peek()?;
+            let token = self.tokens.get(self.position).cloned();
+            self.position += 1;
+            token
+        }
+
+        fn unexpected(&self, token: Option<(usize, Token)>, expected: &'static str) -> Error {
+            let line = self.tokens.last().map_or(1, |(line, _)| *line);
+            match token {
+                Some((line, found)) => Error::Unexpected {
+                    line,
+                    found: found.to_string(),
+                    expected,
+                },
+                None => Error::Unexpected {
+                    line,
+                    found: "end of script".to_owned(),
+                    expected,
+                },
+            }
+        }
+    }
+
+    use super::{Argument, Command, Error, Test};
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_parse() {
+        let source = r#"require ["fileinto", "imap4flags"];
+
+# rule:[Github]
+if anyof (address :matches "from" "*@github.com", not size :over 1M) {
+    fileinto "Github"; /* archived */
+    addflag ["\\Seen", "$label1"];
+}
+if false # header :contains "subject" "x"
+{
+    discard;
+}
+"#;
+        let commands = parse(source).unwrap();
+
+        assert_eq!(commands.len(), 3);
+        assert_eq!(
+            commands[0].arguments,
+            [Argument::Strings(vec![
+                "fileinto".to_owned(),
+                "imap4flags".to_owned()
+            ])]
+        );
+
+        let rule = &commands[1];
+        assert_eq!(rule.line, 4);
+        assert_eq!(rule.comments, [" rule:[Github]"]);
+        let anyof = &rule.tests[0];
+        assert_eq!(anyof.name, "anyof");
+        assert_eq!(
+            anyof.tests[0],
+            Test {
+                line: 4,
+                name: "address".to_owned(),
+                arguments: vec![
+                    Argument::Tag("matches".to_owned()),
+                    Argument::Strings(vec!["from".to_owned()]),
+                    Argument::Strings(vec!["*@github.com".to_owned()]),
+                ],
+                tests: Vec::new(),
+            }
+        );
+        assert_eq!(
+            anyof.tests[1].tests[0].arguments,
+            [Argument::Tag("over".to_owned()), Argument::Number(1 << 20)]
+        );
+        let block = rule.block.as_ref().unwrap();
+        assert_eq!(
+            block[1].arguments,
+            [Argument::Strings(vec![
+                "\\Seen".to_owned(),
+                "$label1".to_owned()
+            ])]
+        );
+        assert_eq!(block[1].comments, [" archived "]);
+
+        assert_eq!(
+            commands[2].inline_comments,
+            [r#" header :contains "subject" "x""#]
+        );
+        assert_eq!(
+            parse_test(commands[2].inline_comments[0].trim()).map(|test| test.name),
+            Ok("header".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_parse_multiline_strings() {
+        let source = "vacation text:\nGone\n..fishing\n.\n;";
+        let commands = parse(source).unwrap();
+        assert_eq!(
+            commands[0].arguments,
+            [Argument::Strings(vec!["Gone\n.fishing\n".to_owned()])]
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            parse("fileinto \"x\";\n}"),
+            Err(Error::Unexpected {
+                line: 2,
+                found: "`}`".to_owned(),
+                expected: "a command",
+            })
+        );
+        assert_eq!(
+            parse("if true {"),
+            Err(Error::Unexpected {
+                line: 1,
+                found: "end of script".to_owned(),
+                expected: "a command or `}`",
+            })
+        );
+        assert_eq!(parse("stop \"x;"), Err(Error::UnterminatedString(1)));
+    }
+
+    use super::*;
+}
@@ -0,0 +1,224 @@
+/// A 1-based line and column in the YAML source.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl std::fmt::Display for Position {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+/// One step of a path from the document root to a node.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Segment {
+    Key(String),
+    Index(usize),
+}
+
+impl From<&str> for Segment {
+    fn from(key: &str) -> Self {
+        Segment::Key(key.to_owned())
+    }
+}
+
+impl From<usize> for Segment {
+    fn from(index: usize) -> Self {
+        Segment::Index(index)
+    }
+}
+
+/// Builds a path from a mix of keys and indices, e.g. `path![0, "message_filters", 2]`.
+#[macro_export]
+macro_rules! path {
+    ($($segment:expr),* $(,)?) => {
+        [$($crate::source::Segment::from($segment)),*]
+    };
+}
+
+/// Positions of the nodes in a YAML document, keyed by their path.
+///
+/// Mapping entries are located at their key, sequence items at their first token.
+#[derive(Default, Debug)]
+pub struct SourceMap {
+    positions: HashMap<Vec<Segment>, Position>,
+}
+
+impl SourceMap {
+    /// Indexes `source`. Malformed YAML yields a partial (possibly empty) map; syntax errors
+    /// are reported by the deserializer instead.
+    pub fn parse(source: &str) -> Self {
+        let mut builder = helpers::Builder::default();
+        let _ = Parser::new_from_str(source).load(&mut builder, false);
+        SourceMap {
+            positions: builder.positions,
+        }
+    }
+
+    /// Returns the position of the node at `path`, falling back to its closest located ancestor.
+    pub fn position(&self, path: &[Segment]) -> Option<Position> {
+        (0..=path.len())
+            .rev()
+            .find_map(|len| self.positions.get(&path[..len]).copied())
+    }
+}
+
+mod helpers {
+
+    enum Frame {
+        Sequence { index: usize },
+        Mapping { key: Option<String> },
+    }
+
+    #[derive(Default)]
+    pub struct Builder {
+        pub positions: HashMap<Vec<Segment>, Position>,
+        path: Vec<Segment>,
+        frames: Vec<Frame>,
+    }
+
+    impl Builder {
+        /// Called before a value node starts, pushes the segment that leads to it. Mappings are
+        /// located at their first key instead, since their start event is reported late.
+        fn enter_value(&mut self, mark: Option<&Marker>) {
+            match self.frames.last() {
+                Some(Frame::Sequence { index }) => {
+                    self.path.push(Segment::Index(*index));
+                    if let Some(mark) = mark {
+                        self.positions
+                            .entry(self.path.clone())
+                            .or_insert(position(mark));
+                    }
+                }
+                Some(Frame::Mapping { key: Some(key) }) => {
+                    self.path.push(Segment::Key(key.clone()));
+                }
+                _ => {}
+            }
+        }
+
+        /// Called after a value node ends, pops its segment and advances the parent.
+        fn leave_value(&mut self) {
+            match self.frames.last_mut() {
+                Some(Frame::Sequence { index }) => {
+                    *index += 1;
+                    self.path.pop();
+                }
+                Some(Frame::Mapping { key }) if key.is_some() => {
+                    *key = None;
+                    self.path.pop();
+                }
+                _ => {}
+            }
+        }
+
+        fn expects_key(&self) -> bool {
+            matches!(self.frames.last(), Some(Frame::Mapping { key: None }))
+        }
+    }
+
+    impl MarkedEventReceiver for Builder {
+        fn on_event(&mut self, event: Event, mark: Marker) {
+            match event {
+                Event::Scalar(value, ..) if self.expects_key() => {
+                    self.positions
+                        .entry(self.path.clone())
+                        .or_insert(position(&mark));
+
+                    let mut path = self.path.clone();
+                    path.push(Segment::Key(value.clone()));
+                    self.positions.insert(path, position(&mark));
+                    if let Some(Frame::Mapping { key }) = self.frames.last_mut() {
+                        *key = Some(value);
+                    }
+                }
+                Event::Scalar(..) | Event::Alias(..) => {
+                    self.enter_value(Some(&mark));
+                    self.leave_value();
+                }
+                Event::SequenceStart(..) => {
+                    self.enter_value(Some(&mark));
+                    self.frames.push(Frame::Sequence { index: 0 });
+                }
+                Event::MappingStart(..) => {
+                    self.enter_value(None);
+                    self.frames.push(Frame::Mapping { key: None });
+                }
+                Event::SequenceEnd | Event::MappingEnd => {
+                    self.frames.pop();
+                    self.leave_value();
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn position(mark: &Marker) -> Position {
+        Position {
+            line: mark.line(),
+            column: mark.col() + 1,
+        }
+    }
+
+    use std::collections::HashMap;
+
+    use yaml_rust2::{
+        parser::{Event, MarkedEventReceiver},
+        scanner::Marker,
+    };
+
+    use super::{Position, Segment};
+}
+
+#[cfg(test)]
+mod tests {
+    const SOURCE: &str = "\
+- account: jdoe@example.com
+  message_filters:
+    - title: DigitalOcean
+      when:
+        - field: from
+          ends_with: ['@digitalocean.com']
+    - title: Github
+      then: []
+";
+
+    #[test]
+    fn test_locates_keys_and_items() {
+        let map = SourceMap::parse(SOURCE);
+
+        assert_eq!(
+            map.position(&path![0]),
+            Some(Position { line: 1, column: 3 })
+        );
+        assert_eq!(
+            map.position(&path![0, "message_filters", 1, "title"]),
+            Some(Position { line: 7, column: 7 })
+        );
+        assert_eq!(
+            map.position(&path![0, "message_filters", 0, "when", 0, "ends_with"]),
+            Some(Position {
+                line: 6,
+                column: 11
+            })
+        );
+    }
+
+    #[test]
+    fn test_falls_back_to_ancestor() {
+        let map = SourceMap::parse(SOURCE);
+
+        assert_eq!(
+            map.position(&path![0, "message_filters", 1, "then", 0]),
+            Some(Position { line: 8, column: 7 })
+        );
+    }
+
+    use super::*;
+}
+
+use std::collections::HashMap;
+
+use yaml_rust2::parser::Parser;
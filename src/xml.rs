@@ -1,33 +1,104 @@
+pub mod reader;
+
+#[derive(Default)]
 pub struct XmlDocument {
-    buff: String,
+    elements: Vec<XmlElement>,
 }
 
 impl XmlDocument {
     pub fn new() -> Self {
         XmlDocument {
-            buff: "<?xml version=\"1.0\"?>".to_owned(),
+            elements: Vec::new(),
         }
     }
 
     pub fn append_element(&mut self, child: XmlElement) {
-        helpers::xml_append(&mut self.buff, child.0);
+        self.elements.push(child);
+    }
+
+    /// Streams the document into `writer`, indenting nested elements when `pretty` is set.
+    pub fn write<W>(&self, writer: W, pretty: bool) -> io::Result<()>
+    where
+        W: io::Write,
+    {
+        let mut writer = XmlWriter::new(writer, pretty);
+        writer.write_declaration()?;
+        for element in &self.elements {
+            writer.write_element(element)?;
+        }
+        writer.finish()
+    }
+}
+
+impl std::fmt::Display for XmlDocument {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut buff = Vec::new();
+        self.write(&mut buff, false).map_err(|_| std::fmt::Error)?;
+        f.write_str(&String::from_utf8_lossy(&buff))
     }
+}
 
-    pub fn to_string(self) -> String {
-        self.buff
+impl std::fmt::Display for XmlElement {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut buff = Vec::new();
+        XmlWriter::new(&mut buff, false)
+            .write_element(self)
+            .map_err(|_| std::fmt::Error)?;
+        f.write_str(&String::from_utf8_lossy(&buff))
     }
 }
 
-pub struct XmlElement(String);
+#[derive(Clone, Debug, PartialEq)]
+pub struct XmlElement {
+    tag: String,
+    attrs: Vec<(String, String)>,
+    content: XmlContent,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum XmlContent {
+    Children(Vec<XmlElement>),
+    Text(String),
+}
 
 impl XmlElement {
-    pub fn new(tag: String, attrs: String, content: String) -> Self {
-        let xml = if !attrs.is_empty() {
-            format!("<{tag} {attrs}>{content}</{tag}>")
-        } else {
-            format!("<{tag}>{content}</{tag}>")
-        };
-        XmlElement(xml)
+    pub fn tag(&self) -> &str {
+        &self.tag
+    }
+
+    pub fn attr(&self, name: &str) -> Option<&str> {
+        self.attrs
+            .iter()
+            .find(|(attr, _)| attr == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Returns the child elements, empty for text elements.
+    pub fn children(&self) -> &[XmlElement] {
+        match &self.content {
+            XmlContent::Children(children) => children,
+            XmlContent::Text(_) => &[],
+        }
+    }
+
+    /// Returns the first child element named `tag`.
+    pub fn child(&self, tag: &str) -> Option<&XmlElement> {
+        self.children().iter().find(|child| child.tag == tag)
+    }
+
+    /// Returns the text content, empty for elements with children.
+    pub fn text(&self) -> &str {
+        match &self.content {
+            XmlContent::Children(_) => "",
+            XmlContent::Text(text) => text,
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        match &self.content {
+            XmlContent::Children(children) => children.is_empty(),
+            XmlContent::Text(text) => text.is_empty(),
+        }
     }
 }
 
@@ -56,7 +127,7 @@ impl XmlElementBuilder {
     where
         S: std::fmt::Display,
     {
-        self.attrs.push((name.to_owned(), format!("\"{value}\"")));
+        self.attrs.push((name.to_owned(), value.to_string()));
         self
     }
 
@@ -66,9 +137,11 @@ impl XmlElementBuilder {
     }
 
     pub fn build(self) -> XmlElement {
-        let attrs = helpers::format_attrs(self.attrs);
-        let children = helpers::format_children(self.children);
-        XmlElement::new(self.tag, attrs, children)
+        XmlElement {
+            tag: self.tag,
+            attrs: self.attrs,
+            content: XmlContent::Children(self.children),
+        }
     }
 }
 
@@ -81,14 +154,6 @@ impl XmlTextElementBuilder {
         }
     }
 
-    pub fn append_attr<S>(&mut self, name: &str, value: S) -> &mut Self
-    where
-        S: std::fmt::Display,
-    {
-        self.attrs.push((name.to_owned(), format!("\"{value}\"")));
-        self
-    }
-
     pub fn append_text<S>(&mut self, text: S) -> &mut Self
     where
         S: std::fmt::Display,
@@ -98,34 +163,159 @@ impl XmlTextElementBuilder {
     }
 
     pub fn build(self) -> XmlElement {
-        let attrs = helpers::format_attrs(self.attrs);
-        XmlElement::new(self.tag, attrs, self.text)
+        XmlElement {
+            tag: self.tag,
+            attrs: self.attrs,
+            content: XmlContent::Text(self.text),
+        }
+    }
+}
+
+/// Writes escaped XML into an [`io::Write`], optionally indenting nested elements.
+pub struct XmlWriter<W> {
+    writer: W,
+    pretty: bool,
+    depth: usize,
+}
+
+impl<W> XmlWriter<W>
+where
+    W: io::Write,
+{
+    pub fn new(writer: W, pretty: bool) -> Self {
+        XmlWriter {
+            writer,
+            pretty,
+            depth: 0,
+        }
+    }
+
+    pub fn write_declaration(&mut self) -> io::Result<()> {
+        write!(self.writer, "<?xml version=\"1.0\"?>")
+    }
+
+    pub fn write_element(&mut self, element: &XmlElement) -> io::Result<()> {
+        self.write_indent()?;
+        write!(self.writer, "<{}", element.tag)?;
+        for (name, value) in &element.attrs {
+            write!(self.writer, " {name}=\"{}\"", helpers::escape_attr(value))?;
+        }
+
+        if element.is_empty() {
+            return write!(self.writer, "/>");
+        }
+        write!(self.writer, ">")?;
+
+        match &element.content {
+            XmlContent::Text(text) => {
+                write!(self.writer, "{}", helpers::escape_text(text))?;
+            }
+            XmlContent::Children(children) => {
+                self.depth += 1;
+                for child in children {
+                    self.write_element(child)?;
+                }
+                self.depth -= 1;
+                self.write_indent()?;
+            }
+        }
+
+        write!(self.writer, "</{}>", element.tag)
+    }
+
+    pub fn finish(mut self) -> io::Result<()> {
+        if self.pretty {
+            writeln!(self.writer)?;
+        }
+        self.writer.flush()
+    }
+
+    fn write_indent(&mut self) -> io::Result<()> {
+        if self.pretty {
+            write!(self.writer, "\n{:indent$}", "", indent = self.depth * 2)?;
+        }
+        Ok(())
     }
 }
 
 mod helpers {
 
-    pub fn format_attrs(attrs: Vec<(String, String)>) -> String {
-        attrs
-            .into_iter()
-            .map(|(name, value)| format!("{name}={value}"))
-            .reduce(|lhs, rhs| format!("{lhs} {rhs}"))
-            .unwrap_or_default()
+    pub fn escape_text(text: &str) -> String {
+        escape(text, false)
     }
 
-    pub fn format_children(children: Vec<XmlElement>) -> String {
-        children
-            .into_iter()
-            .map(|child| child.0)
-            .fold(String::new(), |acc, item| format!("{}{}", acc, item))
+    pub fn escape_attr(value: &str) -> String {
+        escape(value, true)
     }
 
-    pub fn xml_append<S>(xml: &mut String, s: S)
-    where
-        S: std::fmt::Display,
-    {
-        xml.push_str(&s.to_string());
+    fn escape(value: &str, attr: bool) -> String {
+        let mut escaped = String::with_capacity(value.len());
+        for c in value.chars() {
+            match c {
+                '&' => escaped.push_str("&amp;"),
+                '<' => escaped.push_str("&lt;"),
+                '>' => escaped.push_str("&gt;"),
+                '"' if attr => escaped.push_str("&quot;"),
+                '\n' if attr => escaped.push_str("&#10;"),
+                '\r' if attr => escaped.push_str("&#13;"),
+                '\t' if attr => escaped.push_str("&#9;"),
+                c => escaped.push(c),
+            }
+        }
+        escaped
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    fn sample() -> XmlDocument {
+        let mut document = XmlDocument::new();
+        let mut rule = XmlElementBuilder::new("rule");
+        rule.append_attr("name", "say \"hi\" & <bye>");
+        rule.append_child({
+            let mut title = XmlTextElementBuilder::new("title");
+            title.append_text("R&D <alerts>");
+            title.build()
+        });
+        rule.append_child(XmlElementBuilder::new("partset").build());
+        document.append_element(rule.build());
+        document
+    }
+
+    #[test]
+    fn test_escapes_text_and_attrs() {
+        assert_eq!(
+            sample().to_string(),
+            [
+                "<?xml version=\"1.0\"?>",
+                "<rule name=\"say &quot;hi&quot; &amp; &lt;bye&gt;\">",
+                "<title>R&amp;D &lt;alerts&gt;</title>",
+                "<partset/>",
+                "</rule>",
+            ]
+            .join("")
+        );
+    }
+
+    #[test]
+    fn test_write_pretty() {
+        let mut buff = Vec::new();
+        sample().write(&mut buff, true).unwrap();
+        assert_eq!(
+            String::from_utf8(buff).unwrap(),
+            [
+                "<?xml version=\"1.0\"?>",
+                "<rule name=\"say &quot;hi&quot; &amp; &lt;bye&gt;\">",
+                "  <title>R&amp;D &lt;alerts&gt;</title>",
+                "  <partset/>",
+                "</rule>",
+                "",
+            ]
+            .join("\n")
+        );
     }
 
-    use super::XmlElement;
+    use super::*;
 }
+
+use std::io;
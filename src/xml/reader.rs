@@ -0,0 +1,334 @@
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum Error {
+    #[error("line {line}: expected {expected}")]
+    Expected { line: usize, expected: &'static str },
+    #[error("line {line}: `</{found}>` does not close `<{open}>`")]
+    MismatchedTag {
+        line: usize,
+        open: String,
+        found: String,
+    },
+    #[error("line {line}: unknown entity `&{entity};`")]
+    UnknownEntity { line: usize, entity: String },
+    #[error("line {line}: text mixed with elements in `<{tag}>`")]
+    MixedContent { line: usize, tag: String },
+}
+
+/// Parses a document with a single root element, the inverse of [`XmlDocument::write`].
+///
+/// This covers what mail clients write: the declaration, comments, CDATA sections and the
+/// predefined and numeric entities. Whitespace between child elements is dropped; elements
+/// without children keep their text verbatim.
+///
+/// [`XmlDocument::write`]: super::XmlDocument::write
+pub fn parse(source: &str) -> Result<XmlElement, Error> {
+    let mut reader = helpers::Reader {
+        source,
+        position: 0,
+    };
+
+    reader.skip_misc()?;
+    let root = reader.element()?;
+    reader.skip_misc()?;
+    if !reader.rest().is_empty() {
+        return Err(reader.expected("end of document"));
+    }
+
+    Ok(root)
+}
+
+mod helpers {
+    pub struct Reader<'a> {
+        pub source: &'a str,
+        pub position: usize,
+    }
+
+    impl<'a> Reader<'a> {
+        /// Reads an element starting at `<`, up to and including its end tag.
+        pub fn element(&mut self) -> Result<XmlElement, Error> {
+            if !self.eat("<") {
+                return Err(self.expected("`<`"));
+            }
+            let tag = self.name()?;
+
+            let mut attrs = Vec::new();
+            loop {
+                let had_space = self.skip_whitespace();
+                if self.eat("/>") {
+                    return Ok(XmlElement {
+                        tag,
+                        attrs,
+                        content: XmlContent::Children(Vec::new()),
+                    });
+                }
+                if self.eat(">") {
+                    break;
+                }
+                if !had_space {
+                    return Err(self.expected("whitespace, `>` or `/>`"));
+                }
+                let name = self.name()?;
+                self.skip_whitespace();
+                if !self.eat("=") {
+                    return Err(self.expected("`=`"));
+                }
+                self.skip_whitespace();
+                attrs.push((name, self.attr_value()?));
+            }
+
+            let start_line = self.line();
+            let mut children = Vec::new();
+            let mut text = String::new();
+            loop {
+                let rest = self.rest();
+                if rest.starts_with("</") {
+                    self.position += 2;
+                    let found = self.name()?;
+                    if found != tag {
+                        return Err(Error::MismatchedTag {
+                            line: self.line(),
+                            open: tag,
+                            found,
+                        });
+                    }
+                    self.skip_whitespace();
+                    if !self.eat(">") {
+                        return Err(self.expected("`>`"));
+                    }
+                    break;
+                } else if rest.starts_with("<!--") {
+                    self.comment()?;
+                } else if rest.starts_with("<![CDATA[") {
+                    self.position += "<![CDATA[".len();
+                    text.push_str(self.until("]]>", "`]]>`")?);
+                } else if rest.starts_with('<') {
+                    children.push(self.element()?);
+                } else if rest.starts_with('&') {
+                    text.push(self.entity()?);
+                } else if let Some(c) = rest.chars().next() {
+                    text.push(c);
+                    self.position += c.len_utf8();
+                } else {
+                    return Err(self.expected("an end tag"));
+                }
+            }
+
+            let content = match children.is_empty() {
+                true => XmlContent::Text(text),
+                false if text.trim().is_empty() => XmlContent::Children(children),
+                false => {
+                    return Err(Error::MixedContent {
+                        line: start_line,
+                        tag,
+                    })
+                }
+            };
+            Ok(XmlElement {
+                tag,
+                attrs,
+                content,
+            })
+        }
+
+        /// Skips whitespace, comments and processing instructions such as the declaration.
+        pub fn skip_misc(&mut self) -> Result<(), Error> {
+            loop {
+                self.skip_whitespace();
+                if self.rest().starts_with("<?") {
+                    self.until("?>", "`?>`")?;
+                } else if self.rest().starts_with("<!--") {
+                    self.comment()?;
+                } else {
+                    return Ok(());
+                }
+            }
+        }
+
+        fn comment(&mut self) -> Result<(), Error> {
+            self.position += "<!--".len();
+            self.until("-->", "`-->`").map(|_| ())
+        }
+
+        fn name(&mut self) -> Result<String, Error> {
+            let length = self
+                .rest()
+                .find(|c: char| !(c.is_alphanumeric() || matches!(c, '-' | '_' | '.' | ':')))
+                .unwrap_or(self.rest().len());
+            if length == 0 {
+                return Err(self.expected("a name"));
+            }
+            let name = self.rest()[..length].to_owned();
+            self.position += length;
+            Ok(name)
+        }
+
+        fn attr_value(&mut self) -> Result<String, Error> {
+            let quote = match self.rest().chars().next() {
+                Some(quote @ ('"' | '\'')) => quote,
+                _ => return Err(self.expected("a quoted value")),
+            };
+            self.position += 1;
+
+            let mut value = String::new();
+            loop {
+                match self.rest().chars().next() {
+                    Some(c) if c == quote => {
+                        self.position += 1;
+                        return Ok(value);
+                    }
+                    Some('&') => value.push(self.entity()?),
+                    Some(c) => {
+                        value.push(c);
+                        self.position += c.len_utf8();
+                    }
+                    None => return Err(self.expected("the closing quote")),
+                }
+            }
+        }
+
+        /// Reads an entity reference starting at `&`.
+        fn entity(&mut self) -> Result<char, Error> {
+            self.position += 1;
+            let entity = self.until(";", "`;`")?;
+            let c = match entity {
+                "amp" => Some('&'),
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                _ => match entity.strip_prefix("#x") {
+                    Some(hex) => u32::from_str_radix(hex, 16).ok(),
+                    None => entity
+                        .strip_prefix('#')
+                        .and_then(|decimal| decimal.parse().ok()),
+                }
+                .and_then(char::from_u32),
+            };
+            c.ok_or_else(|| Error::UnknownEntity {
+                line: self.line(),
+                entity: entity.to_owned(),
+            })
+        }
+
+        /// Returns the text up to `end` and moves past it.
+        fn until(&mut self, end: &str, expected: &'static str) -> Result<&'a str, Error> {
+            let source = self.source;
+            let Some(length) = self.rest().find(end) else {
+                return Err(self.expected(expected));
+            };
+            let text = &source[self.position..self.position + length];
+            self.position += length + end.len();
+            Ok(text)
+        }
+
+        fn eat(&mut self, token: &str) -> bool {
+            let matches = self.rest().starts_with(token);
+            if matches {
+                self.position += token.len();
+            }
+            matches
+        }
+
+        /// Returns whether any whitespace was skipped.
+        fn skip_whitespace(&mut self) -> bool {
+            let rest = self.rest();
+            let length = rest.len() - rest.trim_start().len();
+            self.position += length;
+            length > 0
+        }
+
+        pub fn rest(&self) -> &str {
+            &self.source[self.position..]
+        }
+
+        fn line(&self) -> usize {
+            self.source[..self.position].matches('\n').count() + 1
+        }
+
+        pub fn expected(&self, expected: &'static str) -> Error {
+            Error::Expected {
+                line: self.line(),
+                expected,
+            }
+        }
+    }
+
+    use super::Error;
+    use crate::xml::{XmlContent, XmlElement};
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_parse_round_trips_writer() {
+        let mut rule = XmlElementBuilder::new("rule");
+        rule.append_attr("name", "say \"hi\" & <bye>\nnow");
+        rule.append_child({
+            let mut title = XmlTextElementBuilder::new("title");
+            title.append_text("R&D <alerts> ✓");
+            title.build()
+        });
+        rule.append_child(XmlElementBuilder::new("partset").build());
+        let rule = rule.build();
+
+        let mut document = XmlDocument::new();
+        document.append_element(rule.clone());
+        for pretty in [false, true] {
+            let mut buff = Vec::new();
+            document.write(&mut buff, pretty).unwrap();
+            assert_eq!(parse(&String::from_utf8(buff).unwrap()), Ok(rule.clone()));
+        }
+    }
+
+    #[test]
+    fn test_parse_comments_cdata_and_entities() {
+        let root = parse(
+            "<?xml version='1.0' encoding='UTF-8'?>\n<!-- filters -->\n\
+             <a x='&#65;&#x42;'><!-- note --><b><![CDATA[<raw> & ]]>&apos;</b><c/></a>\n",
+        )
+        .unwrap();
+
+        assert_eq!(root.tag(), "a");
+        assert_eq!(root.attr("x"), Some("AB"));
+        assert_eq!(root.child("b").map(XmlElement::text), Some("<raw> & '"));
+        assert_eq!(root.children().len(), 2);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            parse("<a>\n<b></a>"),
+            Err(Error::MismatchedTag {
+                line: 2,
+                open: "b".to_owned(),
+                found: "a".to_owned(),
+            })
+        );
+        assert_eq!(
+            parse("<a>&nbsp;</a>"),
+            Err(Error::UnknownEntity {
+                line: 1,
+                entity: "nbsp".to_owned(),
+            })
+        );
+        assert_eq!(
+            parse("<a>text<b/></a>"),
+            Err(Error::MixedContent {
+                line: 1,
+                tag: "a".to_owned(),
+            })
+        );
+        assert_eq!(
+            parse("<a><b/>"),
+            Err(Error::Expected {
+                line: 1,
+                expected: "an end tag",
+            })
+        );
+    }
+
+    use super::*;
+    use crate::xml::{XmlDocument, XmlElementBuilder, XmlTextElementBuilder};
+}
+
+use crate::xml::XmlElement;